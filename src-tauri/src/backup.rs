@@ -0,0 +1,267 @@
+// Backup module
+// Verzovaná historie MCP konfigurace a hlasového nastavení
+// v ~/.config/Claude/backups/<cíl>/<ms>.json
+
+use crate::error::AppError;
+use crate::system::{now_millis, SystemOps};
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[cfg(test)]
+#[path = "backup_tests.rs"]
+mod tests;
+
+/// Kolik posledních verzí se uchovává pro každý soubor
+pub const MAX_SNAPSHOTS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupTarget {
+    /// claude_desktop_config.json
+    McpConfig,
+    /// voice/voice_settings.json
+    VoiceSettings,
+}
+
+impl BackupTarget {
+    fn dir_name(self) -> &'static str {
+        match self {
+            Self::McpConfig => "mcp_config",
+            Self::VoiceSettings => "voice_settings",
+        }
+    }
+
+    /// Cesta k zálohovanému souboru
+    async fn path(self, sys: &dyn SystemOps) -> Result<PathBuf, AppError> {
+        match self {
+            Self::McpConfig => crate::mcp::get_config_path(sys).await,
+            Self::VoiceSettings => crate::voice::get_settings_path(sys).await,
+        }
+    }
+}
+
+/// Jedna uložená verze
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Snapshot {
+    pub target: BackupTarget,
+    /// ID pro diff a obnovení (název souboru bez přípony)
+    pub id: String,
+    /// Kdy byla verze nahrazena, ms od epochy
+    pub timestamp: i64,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffTag {
+    Equal,
+    /// Řádek je jen v aktuálním souboru
+    Added,
+    /// Řádek je jen v záloze
+    Removed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffLine {
+    pub tag: DiffTag,
+    pub text: String,
+}
+
+/// Rozdíl zálohy proti aktuálnímu souboru
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SnapshotDiff {
+    pub snapshot: Snapshot,
+    pub identical: bool,
+    pub added: usize,
+    pub removed: usize,
+    pub lines: Vec<DiffLine>,
+    /// Stejný rozdíl ve formátu unified diff
+    pub unified: String,
+}
+
+async fn backups_dir(sys: &dyn SystemOps, target: BackupTarget) -> Result<PathBuf, AppError> {
+    sys.config_dir()
+        .ok_or(AppError::Config("Nelze najít config directory".to_string()))
+        .map(|d| d.join("Claude").join("backups").join(target.dir_name()))
+}
+
+/// ID jsou časová razítka, nic jiného do cesty nepustíme
+fn parse_id(id: &str) -> Result<i64, AppError> {
+    id.parse::<i64>()
+        .ok()
+        .filter(|ts| *ts >= 0)
+        .ok_or_else(|| AppError::Config(format!("Neplatné ID zálohy: {}", id)))
+}
+
+/// Všechny zálohy souboru, nejnovější první
+pub async fn list_snapshots(
+    sys: &Arc<dyn SystemOps>,
+    target: BackupTarget,
+) -> Result<Vec<Snapshot>, AppError> {
+    let dir = backups_dir(sys.as_ref(), target).await?;
+    let files = match sys.read_dir(&dir).await {
+        Ok(files) => files,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let mut snapshots = Vec::new();
+    for path in files
+        .iter()
+        .filter(|p| p.extension().is_some_and(|e| e == "json"))
+    {
+        let Some(id) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let Ok(timestamp) = parse_id(id) else {
+            continue;
+        };
+        let size = sys.read_to_string(path).await?.len() as u64;
+
+        snapshots.push(Snapshot {
+            target,
+            id: id.to_string(),
+            timestamp,
+            size,
+        });
+    }
+
+    snapshots.sort_by_key(|s| std::cmp::Reverse(s.timestamp));
+    Ok(snapshots)
+}
+
+/// Uloží aktuální verzi souboru před jeho přepsáním obsahem `replacement`.
+/// Nic nedělá, pokud soubor neexistuje, nemění se nebo se neliší od poslední
+/// zálohy. Starší verze nad `MAX_SNAPSHOTS` smaže.
+pub async fn snapshot(
+    sys: &Arc<dyn SystemOps>,
+    target: BackupTarget,
+    replacement: &str,
+) -> Result<Option<Snapshot>, AppError> {
+    let path = target.path(sys.as_ref()).await?;
+    if !sys.exists(&path).await {
+        return Ok(None);
+    }
+    let content = sys.read_to_string(&path).await?;
+    if content == replacement {
+        return Ok(None);
+    }
+
+    let dir = backups_dir(sys.as_ref(), target).await?;
+    let existing = list_snapshots(sys, target).await?;
+    if let Some(latest) = existing.first() {
+        let previous = sys
+            .read_to_string(&dir.join(format!("{}.json", latest.id)))
+            .await?;
+        if previous == content {
+            return Ok(None);
+        }
+    }
+
+    // Dvě zálohy ve stejné milisekundě nesmí kolidovat
+    let mut timestamp = now_millis();
+    if let Some(latest) = existing.first() {
+        timestamp = timestamp.max(latest.timestamp + 1);
+    }
+
+    let snapshot = Snapshot {
+        target,
+        id: timestamp.to_string(),
+        timestamp,
+        size: content.len() as u64,
+    };
+    sys.write_atomic(&dir.join(format!("{}.json", snapshot.id)), &content, false)
+        .await?;
+
+    for old in existing.iter().skip(MAX_SNAPSHOTS - 1) {
+        sys.remove_file(&dir.join(format!("{}.json", old.id)))
+            .await?;
+    }
+
+    log::info!("🗄️  Backed up {:?} as {}", target, snapshot.id);
+    Ok(Some(snapshot))
+}
+
+async fn read_snapshot(
+    sys: &Arc<dyn SystemOps>,
+    target: BackupTarget,
+    id: &str,
+) -> Result<(Snapshot, String), AppError> {
+    let timestamp = parse_id(id)?;
+    let path = backups_dir(sys.as_ref(), target)
+        .await?
+        .join(format!("{}.json", id));
+    if !sys.exists(&path).await {
+        return Err(AppError::Config(format!("Záloha {} neexistuje", id)));
+    }
+
+    let content = sys.read_to_string(&path).await?;
+    let snapshot = Snapshot {
+        target,
+        id: id.to_string(),
+        timestamp,
+        size: content.len() as u64,
+    };
+    Ok((snapshot, content))
+}
+
+/// Porovná zálohu s aktuálním souborem (chybějící soubor = prázdný)
+pub async fn diff_snapshot(
+    sys: &Arc<dyn SystemOps>,
+    target: BackupTarget,
+    id: &str,
+) -> Result<SnapshotDiff, AppError> {
+    let (snapshot, old) = read_snapshot(sys, target, id).await?;
+    let path = target.path(sys.as_ref()).await?;
+    let current = if sys.exists(&path).await {
+        sys.read_to_string(&path).await?
+    } else {
+        String::new()
+    };
+
+    let diff = TextDiff::from_lines(&old, &current);
+    let lines: Vec<DiffLine> = diff
+        .iter_all_changes()
+        .map(|change| DiffLine {
+            tag: match change.tag() {
+                ChangeTag::Equal => DiffTag::Equal,
+                ChangeTag::Insert => DiffTag::Added,
+                ChangeTag::Delete => DiffTag::Removed,
+            },
+            text: change.value().trim_end_matches('\n').to_string(),
+        })
+        .collect();
+
+    let count = |tag: DiffTag| lines.iter().filter(|l| l.tag == tag).count();
+    let unified = diff
+        .unified_diff()
+        .header(&format!("backup/{}", id), "current")
+        .to_string();
+
+    Ok(SnapshotDiff {
+        identical: old == current,
+        added: count(DiffTag::Added),
+        removed: count(DiffTag::Removed),
+        unified,
+        lines,
+        snapshot,
+    })
+}
+
+/// Obnoví soubor ze zálohy. Aktuální verze se nejdřív zazálohuje, takže
+/// obnovení jde vrátit.
+pub async fn restore_snapshot(
+    sys: &Arc<dyn SystemOps>,
+    target: BackupTarget,
+    id: &str,
+) -> Result<(), AppError> {
+    let (_, content) = read_snapshot(sys, target, id).await?;
+
+    snapshot(sys, target, &content).await?;
+    let path = target.path(sys.as_ref()).await?;
+    sys.write_atomic(&path, &content, true).await?;
+
+    log::info!("♻️  Restored {:?} from backup {}", target, id);
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use crate::backup::{
+    diff_snapshot, list_snapshots, restore_snapshot, BackupTarget, DiffTag, MAX_SNAPSHOTS,
+};
+use crate::mcp::save_config;
+use crate::mocks::MockSystemOps;
+use crate::system::SystemOps;
+use crate::voice::{save_voice_settings, VoiceSettings};
+use std::path::Path;
+use std::sync::Arc;
+
+const CONFIG_PATH: &str = "/home/mockuser/.config/Claude/claude_desktop_config.json";
+
+#[tokio::test]
+async fn test_save_keeps_previous_versions() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+
+    // First save has nothing to back up
+    save_config(&sys, "{\"v\": 1}").await.unwrap();
+    assert!(list_snapshots(&sys, BackupTarget::McpConfig)
+        .await
+        .unwrap()
+        .is_empty());
+
+    save_config(&sys, "{\"v\": 2}").await.unwrap();
+    // Saving the same content again doesn't add a duplicate
+    save_config(&sys, "{\"v\": 2}").await.unwrap();
+    save_config(&sys, "{\"v\": 3}").await.unwrap();
+
+    let snapshots = list_snapshots(&sys, BackupTarget::McpConfig).await.unwrap();
+    assert_eq!(snapshots.len(), 2);
+    assert!(snapshots[0].timestamp > snapshots[1].timestamp);
+    assert_eq!(snapshots[0].size, "{\"v\": 2}".len() as u64);
+
+    // Voice settings have their own history
+    let settings = VoiceSettings::default();
+    save_voice_settings(&sys, &settings).await.unwrap();
+    save_voice_settings(&sys, &settings).await.unwrap();
+    assert!(list_snapshots(&sys, BackupTarget::VoiceSettings)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_snapshots_are_capped() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+
+    for i in 0..MAX_SNAPSHOTS + 5 {
+        save_config(&sys, &format!("{{\"v\": {}}}", i))
+            .await
+            .unwrap();
+    }
+
+    let snapshots = list_snapshots(&sys, BackupTarget::McpConfig).await.unwrap();
+    assert_eq!(snapshots.len(), MAX_SNAPSHOTS);
+    // The newest versions survive; the last save is the current file
+    let newest = format!("{{\"v\": {}}}", MAX_SNAPSHOTS + 3);
+    let diff = diff_snapshot(&sys, BackupTarget::McpConfig, &snapshots[0].id)
+        .await
+        .unwrap();
+    assert!(diff
+        .lines
+        .iter()
+        .any(|l| l.tag == DiffTag::Removed && l.text == newest));
+}
+
+#[tokio::test]
+async fn test_diff_and_restore() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    save_config(&sys, "{\n  \"a\": 1,\n  \"b\": 2\n}\n")
+        .await
+        .unwrap();
+    save_config(&sys, "{\n  \"a\": 1,\n  \"c\": 3\n}\n")
+        .await
+        .unwrap();
+
+    let id = list_snapshots(&sys, BackupTarget::McpConfig).await.unwrap()[0]
+        .id
+        .clone();
+    let diff = diff_snapshot(&sys, BackupTarget::McpConfig, &id)
+        .await
+        .unwrap();
+    assert!(!diff.identical);
+    assert_eq!((diff.added, diff.removed), (1, 1));
+    assert!(diff
+        .lines
+        .iter()
+        .any(|l| l.tag == DiffTag::Removed && l.text == "  \"b\": 2"));
+    assert!(diff.unified.contains("+  \"c\": 3"));
+
+    restore_snapshot(&sys, BackupTarget::McpConfig, &id)
+        .await
+        .unwrap();
+    assert!(sys
+        .read_to_string(Path::new(CONFIG_PATH))
+        .await
+        .unwrap()
+        .contains("\"b\": 2"));
+
+    // The replaced version was backed up, so the restore can be undone
+    let snapshots = list_snapshots(&sys, BackupTarget::McpConfig).await.unwrap();
+    assert_eq!(snapshots.len(), 2);
+    let diff = diff_snapshot(&sys, BackupTarget::McpConfig, &snapshots[0].id)
+        .await
+        .unwrap();
+    assert!(diff.lines.iter().any(|l| l.text == "  \"c\": 3"));
+
+    assert!(restore_snapshot(&sys, BackupTarget::McpConfig, "../x")
+        .await
+        .is_err());
+    assert!(diff_snapshot(&sys, BackupTarget::McpConfig, "42")
+        .await
+        .is_err());
+}
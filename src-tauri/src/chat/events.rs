@@ -0,0 +1,185 @@
+// Parsování výstupu `claude -p --output-format stream-json`
+// Každý řádek je jeden JSON objekt, z něhož vzniká nula nebo více ChatEventů
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Spotřeba tokenů tak, jak ji CLI hlásí ve výsledku
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cache_creation_input_tokens: u64,
+    #[serde(default)]
+    pub cache_read_input_tokens: u64,
+}
+
+/// Typovaná událost chatu posílaná do webview
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChatEvent {
+    /// Začátek běhu, CLI přidělilo session
+    Init {
+        session_id: String,
+        model: Option<String>,
+    },
+    /// Průběžný kousek textu odpovědi
+    TextDelta { text: String },
+    /// Kompletní textový blok odpovědi
+    Text { text: String },
+    /// Claude volá nástroj
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    /// Výsledek volání nástroje
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        is_error: bool,
+    },
+    /// Konec běhu včetně spotřeby
+    Result {
+        session_id: Option<String>,
+        is_error: bool,
+        result: Option<String>,
+        duration_ms: Option<u64>,
+        num_turns: Option<u32>,
+        total_cost_usd: Option<f64>,
+        usage: Option<Usage>,
+    },
+}
+
+impl ChatEvent {
+    /// Jméno Tauri eventu, pod kterým se událost posílá do webview
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            ChatEvent::Init { .. } => "chat-init",
+            ChatEvent::TextDelta { .. } => "chat-text-delta",
+            ChatEvent::Text { .. } => "chat-text",
+            ChatEvent::ToolUse { .. } => "chat-tool-use",
+            ChatEvent::ToolResult { .. } => "chat-tool-result",
+            ChatEvent::Result { .. } => "chat-result",
+        }
+    }
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+/// Obsah tool_result může být string nebo pole textových bloků
+fn tool_result_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Some(Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn content_blocks(line: &Value) -> &[Value] {
+    line.get("message")
+        .and_then(|m| m.get("content"))
+        .and_then(|c| c.as_array())
+        .map(|blocks| blocks.as_slice())
+        .unwrap_or(&[])
+}
+
+/// Převede jeden řádek stream-json výstupu na události.
+/// Neznámé typy zpráv se ignorují, aby novější CLI nerozbilo aplikaci.
+pub fn parse_line(line: &str) -> Result<Vec<ChatEvent>, serde_json::Error> {
+    let value: Value = serde_json::from_str(line)?;
+    let mut events = Vec::new();
+
+    match value.get("type").and_then(|t| t.as_str()) {
+        Some("system") if value.get("subtype").and_then(|s| s.as_str()) == Some("init") => {
+            if let Some(session_id) = str_field(&value, "session_id") {
+                events.push(ChatEvent::Init {
+                    session_id,
+                    model: str_field(&value, "model"),
+                });
+            }
+        }
+        Some("stream_event") => {
+            let delta = value
+                .get("event")
+                .filter(|e| e.get("type").and_then(|t| t.as_str()) == Some("content_block_delta"))
+                .and_then(|e| e.get("delta"));
+
+            if let Some(delta) = delta {
+                if delta.get("type").and_then(|t| t.as_str()) == Some("text_delta") {
+                    if let Some(text) = str_field(delta, "text") {
+                        events.push(ChatEvent::TextDelta { text });
+                    }
+                }
+            }
+        }
+        Some("assistant") => {
+            for block in content_blocks(&value) {
+                match block.get("type").and_then(|t| t.as_str()) {
+                    Some("text") => {
+                        if let Some(text) = str_field(block, "text") {
+                            events.push(ChatEvent::Text { text });
+                        }
+                    }
+                    Some("tool_use") => events.push(ChatEvent::ToolUse {
+                        id: str_field(block, "id").unwrap_or_default(),
+                        name: str_field(block, "name").unwrap_or_default(),
+                        input: block.get("input").cloned().unwrap_or(Value::Null),
+                    }),
+                    _ => {}
+                }
+            }
+        }
+        Some("user") => {
+            for block in content_blocks(&value) {
+                if block.get("type").and_then(|t| t.as_str()) == Some("tool_result") {
+                    events.push(ChatEvent::ToolResult {
+                        tool_use_id: str_field(block, "tool_use_id").unwrap_or_default(),
+                        content: tool_result_text(block.get("content")),
+                        is_error: block
+                            .get("is_error")
+                            .and_then(|v| v.as_bool())
+                            .unwrap_or(false),
+                    });
+                }
+            }
+        }
+        Some("result") => events.push(ChatEvent::Result {
+            session_id: str_field(&value, "session_id"),
+            is_error: value
+                .get("is_error")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            result: str_field(&value, "result"),
+            duration_ms: value.get("duration_ms").and_then(|v| v.as_u64()),
+            num_turns: value
+                .get("num_turns")
+                .and_then(|v| v.as_u64())
+                .map(|n| n as u32),
+            // Starší verze CLI posílaly `cost_usd`
+            total_cost_usd: value
+                .get("total_cost_usd")
+                .or_else(|| value.get("cost_usd"))
+                .and_then(|v| v.as_f64()),
+            usage: value
+                .get("usage")
+                .and_then(|u| serde_json::from_value(u.clone()).ok()),
+        }),
+        _ => {}
+    }
+
+    Ok(events)
+}
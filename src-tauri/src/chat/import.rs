@@ -0,0 +1,163 @@
+// Import transkriptů Claude CLI do historie konverzací aplikace
+
+use crate::chat::events::ChatEvent;
+use crate::chat::sessions;
+use crate::chat::transcript::{self, Role, Transcript};
+use crate::error::AppError;
+use crate::system::SystemOps;
+use crate::voice::{self, ConversationEntry, ConversationStore, ThreadStore, ToolCall};
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Průběh importu posílaný jako `cli-import-progress`
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportProgress {
+    pub session_id: String,
+    pub processed: usize,
+    pub total: usize,
+}
+
+/// Výsledek importu
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ImportReport {
+    pub sessions: usize,
+    pub found: usize,
+    pub imported: usize,
+    pub duplicates: usize,
+}
+
+/// Rozpracovaná dvojice dotaz/odpověď
+struct Turn {
+    user_input: String,
+    timestamp: Option<i64>,
+    message_id: Option<String>,
+    response: Vec<String>,
+    tool_calls: Vec<ToolCall>,
+}
+
+impl Turn {
+    fn into_entry(self) -> Option<ConversationEntry> {
+        // Bez odpovědi asistenta není co importovat
+        let id = self.message_id?;
+
+        Some(ConversationEntry {
+            id,
+            timestamp: self.timestamp.unwrap_or_default(),
+            user_input: self.user_input,
+            assistant_response: self.response.join("\n\n"),
+            voice_used: false,
+            played_back: false,
+            starred: false,
+            tool_calls: self.tool_calls,
+        })
+    }
+}
+
+/// Převede transkript na záznamy historie. Každý dotaz uživatele tvoří jeden záznam,
+/// jehož ID je ID první odpovědi asistenta.
+pub fn transcript_to_entries(transcript: &Transcript) -> Vec<ConversationEntry> {
+    let mut entries = Vec::new();
+    let mut turn: Option<Turn> = None;
+
+    for message in &transcript.messages {
+        let starts_turn = message.role == Role::User && !message.text.trim().is_empty();
+
+        if starts_turn {
+            if let Some(entry) = turn.take().and_then(Turn::into_entry) {
+                entries.push(entry);
+            }
+            turn = Some(Turn {
+                user_input: message.text.clone(),
+                timestamp: message.timestamp,
+                message_id: None,
+                response: Vec::new(),
+                tool_calls: Vec::new(),
+            });
+            continue;
+        }
+
+        // Odpovědi před prvním dotazem (např. po /compact) se přeskočí
+        let Some(current) = turn.as_mut() else {
+            continue;
+        };
+
+        if message.role == Role::Assistant {
+            if current.message_id.is_none() {
+                current.message_id = message.message_id.clone().or(message.uuid.clone());
+            }
+            if current.timestamp.is_none() {
+                current.timestamp = message.timestamp;
+            }
+            if !message.text.trim().is_empty() {
+                current.response.push(message.text.clone());
+            }
+        }
+
+        for event in &message.tool_events {
+            match event {
+                ChatEvent::ToolUse { id, name, input } => current.tool_calls.push(ToolCall {
+                    id: id.clone(),
+                    name: name.clone(),
+                    input: input.clone(),
+                    result: None,
+                    is_error: false,
+                }),
+                ChatEvent::ToolResult {
+                    tool_use_id,
+                    content,
+                    is_error,
+                } => {
+                    if let Some(call) = current.tool_calls.iter_mut().find(|c| c.id == *tool_use_id)
+                    {
+                        call.result = Some(content.clone());
+                        call.is_error = *is_error;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if let Some(entry) = turn.and_then(Turn::into_entry) {
+        entries.push(entry);
+    }
+
+    entries
+}
+
+/// Naimportuje transkripty CLI (volitelně jen jednoho projektu) do historie.
+/// Záznamy se stejným ID zprávy, které už v historii jsou, se přeskočí.
+pub async fn import_cli_history(
+    sys: &Arc<dyn SystemOps>,
+    store: &dyn ConversationStore,
+    threads: &ThreadStore,
+    project_cwd: Option<&str>,
+    mut on_progress: impl FnMut(ImportProgress),
+) -> Result<ImportReport, AppError> {
+    let files = sessions::transcript_files(sys.as_ref(), project_cwd).await?;
+    let total = files.len();
+    let mut entries = Vec::new();
+
+    for (processed, (_project, session_id, path)) in files.into_iter().enumerate() {
+        let content = sys.read_to_string(&path).await?;
+        let transcript = transcript::parse_transcript(&session_id, &content);
+        entries.extend(transcript_to_entries(&transcript));
+
+        on_progress(ImportProgress {
+            session_id,
+            processed: processed + 1,
+            total,
+        });
+    }
+
+    let found = entries.len();
+    // Co se nevložilo, už v historii bylo (nebo se v dávce opakovalo)
+    let imported = voice::import_conversations(store, threads, entries).await?;
+
+    Ok(ImportReport {
+        sessions: total,
+        found,
+        imported,
+        duplicates: found - imported,
+    })
+}
@@ -0,0 +1,382 @@
+// Chat module
+// Vlastní chat nad `claude -p --output-format stream-json`
+
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::system::{now_millis, SystemOps};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Stdio;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::oneshot;
+
+pub mod events;
+pub mod import;
+pub mod sessions;
+pub mod transcript;
+pub mod usage;
+
+pub use events::{ChatEvent, Usage};
+pub use sessions::CliSession;
+
+#[cfg(test)]
+#[path = "tests.rs"]
+mod tests;
+
+/// Požadavek na jeden běh chatu z frontendu
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatRequest {
+    pub prompt: String,
+    /// Pracovní adresář, ve kterém CLI poběží (projekt)
+    #[serde(default)]
+    pub cwd: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Konverzace v aplikaci, ke které běh patří (pro navázání na její session)
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    /// Obnovit konkrétní session CLI (`--resume`)
+    #[serde(default)]
+    pub resume: Option<String>,
+    /// Pokračovat v poslední session v `cwd` (`--continue`)
+    #[serde(default)]
+    pub continue_last: bool,
+}
+
+/// Jak běh skončil
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatOutcome {
+    Completed,
+    Cancelled,
+}
+
+/// Payload typovaných chat eventů - každá událost nese ID běhu
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatEventPayload {
+    pub chat_id: String,
+    #[serde(flatten)]
+    pub event: ChatEvent,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatFinished {
+    pub chat_id: String,
+    pub conversation_id: Option<String>,
+    pub session_id: Option<String>,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatFailed {
+    pub chat_id: String,
+    pub message: String,
+}
+
+/// Cesta ke Claude CLI (lze přepsat přes `CLAUDE_CLI`)
+pub fn cli_program(sys: &dyn SystemOps) -> String {
+    sys.env_var("CLAUDE_CLI")
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| "claude".to_string())
+}
+
+/// Argumenty pro print mód se stream-json výstupem (prompt jde přes stdin)
+pub fn build_args(request: &ChatRequest) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "-p",
+        "--output-format",
+        "stream-json",
+        "--verbose",
+        "--include-partial-messages",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+
+    if let Some(model) = &request.model {
+        args.push("--model".to_string());
+        args.push(model.clone());
+    }
+
+    if let Some(session_id) = &request.resume {
+        args.push("--resume".to_string());
+        args.push(session_id.clone());
+    } else if request.continue_last {
+        args.push("--continue".to_string());
+    }
+
+    args
+}
+
+/// Spustí CLI, průběžně parsuje jeho výstup a předává události do `on_event`.
+/// Proces lze zrušit přes `cancel`, v tom případě se zabije.
+pub async fn run(
+    program: &str,
+    request: &ChatRequest,
+    mut on_event: impl FnMut(ChatEvent),
+    mut cancel: oneshot::Receiver<()>,
+) -> Result<ChatOutcome, AppError> {
+    let mut command = Command::new(program);
+    command
+        .args(build_args(request))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    if let Some(cwd) = &request.cwd {
+        command.current_dir(cwd);
+    }
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| AppError::Chat(format!("Nelze spustit {}: {}", program, e)))?;
+
+    // Prompt zapisujeme zvlášť, aby se zápis a čtení výstupu navzájem neblokovaly
+    if let Some(mut stdin) = child.stdin.take() {
+        let prompt = request.prompt.clone();
+        tokio::spawn(async move {
+            if let Err(e) = stdin.write_all(prompt.as_bytes()).await {
+                log::warn!("⚠️  Zápis promptu do CLI selhal: {}", e);
+            }
+        });
+    }
+
+    let mut stderr = child.stderr.take();
+    let stderr_task = tokio::spawn(async move {
+        let mut buf = String::new();
+        if let Some(stderr) = stderr.as_mut() {
+            let _ = stderr.read_to_string(&mut buf).await;
+        }
+        buf
+    });
+
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| AppError::Chat("CLI nemá stdout".to_string()))?;
+    let mut lines = BufReader::new(stdout).lines();
+    let mut got_result = false;
+
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else { break };
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                match events::parse_line(&line) {
+                    Ok(events) => {
+                        for event in events {
+                            got_result |= matches!(event, ChatEvent::Result { .. });
+                            on_event(event);
+                        }
+                    }
+                    Err(e) => log::warn!("⚠️  Neplatný řádek stream-json: {}", e),
+                }
+            }
+            _ = &mut cancel => {
+                child.kill().await?;
+                log::info!("🛑 Chat cancelled");
+                return Ok(ChatOutcome::Cancelled);
+            }
+        }
+    }
+
+    let status = child.wait().await?;
+    let stderr = stderr_task.await.unwrap_or_default();
+
+    if !status.success() && !got_result {
+        return Err(AppError::Chat(format!(
+            "CLI skončilo s chybou ({}): {}",
+            status,
+            stderr.trim()
+        )));
+    }
+
+    Ok(ChatOutcome::Completed)
+}
+
+/// Mapa session konverzací; při prvním použití se načte z disku
+async fn loaded_sessions(
+    state: &AppState,
+) -> tokio::sync::RwLockWriteGuard<'_, Option<HashMap<String, String>>> {
+    let mut guard = state.chat_sessions.write().await;
+    if guard.is_none() {
+        let stored = sessions::load_conversation_sessions(state.sys.as_ref())
+            .await
+            .unwrap_or_else(|e| {
+                log::warn!("⚠️  Nelze načíst session konverzací: {}", e);
+                HashMap::new()
+            });
+        *guard = Some(stored);
+    }
+    guard
+}
+
+/// Session CLI naposledy použitá v dané konverzaci
+pub async fn conversation_session(state: &AppState, conversation_id: &str) -> Option<String> {
+    loaded_sessions(state)
+        .await
+        .as_ref()
+        .and_then(|sessions| sessions.get(conversation_id).cloned())
+}
+
+/// Zapamatuje si session konverzace, i pro další spuštění aplikace
+pub async fn remember_session(
+    state: &AppState,
+    conversation_id: &str,
+    session_id: &str,
+) -> Result<(), AppError> {
+    let mut guard = loaded_sessions(state).await;
+    let sessions = guard.get_or_insert_with(HashMap::new);
+    if sessions.get(conversation_id).map(String::as_str) == Some(session_id) {
+        return Ok(());
+    }
+    sessions.insert(conversation_id.to_string(), session_id.to_string());
+    sessions::save_conversation_sessions(state.sys.as_ref(), sessions).await
+}
+
+/// Spustí chat na pozadí a vrátí jeho ID. Události chodí jako `chat-*` eventy.
+/// Pokud konverzace už má session, naváže se na ni přes `--resume`.
+pub async fn start<R: Runtime>(
+    app: AppHandle<R>,
+    mut request: ChatRequest,
+) -> Result<String, AppError> {
+    let chat_id = uuid::Uuid::new_v4().to_string();
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+
+    let state = app.state::<AppState>();
+    if request.resume.is_none() && !request.continue_last {
+        if let Some(conversation_id) = &request.conversation_id {
+            request.resume = conversation_session(&state, conversation_id).await;
+        }
+    }
+
+    state.chats.write().await.insert(chat_id.clone(), cancel_tx);
+
+    let id = chat_id.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let program = cli_program(state.sys.as_ref());
+        let mut session_id = request.resume.clone();
+        let mut model = request.model.clone();
+        let mut reported: Option<(Usage, Option<f64>)> = None;
+
+        let result = run(
+            &program,
+            &request,
+            |event| {
+                match &event {
+                    ChatEvent::Init {
+                        session_id: id,
+                        model: init_model,
+                    } => {
+                        session_id = Some(id.clone());
+                        if init_model.is_some() {
+                            model = init_model.clone();
+                        }
+                    }
+                    ChatEvent::Result {
+                        session_id: id,
+                        usage,
+                        total_cost_usd,
+                        ..
+                    } => {
+                        if id.is_some() {
+                            session_id = id.clone();
+                        }
+                        if let Some(usage) = usage {
+                            reported = Some((usage.clone(), *total_cost_usd));
+                        }
+                    }
+                    _ => {}
+                }
+
+                let payload = ChatEventPayload {
+                    chat_id: id.clone(),
+                    event,
+                };
+                if let Err(e) = app.emit(payload.event.event_name(), &payload) {
+                    log::warn!("⚠️  Nelze odeslat chat event: {}", e);
+                }
+            },
+            cancel_rx,
+        )
+        .await;
+
+        state.chats.write().await.remove(&id);
+
+        if let (Some(conversation_id), Some(session_id)) = (&request.conversation_id, &session_id) {
+            if let Err(e) = remember_session(&state, conversation_id, session_id).await {
+                log::warn!("⚠️  Nelze uložit session konverzace: {}", e);
+            }
+        }
+
+        if let Some((usage, cost_usd)) = reported {
+            let timestamp = now_millis();
+            let record = usage::UsageRecord {
+                timestamp,
+                day: usage::day_of(timestamp),
+                conversation_id: request.conversation_id.clone(),
+                session_id: session_id.clone(),
+                model,
+                project: request.cwd.clone(),
+                usage,
+                cost_usd,
+            };
+
+            match usage::record(&state.sys, &state.usage_lock, record).await {
+                Ok(Some(exceeded)) => {
+                    log::warn!("💸 Daily budget exceeded: {:.2} USD", exceeded.spent_usd);
+                    if let Err(e) = app.emit("usage-budget-exceeded", &exceeded) {
+                        log::warn!("⚠️  Nelze odeslat varování o rozpočtu: {}", e);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!("⚠️  Nelze uložit spotřebu: {}", e),
+            }
+        }
+
+        let emitted = match result {
+            Ok(outcome) => app.emit(
+                "chat-finished",
+                ChatFinished {
+                    chat_id: id.clone(),
+                    conversation_id: request.conversation_id.clone(),
+                    session_id: session_id.clone(),
+                    cancelled: outcome == ChatOutcome::Cancelled,
+                },
+            ),
+            Err(e) => {
+                log::error!("❌ Chat {} failed: {}", id, e);
+                app.emit(
+                    "chat-error",
+                    ChatFailed {
+                        chat_id: id.clone(),
+                        message: e.to_string(),
+                    },
+                )
+            }
+        };
+
+        if let Err(e) = emitted {
+            log::warn!("⚠️  Nelze odeslat konec chatu: {}", e);
+        }
+    });
+
+    Ok(chat_id)
+}
+
+/// Zruší běžící chat. Vrací false, pokud už neběží.
+pub async fn cancel(state: &AppState, chat_id: &str) -> Result<bool, AppError> {
+    let Some(cancel_tx) = state.chats.write().await.remove(chat_id) else {
+        return Ok(false);
+    };
+
+    // Pokud úloha mezitím skončila, receiver už neexistuje - to nevadí
+    let _ = cancel_tx.send(());
+    Ok(true)
+}
@@ -0,0 +1,166 @@
+// Přehled existujících sessions Claude CLI
+
+use crate::chat::transcript::{self, Role};
+use crate::error::AppError;
+use crate::schema::{self, Schema};
+use crate::system::SystemOps;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Formát `chat/sessions.json`
+pub const CONVERSATION_SESSIONS_SCHEMA: Schema = Schema {
+    name: "sessions.json",
+    migrations: &[schema::add_version],
+};
+
+/// Session CLI naposledy použitá v každé konverzaci aplikace
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ConversationSessions {
+    /// ID konverzace -> session_id
+    #[serde(default)]
+    sessions: HashMap<String, String>,
+}
+
+/// Session nalezená v adresáři transkriptů CLI
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CliSession {
+    pub session_id: String,
+    /// Jméno adresáře projektu v ~/.claude/projects
+    pub project: String,
+    pub cwd: Option<String>,
+    pub summary: Option<String>,
+    pub first_prompt: Option<String>,
+    pub message_count: usize,
+    pub started_at: Option<i64>,
+    pub updated_at: Option<i64>,
+}
+
+async fn conversation_sessions_path(sys: &dyn SystemOps) -> Result<PathBuf, AppError> {
+    let chat_dir = sys
+        .config_dir()
+        .ok_or(AppError::Config("Cannot find config directory".to_string()))?
+        .join("Claude")
+        .join("chat");
+
+    if !sys.exists(&chat_dir).await {
+        sys.create_dir_all(&chat_dir).await?;
+    }
+
+    Ok(chat_dir.join("sessions.json"))
+}
+
+/// Načte uložené session konverzací, aby `--resume` fungovalo i po restartu
+pub async fn load_conversation_sessions(
+    sys: &dyn SystemOps,
+) -> Result<HashMap<String, String>, AppError> {
+    let path = conversation_sessions_path(sys).await?;
+    let stored: Option<ConversationSessions> =
+        schema::load(sys, &CONVERSATION_SESSIONS_SCHEMA, &path).await?;
+    Ok(stored.unwrap_or_default().sessions)
+}
+
+pub async fn save_conversation_sessions(
+    sys: &dyn SystemOps,
+    sessions: &HashMap<String, String>,
+) -> Result<(), AppError> {
+    let path = conversation_sessions_path(sys).await?;
+    let json = CONVERSATION_SESSIONS_SCHEMA.to_string_pretty(&ConversationSessions {
+        sessions: sessions.clone(),
+    })?;
+    sys.write_atomic(&path, &json, false).await
+}
+
+/// Adresář, kam CLI ukládá transkripty
+pub fn projects_dir(sys: &dyn SystemOps) -> Result<PathBuf, AppError> {
+    sys.home_dir()
+        .map(|home| home.join(".claude").join("projects"))
+        .ok_or(AppError::Config("Nelze najít home directory".to_string()))
+}
+
+/// Stejné kódování cesty projektu, jaké používá CLI (`/home/u/app` -> `-home-u-app`)
+pub fn encode_project_dir(cwd: &str) -> String {
+    cwd.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// Všechny transkripty `(projekt, session_id, cesta)`, volitelně jen pro jeden projekt
+pub async fn transcript_files(
+    sys: &dyn SystemOps,
+    project_cwd: Option<&str>,
+) -> Result<Vec<(String, String, PathBuf)>, AppError> {
+    let root = projects_dir(sys)?;
+    if !sys.exists(&root).await {
+        return Ok(Vec::new());
+    }
+
+    let wanted = project_cwd.map(encode_project_dir);
+    let mut files = Vec::new();
+
+    for project_dir in sys.read_dir(&root).await? {
+        let Some(project) = project_dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+        else {
+            continue;
+        };
+        if wanted.as_ref().is_some_and(|w| *w != project) {
+            continue;
+        }
+
+        // Položky, které nejsou adresáře, se prostě nepodaří vypsat
+        let Ok(entries) = sys.read_dir(&project_dir).await else {
+            continue;
+        };
+
+        for path in entries {
+            if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+                continue;
+            }
+            if let Some(session_id) = path.file_stem().map(|s| s.to_string_lossy().to_string()) {
+                files.push((project.clone(), session_id, path));
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Vypíše sessions CLI, nejnovější první
+pub async fn list_sessions(
+    sys: &dyn SystemOps,
+    project_cwd: Option<&str>,
+) -> Result<Vec<CliSession>, AppError> {
+    let mut sessions = Vec::new();
+
+    for (project, session_id, path) in transcript_files(sys, project_cwd).await? {
+        let content = sys.read_to_string(&path).await?;
+        let transcript = transcript::parse_transcript(&session_id, &content);
+
+        // Prázdné transkripty (např. jen summary) nemá smysl nabízet k obnovení
+        if transcript.messages.is_empty() {
+            continue;
+        }
+
+        let timestamps = transcript.messages.iter().filter_map(|m| m.timestamp);
+        sessions.push(CliSession {
+            session_id,
+            project,
+            cwd: transcript.messages.iter().find_map(|m| m.cwd.clone()),
+            summary: transcript.summary.clone(),
+            first_prompt: transcript
+                .messages
+                .iter()
+                .find(|m| m.role == Role::User && !m.text.trim().is_empty())
+                .map(|m| m.text.clone()),
+            message_count: transcript.messages.len(),
+            started_at: timestamps.clone().min(),
+            updated_at: timestamps.max(),
+        });
+    }
+
+    sessions.sort_by_key(|s| Reverse(s.updated_at));
+    Ok(sessions)
+}
@@ -0,0 +1,484 @@
+use crate::chat::events::parse_line;
+use crate::chat::import::{import_cli_history, transcript_to_entries};
+use crate::chat::sessions::{encode_project_dir, list_sessions};
+use crate::chat::transcript::{parse_timestamp, parse_transcript, Role};
+use crate::chat::usage::{
+    aggregate, day_of, load_records, record, save_budget, UsageBudget, UsageGroup, UsageRecord,
+};
+use crate::chat::{
+    build_args, conversation_session, remember_session, run, ChatEvent, ChatOutcome, ChatRequest,
+    Usage,
+};
+use crate::error::AppError;
+use crate::mocks::MockSystemOps;
+use crate::state::AppState;
+use crate::system::SystemOps;
+use crate::voice::threads::ThreadStore;
+use crate::voice::{load_conversations, ConversationStore, LogConversationStore};
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, Mutex};
+
+const INIT: &str = r#"{"type":"system","subtype":"init","session_id":"sess-1","model":"claude-sonnet-4-5","tools":[]}"#;
+const DELTA: &str = r#"{"type":"stream_event","event":{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Ahoj"}},"session_id":"sess-1"}"#;
+const ASSISTANT: &str = r#"{"type":"assistant","message":{"id":"msg_1","content":[{"type":"text","text":"Ahoj"},{"type":"tool_use","id":"tool_1","name":"Read","input":{"file_path":"/tmp/a"}}]},"session_id":"sess-1"}"#;
+const TOOL_RESULT: &str = r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"tool_1","content":[{"type":"text","text":"obsah"}],"is_error":false}]},"session_id":"sess-1"}"#;
+const RESULT: &str = r#"{"type":"result","subtype":"success","is_error":false,"duration_ms":1200,"num_turns":2,"result":"Ahoj","session_id":"sess-1","total_cost_usd":0.0125,"usage":{"input_tokens":10,"output_tokens":5,"cache_read_input_tokens":100}}"#;
+
+const TRANSCRIPT: &str = r#"{"type":"summary","summary":"Oprava buildu","leafUuid":"u3"}
+{"type":"user","uuid":"u1","sessionId":"sess-1","cwd":"/home/mockuser/app","timestamp":"2025-06-01T10:00:00.000Z","message":{"role":"user","content":"Proč padá build?"}}
+{"type":"user","uuid":"m1","isMeta":true,"timestamp":"2025-06-01T10:00:00.500Z","message":{"role":"user","content":"<command-name>/clear</command-name>"}}
+{"type":"assistant","uuid":"u2","sessionId":"sess-1","timestamp":"2025-06-01T10:00:05.000Z","message":{"id":"msg_1","model":"claude-sonnet-4-5","content":[{"type":"text","text":"Podívám se."},{"type":"tool_use","id":"tool_1","name":"Bash","input":{"command":"cargo build"}}]}}
+{"type":"user","uuid":"u3","sessionId":"sess-1","timestamp":"2025-06-01T10:00:09.000Z","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"tool_1","content":"error[E0433]"}]}}
+{"broken json"#;
+
+/// Vytvoří falešné `claude` CLI, které vypíše připravené řádky
+fn fake_cli(dir: &tempfile::TempDir, body: &str) -> PathBuf {
+    let path = dir.path().join("claude");
+    std::fs::write(&path, format!("#!/bin/sh\ncat > /dev/null\n{}\n", body)).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+fn canned(lines: &[&str]) -> String {
+    format!("cat <<'EOF'\n{}\nEOF", lines.join("\n"))
+}
+
+fn request() -> ChatRequest {
+    ChatRequest {
+        prompt: "Ahoj".to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_parse_assistant_blocks() {
+    let events = parse_line(ASSISTANT).unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        events[0],
+        ChatEvent::Text {
+            text: "Ahoj".to_string()
+        }
+    );
+    match &events[1] {
+        ChatEvent::ToolUse { id, name, input } => {
+            assert_eq!(id, "tool_1");
+            assert_eq!(name, "Read");
+            assert_eq!(input["file_path"], "/tmp/a");
+        }
+        other => panic!("Unexpected event: {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_tool_result_and_result() {
+    assert_eq!(
+        parse_line(TOOL_RESULT).unwrap(),
+        vec![ChatEvent::ToolResult {
+            tool_use_id: "tool_1".to_string(),
+            content: "obsah".to_string(),
+            is_error: false,
+        }]
+    );
+
+    match &parse_line(RESULT).unwrap()[0] {
+        ChatEvent::Result {
+            session_id,
+            total_cost_usd,
+            usage,
+            ..
+        } => {
+            assert_eq!(session_id.as_deref(), Some("sess-1"));
+            assert_eq!(*total_cost_usd, Some(0.0125));
+            assert_eq!(
+                usage.clone().unwrap(),
+                Usage {
+                    input_tokens: 10,
+                    output_tokens: 5,
+                    cache_creation_input_tokens: 0,
+                    cache_read_input_tokens: 100,
+                }
+            );
+        }
+        other => panic!("Unexpected event: {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_ignores_unknown_types() {
+    assert!(parse_line(r#"{"type":"something_new"}"#)
+        .unwrap()
+        .is_empty());
+    assert!(parse_line("not json").is_err());
+}
+
+#[test]
+fn test_build_args() {
+    let args = build_args(&ChatRequest {
+        model: Some("opus".to_string()),
+        ..request()
+    });
+    assert_eq!(args[0], "-p");
+    assert!(args
+        .windows(2)
+        .any(|w| w == ["--output-format", "stream-json"]));
+    assert!(args.windows(2).any(|w| w == ["--model", "opus"]));
+}
+
+#[tokio::test]
+async fn test_run_streams_events() {
+    let dir = tempfile::tempdir().unwrap();
+    let cli = fake_cli(
+        &dir,
+        &canned(&[INIT, DELTA, ASSISTANT, TOOL_RESULT, "", RESULT]),
+    );
+    let (_cancel_tx, cancel_rx) = oneshot::channel();
+
+    let mut events = Vec::new();
+    let outcome = run(
+        cli.to_str().unwrap(),
+        &request(),
+        |e| events.push(e),
+        cancel_rx,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(outcome, ChatOutcome::Completed);
+    let names: Vec<_> = events.iter().map(|e| e.event_name()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "chat-init",
+            "chat-text-delta",
+            "chat-text",
+            "chat-tool-use",
+            "chat-tool-result",
+            "chat-result"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_run_cancel() {
+    let dir = tempfile::tempdir().unwrap();
+    let cli = fake_cli(&dir, &format!("{}\nsleep 30", canned(&[INIT])));
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let _ = cancel_tx.send(());
+    });
+
+    let started = Instant::now();
+    let mut events = Vec::new();
+    let outcome = run(
+        cli.to_str().unwrap(),
+        &request(),
+        |e| events.push(e),
+        cancel_rx,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(outcome, ChatOutcome::Cancelled);
+    assert_eq!(events.len(), 1);
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
+
+#[tokio::test]
+async fn test_run_failure_reports_stderr() {
+    let dir = tempfile::tempdir().unwrap();
+    let cli = fake_cli(&dir, "echo 'Invalid API key' >&2\nexit 1");
+    let (_cancel_tx, cancel_rx) = oneshot::channel();
+
+    let result = run(cli.to_str().unwrap(), &request(), |_| {}, cancel_rx).await;
+
+    match result.unwrap_err() {
+        AppError::Chat(msg) => assert!(msg.contains("Invalid API key")),
+        other => panic!("Unexpected error type: {:?}", other),
+    }
+}
+
+#[test]
+fn test_build_args_resume_and_continue() {
+    let resume = build_args(&ChatRequest {
+        resume: Some("sess-1".to_string()),
+        continue_last: true,
+        ..request()
+    });
+    assert!(resume.windows(2).any(|w| w == ["--resume", "sess-1"]));
+    assert!(!resume.contains(&"--continue".to_string()));
+
+    let cont = build_args(&ChatRequest {
+        continue_last: true,
+        ..request()
+    });
+    assert!(cont.contains(&"--continue".to_string()));
+}
+
+#[test]
+fn test_parse_transcript() {
+    let transcript = parse_transcript("sess-1", TRANSCRIPT);
+
+    assert_eq!(transcript.summary.as_deref(), Some("Oprava buildu"));
+    // Meta zpráva a poškozený řádek se přeskočí
+    assert_eq!(transcript.messages.len(), 3);
+
+    let first = &transcript.messages[0];
+    assert_eq!(first.role, Role::User);
+    assert_eq!(first.text, "Proč padá build?");
+    assert_eq!(first.timestamp, parse_timestamp("2025-06-01T10:00:00Z"));
+
+    let answer = &transcript.messages[1];
+    assert_eq!(answer.role, Role::Assistant);
+    assert_eq!(answer.message_id.as_deref(), Some("msg_1"));
+    assert_eq!(answer.text, "Podívám se.");
+    assert!(matches!(answer.tool_events[0], ChatEvent::ToolUse { .. }));
+
+    assert!(matches!(
+        transcript.messages[2].tool_events[0],
+        ChatEvent::ToolResult { .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_list_sessions() {
+    let mock = MockSystemOps::new()
+        .with_file(
+            "/home/mockuser/.claude/projects/-home-mockuser-app/sess-1.jsonl",
+            TRANSCRIPT,
+        )
+        .with_file(
+            "/home/mockuser/.claude/projects/-home-mockuser-other/sess-2.jsonl",
+            r#"{"type":"summary","summary":"Prázdná"}"#,
+        )
+        .with_file(
+            "/home/mockuser/.claude/projects/-home-mockuser-app/notes.txt",
+            "ignored",
+        );
+
+    let sessions = list_sessions(&mock, None).await.unwrap();
+    assert_eq!(sessions.len(), 1);
+
+    let session = &sessions[0];
+    assert_eq!(session.session_id, "sess-1");
+    assert_eq!(session.project, "-home-mockuser-app");
+    assert_eq!(session.cwd.as_deref(), Some("/home/mockuser/app"));
+    assert_eq!(session.first_prompt.as_deref(), Some("Proč padá build?"));
+    assert_eq!(session.message_count, 3);
+    assert!(session.started_at < session.updated_at);
+
+    let filtered = list_sessions(&mock, Some("/home/mockuser/other"))
+        .await
+        .unwrap();
+    assert!(filtered.is_empty());
+}
+
+#[test]
+fn test_encode_project_dir() {
+    assert_eq!(
+        encode_project_dir("/home/user/my.project"),
+        "-home-user-my-project"
+    );
+}
+
+#[test]
+fn test_transcript_to_entries() {
+    let entries = transcript_to_entries(&parse_transcript("sess-1", TRANSCRIPT));
+    assert_eq!(entries.len(), 1);
+
+    let entry = &entries[0];
+    assert_eq!(entry.id, "msg_1");
+    assert_eq!(entry.user_input, "Proč padá build?");
+    assert_eq!(entry.assistant_response, "Podívám se.");
+    assert_eq!(entry.timestamp, 1748772000000);
+    assert_eq!(entry.tool_calls.len(), 1);
+    assert_eq!(entry.tool_calls[0].name, "Bash");
+    assert_eq!(entry.tool_calls[0].result.as_deref(), Some("error[E0433]"));
+}
+
+#[tokio::test]
+async fn test_import_cli_history_skips_duplicates() {
+    let mock = MockSystemOps::new().with_file(
+        "/home/mockuser/.claude/projects/-home-mockuser-app/sess-1.jsonl",
+        TRANSCRIPT,
+    );
+    let sys: Arc<dyn SystemOps> = Arc::new(mock);
+    let store: Arc<dyn ConversationStore> = Arc::new(LogConversationStore::new(sys.clone()));
+    let threads = ThreadStore::new(sys.clone(), store.clone());
+
+    let mut progress = Vec::new();
+    let report = import_cli_history(&sys, store.as_ref(), &threads, None, |p| progress.push(p))
+        .await
+        .unwrap();
+    assert_eq!(report.sessions, 1);
+    assert_eq!(report.imported, 1);
+    assert_eq!(progress.len(), 1);
+    assert_eq!(progress[0].processed, 1);
+
+    let again = import_cli_history(&sys, store.as_ref(), &threads, None, |_| {})
+        .await
+        .unwrap();
+    assert_eq!(again.imported, 0);
+    assert_eq!(again.duplicates, 1);
+
+    let loaded = load_conversations(store.as_ref()).await.unwrap();
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded[0].tool_calls.len(), 1);
+}
+
+fn usage_record(day: &str, model: &str, project: &str, cost_usd: Option<f64>) -> UsageRecord {
+    UsageRecord {
+        timestamp: 0,
+        day: day.to_string(),
+        conversation_id: Some("conv-1".to_string()),
+        session_id: None,
+        model: Some(model.to_string()),
+        project: Some(project.to_string()),
+        usage: Usage {
+            input_tokens: 10,
+            output_tokens: 20,
+            cache_creation_input_tokens: 0,
+            cache_read_input_tokens: 5,
+        },
+        cost_usd,
+    }
+}
+
+#[test]
+fn test_usage_aggregate() {
+    let records = vec![
+        usage_record("2025-06-01", "sonnet", "/app", Some(0.5)),
+        usage_record("2025-06-01", "opus", "/app", Some(1.5)),
+        usage_record("2025-06-02", "sonnet", "/lib", None),
+    ];
+
+    let by_model = aggregate(&records, UsageGroup::Model, None, None);
+    assert_eq!(by_model.len(), 2);
+    assert_eq!(by_model[1].key, "sonnet");
+    assert_eq!(by_model[1].runs, 2);
+    assert_eq!(by_model[1].output_tokens, 40);
+    assert_eq!(by_model[1].cost_usd, 0.5);
+
+    let by_day = aggregate(&records, UsageGroup::Day, Some("2025-06-02"), None);
+    assert_eq!(by_day.len(), 1);
+    assert_eq!(by_day[0].key, "2025-06-02");
+
+    let by_project = aggregate(&records, UsageGroup::Project, None, Some("2025-06-01"));
+    assert_eq!(by_project.len(), 1);
+    assert_eq!(by_project[0].cost_usd, 2.0);
+}
+
+#[tokio::test]
+async fn test_usage_budget_exceeded_once() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    save_budget(
+        &sys,
+        &UsageBudget {
+            daily_limit_usd: Some(1.0),
+        },
+    )
+    .await
+    .unwrap();
+
+    let lock = Mutex::new(());
+    let day = day_of(1748772000000);
+    let first = record(&sys, &lock, usage_record(&day, "sonnet", "/app", Some(0.6)))
+        .await
+        .unwrap();
+    assert!(first.is_none());
+
+    let second = record(&sys, &lock, usage_record(&day, "sonnet", "/app", Some(0.6)))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(second.limit_usd, 1.0);
+    assert!((second.spent_usd - 1.2).abs() < 1e-9);
+
+    // Varování přijde jen při překročení, ne při každém dalším běhu
+    let third = record(&sys, &lock, usage_record(&day, "sonnet", "/app", Some(0.1)))
+        .await
+        .unwrap();
+    assert!(third.is_none());
+
+    assert_eq!(load_records(&sys).await.unwrap().len(), 3);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_usage_concurrent_records() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    save_budget(
+        &sys,
+        &UsageBudget {
+            daily_limit_usd: Some(1.0),
+        },
+    )
+    .await
+    .unwrap();
+
+    let lock = Arc::new(Mutex::new(()));
+    let day = day_of(1748772000000);
+    let tasks: Vec<_> = (0..10)
+        .map(|_| {
+            let (sys, lock, day) = (sys.clone(), lock.clone(), day.clone());
+            tokio::spawn(async move {
+                record(&sys, &lock, usage_record(&day, "sonnet", "/app", Some(0.3))).await
+            })
+        })
+        .collect();
+
+    let mut exceeded = 0;
+    for task in tasks {
+        if task.await.unwrap().unwrap().is_some() {
+            exceeded += 1;
+        }
+    }
+    // Každý běh je uložený a varování přišlo právě jednou
+    assert_eq!(load_records(&sys).await.unwrap().len(), 10);
+    assert_eq!(exceeded, 1);
+}
+
+#[tokio::test]
+async fn test_usage_budget_rejects_negative() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let result = save_budget(
+        &sys,
+        &UsageBudget {
+            daily_limit_usd: Some(-1.0),
+        },
+    )
+    .await;
+    assert!(matches!(result, Err(AppError::Config(_))));
+}
+
+#[tokio::test]
+async fn test_conversation_sessions_survive_restart() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+
+    let state = AppState::new(sys.clone());
+    assert_eq!(conversation_session(&state, "conv-1").await, None);
+    remember_session(&state, "conv-1", "session-a")
+        .await
+        .unwrap();
+    remember_session(&state, "conv-2", "session-b")
+        .await
+        .unwrap();
+    remember_session(&state, "conv-1", "session-c")
+        .await
+        .unwrap();
+
+    // A new app instance finds the sessions on disk
+    let restarted = AppState::new(sys.clone());
+    assert_eq!(
+        conversation_session(&restarted, "conv-1").await.as_deref(),
+        Some("session-c")
+    );
+    assert_eq!(
+        conversation_session(&restarted, "conv-2").await.as_deref(),
+        Some("session-b")
+    );
+}
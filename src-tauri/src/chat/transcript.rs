@@ -0,0 +1,133 @@
+// Parsování JSONL transkriptů Claude CLI (~/.claude/projects/<projekt>/<session>.jsonl)
+
+use crate::chat::events::{self, ChatEvent};
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+/// Jedna zpráva z transkriptu
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptMessage {
+    pub role: Role,
+    pub uuid: Option<String>,
+    /// ID zprávy z API (`msg_...`), jen u odpovědí asistenta
+    pub message_id: Option<String>,
+    pub cwd: Option<String>,
+    /// ms od epochy
+    pub timestamp: Option<i64>,
+    pub model: Option<String>,
+    pub text: String,
+    /// `ChatEvent::ToolUse` a `ChatEvent::ToolResult` obsažené ve zprávě
+    pub tool_events: Vec<ChatEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Transcript {
+    pub session_id: String,
+    pub summary: Option<String>,
+    pub messages: Vec<TranscriptMessage>,
+}
+
+/// Převede ISO 8601 čas z transkriptu na ms od epochy
+pub fn parse_timestamp(value: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|dt| dt.timestamp_millis())
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+/// Text zprávy - obsah může být string nebo pole bloků
+fn message_text(message: &Value) -> String {
+    match message.get("content") {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn parse_message(line: &str, value: &Value, role: Role) -> Option<TranscriptMessage> {
+    // Meta zprávy (výstupy příkazů apod.) a sidechainy subagentů nejsou součástí konverzace
+    let is_flag = |key: &str| value.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+    if is_flag("isMeta") || is_flag("isSidechain") {
+        return None;
+    }
+
+    let message = value.get("message")?;
+    let tool_events = events::parse_line(line)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|e| matches!(e, ChatEvent::ToolUse { .. } | ChatEvent::ToolResult { .. }))
+        .collect();
+
+    Some(TranscriptMessage {
+        role,
+        uuid: str_field(value, "uuid"),
+        message_id: match role {
+            Role::Assistant => str_field(message, "id"),
+            Role::User => None,
+        },
+        cwd: str_field(value, "cwd"),
+        timestamp: value
+            .get("timestamp")
+            .and_then(|v| v.as_str())
+            .and_then(parse_timestamp),
+        model: str_field(message, "model"),
+        text: message_text(message),
+        tool_events,
+    })
+}
+
+/// Rozparsuje celý transkript. Poškozené řádky se přeskočí.
+pub fn parse_transcript(session_id: &str, content: &str) -> Transcript {
+    let mut transcript = Transcript {
+        session_id: session_id.to_string(),
+        ..Default::default()
+    };
+
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        let value: Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(e) => {
+                log::warn!(
+                    "⚠️  Skipping invalid transcript line in {}: {}",
+                    session_id,
+                    e
+                );
+                continue;
+            }
+        };
+
+        let role = match value.get("type").and_then(|t| t.as_str()) {
+            Some("user") => Role::User,
+            Some("assistant") => Role::Assistant,
+            Some("summary") => {
+                transcript.summary = str_field(&value, "summary");
+                continue;
+            }
+            _ => continue,
+        };
+
+        if let Some(message) = parse_message(line, &value, role) {
+            transcript.messages.push(message);
+        }
+    }
+
+    transcript
+}
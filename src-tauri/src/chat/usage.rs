@@ -0,0 +1,257 @@
+// Sledování spotřeby tokenů a nákladů z výsledků stream-json
+
+use crate::chat::events::Usage;
+use crate::error::AppError;
+use crate::schema::{self, Schema};
+use crate::system::SystemOps;
+use chrono::TimeZone;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Formát `usage.json`. Verze 0 bylo holé pole záznamů.
+pub const USAGE_SCHEMA: Schema = Schema {
+    name: "usage.json",
+    migrations: &[records_into_object],
+};
+
+/// Formát `budget.json`
+pub const BUDGET_SCHEMA: Schema = Schema {
+    name: "budget.json",
+    migrations: &[schema::add_version],
+};
+
+/// 0 -> 1: pole záznamů se přesune do objektu, který unese číslo verze
+fn records_into_object(value: Value) -> Result<Value, AppError> {
+    match value {
+        Value::Array(records) => Ok(serde_json::json!({ "records": records })),
+        other => Err(AppError::Config(format!(
+            "usage.json verze 0 má být pole, ne {}",
+            other
+        ))),
+    }
+}
+
+/// Obsah `usage.json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct UsageFile {
+    #[serde(default)]
+    records: Vec<UsageRecord>,
+}
+
+/// Spotřeba jednoho běhu chatu
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// ms od epochy
+    pub timestamp: i64,
+    /// Lokální den `YYYY-MM-DD`
+    pub day: String,
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Pracovní adresář (projekt), ve kterém CLI běželo
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(flatten)]
+    pub usage: Usage,
+    /// Cena, pokud ji CLI nahlásilo
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageGroup {
+    Model,
+    Day,
+    Project,
+    Conversation,
+}
+
+/// Součet spotřeby za jednu skupinu
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageAggregate {
+    pub key: String,
+    pub runs: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageBudget {
+    /// Denní limit v USD, `None` = bez limitu
+    pub daily_limit_usd: Option<f64>,
+}
+
+/// Payload eventu `usage-budget-exceeded`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BudgetExceeded {
+    pub day: String,
+    pub spent_usd: f64,
+    pub limit_usd: f64,
+}
+
+/// Lokální den pro daný čas (ms od epochy)
+pub fn day_of(timestamp: i64) -> String {
+    chrono::Local
+        .timestamp_millis_opt(timestamp)
+        .single()
+        .map(|dt| dt.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+/// Adresář s daty o spotřebě
+async fn get_usage_dir(sys: &dyn SystemOps) -> Result<PathBuf, AppError> {
+    let config_dir = sys
+        .config_dir()
+        .ok_or(AppError::Config("Cannot find config directory".to_string()))?;
+
+    let usage_dir = config_dir.join("Claude").join("usage");
+
+    if !sys.exists(&usage_dir).await {
+        sys.create_dir_all(&usage_dir).await?;
+    }
+
+    Ok(usage_dir)
+}
+
+async fn get_records_path(sys: &dyn SystemOps) -> Result<PathBuf, AppError> {
+    Ok(get_usage_dir(sys).await?.join("usage.json"))
+}
+
+async fn get_budget_path(sys: &dyn SystemOps) -> Result<PathBuf, AppError> {
+    Ok(get_usage_dir(sys).await?.join("budget.json"))
+}
+
+/// Načte všechny záznamy spotřeby
+pub async fn load_records(sys: &Arc<dyn SystemOps>) -> Result<Vec<UsageRecord>, AppError> {
+    let path = get_records_path(sys.as_ref()).await?;
+
+    let file: Option<UsageFile> = schema::load(sys.as_ref(), &USAGE_SCHEMA, &path).await?;
+    Ok(file.unwrap_or_default().records)
+}
+
+/// Načte denní rozpočet
+pub async fn load_budget(sys: &Arc<dyn SystemOps>) -> Result<UsageBudget, AppError> {
+    let path = get_budget_path(sys.as_ref()).await?;
+
+    Ok(schema::load(sys.as_ref(), &BUDGET_SCHEMA, &path)
+        .await?
+        .unwrap_or_default())
+}
+
+/// Uloží denní rozpočet
+pub async fn save_budget(sys: &Arc<dyn SystemOps>, budget: &UsageBudget) -> Result<(), AppError> {
+    if budget
+        .daily_limit_usd
+        .is_some_and(|limit| !limit.is_finite() || limit < 0.0)
+    {
+        return Err(AppError::Config(
+            "Denní rozpočet musí být nezáporné číslo".to_string(),
+        ));
+    }
+
+    let path = get_budget_path(sys.as_ref()).await?;
+    let json = BUDGET_SCHEMA.to_string_pretty(budget)?;
+
+    sys.write_atomic(&path, &json, false).await
+}
+
+/// Útrata za daný den
+fn spent_on(records: &[UsageRecord], day: &str) -> f64 {
+    records
+        .iter()
+        .filter(|r| r.day == day)
+        .filter_map(|r| r.cost_usd)
+        .sum()
+}
+
+/// Uloží spotřebu jednoho běhu. Vrací `Some`, pokud tímto během
+/// útrata za den překročila rozpočet. `lock` drží celé načtení a přepsání
+/// souboru, aby souběžně skončené chaty neztratily záznam ani nezdvojily
+/// varování o rozpočtu.
+pub async fn record(
+    sys: &Arc<dyn SystemOps>,
+    lock: &Mutex<()>,
+    record: UsageRecord,
+) -> Result<Option<BudgetExceeded>, AppError> {
+    let _guard = lock.lock().await;
+    let mut records = load_records(sys).await?;
+    let day = record.day.clone();
+    let spent_before = spent_on(&records, &day);
+
+    records.push(record);
+    let spent_after = spent_on(&records, &day);
+
+    let path = get_records_path(sys.as_ref()).await?;
+    let json = USAGE_SCHEMA.to_string_pretty(&UsageFile { records })?;
+    sys.write_atomic(&path, &json, false).await?;
+
+    let budget = load_budget(sys).await?;
+    Ok(budget
+        .daily_limit_usd
+        .filter(|limit| spent_before <= *limit && spent_after > *limit)
+        .map(|limit| BudgetExceeded {
+            day,
+            spent_usd: spent_after,
+            limit_usd: limit,
+        }))
+}
+
+/// Sečte záznamy podle zvolené skupiny, volitelně jen v rozsahu dnů (včetně)
+pub fn aggregate(
+    records: &[UsageRecord],
+    group: UsageGroup,
+    from_day: Option<&str>,
+    to_day: Option<&str>,
+) -> Vec<UsageAggregate> {
+    let mut groups: BTreeMap<String, UsageAggregate> = BTreeMap::new();
+
+    let in_range = |r: &&UsageRecord| {
+        from_day.is_none_or(|from| r.day.as_str() >= from)
+            && to_day.is_none_or(|to| r.day.as_str() <= to)
+    };
+
+    for r in records.iter().filter(in_range) {
+        let key = match group {
+            UsageGroup::Model => r.model.clone(),
+            UsageGroup::Day => Some(r.day.clone()),
+            UsageGroup::Project => r.project.clone(),
+            UsageGroup::Conversation => r.conversation_id.clone(),
+        }
+        .unwrap_or_else(|| "unknown".to_string());
+
+        let entry = groups.entry(key.clone()).or_insert_with(|| UsageAggregate {
+            key,
+            ..Default::default()
+        });
+        entry.runs += 1;
+        entry.input_tokens += r.usage.input_tokens;
+        entry.output_tokens += r.usage.output_tokens;
+        entry.cache_creation_input_tokens += r.usage.cache_creation_input_tokens;
+        entry.cache_read_input_tokens += r.usage.cache_read_input_tokens;
+        entry.cost_usd += r.cost_usd.unwrap_or(0.0);
+    }
+
+    groups.into_values().collect()
+}
+
+/// Načte záznamy a sečte je podle skupiny
+pub async fn summary(
+    sys: &Arc<dyn SystemOps>,
+    group: UsageGroup,
+    from_day: Option<&str>,
+    to_day: Option<&str>,
+) -> Result<Vec<UsageAggregate>, AppError> {
+    let records = load_records(sys).await?;
+    Ok(aggregate(&records, group, from_day, to_day))
+}
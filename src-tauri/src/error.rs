@@ -15,6 +15,9 @@ pub enum AppError {
     #[error("Voice chyba: {0}")]
     Voice(String),
 
+    #[error("Chat chyba: {0}")]
+    Chat(String),
+
     #[error("Konfigurační chyba: {0}")]
     Config(String),
 
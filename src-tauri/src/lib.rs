@@ -5,9 +5,13 @@
 
 // Re-export modules for Tauri
 pub mod auth;
+pub mod backup;
+pub mod chat;
 pub mod debug;
 pub mod error;
 pub mod mcp;
+pub mod schema;
+pub mod session;
 pub mod state;
 pub mod system;
 pub mod voice;
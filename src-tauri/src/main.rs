@@ -3,16 +3,19 @@
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use std::path::Path;
 use std::sync::Arc;
-use tauri::Emitter;
-use tauri::Manager;
-use tokio::sync::RwLock; // Import Emitter pro emitování eventů
+use tauri::Emitter; // Import Emitter pro emitování eventů
 
 // Použití modulů z knihovny
+use claude_desktop_lib::backup::{self, BackupTarget, Snapshot, SnapshotDiff};
 use claude_desktop_lib::error::AppError;
+use claude_desktop_lib::session::{self, Session};
 use claude_desktop_lib::state::AppState;
-use claude_desktop_lib::system::{RealSystemOps, SystemOps};
-use claude_desktop_lib::{auth, mcp, voice};
+use claude_desktop_lib::system::{now_millis, RealSystemOps};
+use claude_desktop_lib::voice::crypto::{self, EncryptionStatus, KeySource};
+use claude_desktop_lib::voice::threads::{Conversation, ConversationSummary, MessageRole};
+use claude_desktop_lib::{auth, chat, mcp, voice};
 
 // Tauri commands (volané z JavaScriptu)
 #[tauri::command]
@@ -22,7 +25,19 @@ async fn check_auth(state: tauri::State<'_, AppState>) -> Result<bool, AppError>
 
 #[tauri::command]
 async fn login(state: tauri::State<'_, AppState>) -> Result<String, AppError> {
-    auth::login(&state.sys).await
+    let message = auth::login(&state.sys).await?;
+    session::refresh(&state).await?;
+    Ok(message)
+}
+
+#[tauri::command]
+async fn get_session(state: tauri::State<'_, AppState>) -> Result<Option<Session>, AppError> {
+    Ok(state.session.read().await.clone())
+}
+
+#[tauri::command]
+async fn refresh_session(state: tauri::State<'_, AppState>) -> Result<Option<Session>, AppError> {
+    session::refresh(&state).await
 }
 
 #[tauri::command]
@@ -54,6 +69,33 @@ async fn save_mcp_config(
     mcp::save_config(&state.sys, &config).await
 }
 
+// Backup commands
+#[tauri::command]
+async fn list_backups(
+    target: BackupTarget,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<Snapshot>, AppError> {
+    backup::list_snapshots(&state.sys, target).await
+}
+
+#[tauri::command]
+async fn diff_backup(
+    target: BackupTarget,
+    id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<SnapshotDiff, AppError> {
+    backup::diff_snapshot(&state.sys, target, &id).await
+}
+
+#[tauri::command]
+async fn restore_backup(
+    target: BackupTarget,
+    id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    backup::restore_snapshot(&state.sys, target, &id).await
+}
+
 #[tauri::command]
 fn get_app_version() -> String {
     env!("CARGO_PKG_VERSION").to_string()
@@ -98,25 +140,375 @@ fn switch_view(app: tauri::AppHandle, view: String) -> Result<(), AppError> {
     Ok(())
 }
 
+// Chat commands
+#[tauri::command]
+async fn send_chat_message(
+    request: chat::ChatRequest,
+    app: tauri::AppHandle,
+) -> Result<String, AppError> {
+    chat::start(app, request).await
+}
+
+#[tauri::command]
+async fn cancel_chat(chat_id: String, state: tauri::State<'_, AppState>) -> Result<bool, AppError> {
+    chat::cancel(&state, &chat_id).await
+}
+
+#[tauri::command]
+async fn continue_last_session(
+    mut request: chat::ChatRequest,
+    app: tauri::AppHandle,
+) -> Result<String, AppError> {
+    request.continue_last = true;
+    request.resume = None;
+    chat::start(app, request).await
+}
+
+#[tauri::command]
+async fn resume_session(
+    session_id: String,
+    mut request: chat::ChatRequest,
+    app: tauri::AppHandle,
+) -> Result<String, AppError> {
+    request.resume = Some(session_id);
+    chat::start(app, request).await
+}
+
+#[tauri::command]
+async fn list_cli_sessions(
+    project: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<chat::CliSession>, AppError> {
+    chat::sessions::list_sessions(state.sys.as_ref(), project.as_deref()).await
+}
+
+#[tauri::command]
+async fn import_cli_history(
+    project: Option<String>,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<chat::import::ImportReport, AppError> {
+    let store = state.conversations.as_ref();
+    chat::import::import_cli_history(
+        &state.sys,
+        store,
+        &state.threads,
+        project.as_deref(),
+        |progress| {
+            if let Err(e) = app.emit("cli-import-progress", &progress) {
+                log::warn!("⚠️  Nelze odeslat průběh importu: {}", e);
+            }
+        },
+    )
+    .await
+}
+
+#[tauri::command]
+async fn get_usage_summary(
+    group: chat::usage::UsageGroup,
+    from_day: Option<String>,
+    to_day: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<chat::usage::UsageAggregate>, AppError> {
+    chat::usage::summary(&state.sys, group, from_day.as_deref(), to_day.as_deref()).await
+}
+
+#[tauri::command]
+async fn get_usage_budget(
+    state: tauri::State<'_, AppState>,
+) -> Result<chat::usage::UsageBudget, AppError> {
+    chat::usage::load_budget(&state.sys).await
+}
+
+#[tauri::command]
+async fn set_usage_budget(
+    budget: chat::usage::UsageBudget,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    chat::usage::save_budget(&state.sys, &budget).await
+}
+
 // Voice commands
 #[tauri::command]
 async fn save_conversation(
     entry: voice::ConversationEntry,
     state: tauri::State<'_, AppState>,
 ) -> Result<(), AppError> {
-    voice::save_conversation(&state.sys, entry).await
+    voice::save_conversation(
+        &state.sys,
+        state.conversations.as_ref(),
+        &state.threads,
+        entry,
+    )
+    .await
 }
 
 #[tauri::command]
 async fn load_conversations(
     state: tauri::State<'_, AppState>,
 ) -> Result<Vec<voice::ConversationEntry>, AppError> {
-    voice::load_conversations(&state.sys).await
+    voice::load_conversations(state.conversations.as_ref()).await
+}
+
+#[tauri::command]
+async fn load_conversations_page(
+    filters: Option<voice::ConversationFilter>,
+    page: Option<voice::PageRequest>,
+    state: tauri::State<'_, AppState>,
+) -> Result<voice::ConversationPage, AppError> {
+    let filters = filters.unwrap_or_default();
+    let page = page.unwrap_or_default();
+    voice::load_conversations_page(state.conversations.as_ref(), &filters, &page).await
+}
+
+#[tauri::command]
+async fn count_conversations(
+    filters: Option<voice::ConversationFilter>,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, AppError> {
+    let filters = filters.unwrap_or_default();
+    voice::count_conversations(state.conversations.as_ref(), &filters).await
+}
+
+#[tauri::command]
+async fn search_conversations(
+    query: String,
+    filters: Option<voice::ConversationFilter>,
+    limit: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<voice::SearchHit>, AppError> {
+    let filters = filters.unwrap_or_default();
+    voice::search_conversations(state.conversations.as_ref(), &query, &filters, limit).await
+}
+
+#[tauri::command]
+async fn export_conversations(
+    selection: voice::export::ExportSelection,
+    format: voice::export::ExportFormat,
+    path: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<voice::export::ExportReport, AppError> {
+    voice::export::export_conversations(
+        &state.sys,
+        state.conversations.as_ref(),
+        &selection,
+        format,
+        Path::new(&path),
+    )
+    .await
+}
+
+#[tauri::command]
+async fn export_audio(
+    conversation_ids: Vec<String>,
+    format: voice::audio_export::AudioExportFormat,
+    path: String,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<voice::audio_export::AudioExportReport, AppError> {
+    voice::audio_export::export_audio(
+        &state.sys,
+        state.conversations.as_ref(),
+        &conversation_ids,
+        format,
+        Path::new(&path),
+        |progress| {
+            if let Err(e) = app.emit("audio-export-progress", &progress) {
+                log::warn!("⚠️  Nelze odeslat průběh exportu: {}", e);
+            }
+        },
+    )
+    .await
+}
+
+#[tauri::command]
+async fn import_conversations_file(
+    path: String,
+    options: Option<voice::import::ImportOptions>,
+    state: tauri::State<'_, AppState>,
+) -> Result<voice::import::ImportStats, AppError> {
+    let options = options.unwrap_or_default();
+    voice::import::import_file(
+        &state.sys,
+        state.conversations.as_ref(),
+        &state.threads,
+        Path::new(&path),
+        &options,
+    )
+    .await
 }
 
 #[tauri::command]
 async fn clear_conversations(state: tauri::State<'_, AppState>) -> Result<(), AppError> {
-    voice::clear_conversations(&state.sys).await
+    voice::clear_conversations(state.conversations.as_ref()).await
+}
+
+#[tauri::command]
+async fn set_conversation_starred(
+    id: String,
+    starred: bool,
+    state: tauri::State<'_, AppState>,
+) -> Result<voice::ConversationEntry, AppError> {
+    voice::set_conversation_starred(state.conversations.as_ref(), &id, starred).await
+}
+
+#[tauri::command]
+async fn prune_conversations(
+    dry_run: bool,
+    state: tauri::State<'_, AppState>,
+) -> Result<voice::retention::PruneReport, AppError> {
+    voice::retention::prune(
+        &state.sys,
+        state.conversations.as_ref(),
+        &state.threads,
+        now_millis(),
+        dry_run,
+    )
+    .await
+}
+
+// Encryption commands
+#[tauri::command]
+async fn get_encryption_status(
+    state: tauri::State<'_, AppState>,
+) -> Result<EncryptionStatus, AppError> {
+    state.encryption.status().await
+}
+
+#[tauri::command]
+async fn enable_encryption(
+    source: KeySource,
+    passphrase: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    crypto::enable_encryption(
+        &state.encryption,
+        state.conversations.as_ref(),
+        &state.threads,
+        source,
+        passphrase.as_deref(),
+    )
+    .await
+}
+
+#[tauri::command]
+async fn unlock_history(
+    passphrase: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    crypto::unlock(
+        &state.encryption,
+        state.conversations.as_ref(),
+        passphrase.as_deref(),
+    )
+    .await
+}
+
+#[tauri::command]
+async fn lock_history(state: tauri::State<'_, AppState>) -> Result<(), AppError> {
+    crypto::lock(&state.encryption, state.conversations.as_ref()).await
+}
+
+#[tauri::command]
+async fn rotate_encryption_key(
+    source: KeySource,
+    passphrase: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    crypto::rotate_key(
+        &state.encryption,
+        state.conversations.as_ref(),
+        &state.threads,
+        source,
+        passphrase.as_deref(),
+    )
+    .await
+}
+
+#[tauri::command]
+async fn disable_encryption(state: tauri::State<'_, AppState>) -> Result<(), AppError> {
+    crypto::disable_encryption(
+        &state.encryption,
+        state.conversations.as_ref(),
+        &state.threads,
+    )
+    .await
+}
+
+// Thread commands
+#[tauri::command]
+async fn create_thread(
+    title: Option<String>,
+    tags: Option<Vec<String>>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Conversation, AppError> {
+    state.threads.create(title, tags.unwrap_or_default()).await
+}
+
+#[tauri::command]
+async fn list_threads(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<ConversationSummary>, AppError> {
+    state.threads.list().await
+}
+
+#[tauri::command]
+async fn get_thread(
+    id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Conversation, AppError> {
+    state.threads.get(&id).await
+}
+
+#[tauri::command]
+async fn rename_thread(
+    id: String,
+    title: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Conversation, AppError> {
+    state.threads.rename(&id, &title).await
+}
+
+#[tauri::command]
+async fn set_thread_pinned(
+    id: String,
+    pinned: bool,
+    state: tauri::State<'_, AppState>,
+) -> Result<Conversation, AppError> {
+    state.threads.set_pinned(&id, pinned).await
+}
+
+#[tauri::command]
+async fn set_thread_tags(
+    id: String,
+    tags: Vec<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Conversation, AppError> {
+    state.threads.set_tags(&id, tags).await
+}
+
+#[tauri::command]
+async fn add_thread_message(
+    id: String,
+    role: MessageRole,
+    content: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Conversation, AppError> {
+    state.threads.add_message(&id, role, content).await
+}
+
+#[tauri::command]
+async fn delete_thread(id: String, state: tauri::State<'_, AppState>) -> Result<(), AppError> {
+    state.threads.delete(&id).await
+}
+
+#[tauri::command]
+async fn fork_thread(
+    id: String,
+    message_id: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Conversation, AppError> {
+    state.threads.fork(&id, message_id.as_deref()).await
 }
 
 #[tauri::command]
@@ -128,10 +520,114 @@ async fn get_voice_settings(
 
 #[tauri::command]
 async fn save_voice_settings(
-    settings: voice::VoiceSettings,
+    settings: serde_json::Value,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<voice::VoiceSettings, AppError> {
+    // Chybějící pole zůstanou beze změny; vrací uložené nastavení, hodnoty
+    // mohou být upravené validací
+    let current = voice::load_voice_settings(&state.sys).await?;
+    let patched = voice::profiles::apply_patch(&current, &settings)?;
+    let settings = voice::validate_voice_settings(&state.sys, &patched).await?;
+
+    // Zkratka se registruje před uložením, takže se neuloží nastavení se
+    // zkratkou, kterou nejde použít; při chybě zápisu se vrátí původní
+    voice::ptt::switch(&app, &current, &settings)?;
+    if let Err(e) = voice::write_voice_settings(&state.sys, &settings).await {
+        if let Err(restore) = voice::ptt::switch(&app, &settings, &current) {
+            log::warn!("⚠️  Nelze obnovit push-to-talk: {}", restore);
+        }
+        return Err(e);
+    }
+    Ok(settings)
+}
+
+#[tauri::command]
+async fn transcribe_audio(
+    input: voice::audio::AudioInput,
+    state: tauri::State<'_, AppState>,
+) -> Result<voice::stt::Transcript, AppError> {
+    voice::transcribe_audio(&state.sys, &input).await
+}
+
+#[tauri::command]
+async fn list_tts_voices(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<voice::catalog::VoiceInfo>, AppError> {
+    voice::list_tts_voices(&state.sys).await
+}
+
+#[tauri::command]
+async fn list_stt_models(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<voice::catalog::SttModelInfo>, AppError> {
+    voice::list_stt_models(&state.sys).await
+}
+
+#[tauri::command]
+async fn list_voice_models(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<voice::models::ModelEntry>, AppError> {
+    voice::models::list_models(state.sys.as_ref()).await
+}
+
+#[tauri::command]
+async fn register_voice_model(
+    source: String,
+    sha256: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<voice::models::ModelEntry>, AppError> {
+    voice::models::register(
+        state.sys.as_ref(),
+        &voice::models::LocalFetcher,
+        &source,
+        sha256.as_deref(),
+    )
+    .await
+}
+
+#[tauri::command]
+async fn verify_voice_models(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<voice::models::VerifyResult>, AppError> {
+    voice::models::verify(state.sys.as_ref()).await
+}
+
+#[tauri::command]
+async fn get_voice_models_disk_usage(
+    state: tauri::State<'_, AppState>,
+) -> Result<voice::models::DiskUsage, AppError> {
+    voice::models::disk_usage(state.sys.as_ref()).await
+}
+
+#[tauri::command]
+async fn delete_voice_model(
+    kind: voice::models::ModelKind,
+    name: String,
     state: tauri::State<'_, AppState>,
 ) -> Result<(), AppError> {
-    voice::save_voice_settings(&state.sys, &settings).await
+    voice::models::delete(state.sys.as_ref(), kind, &name).await
+}
+
+#[tauri::command]
+async fn synthesize_speech(
+    text: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<voice::tts::SpeechAudio, AppError> {
+    voice::synthesize_speech(&state.sys, &text).await
+}
+
+#[tauri::command]
+async fn speak_text(text: String, app: tauri::AppHandle) -> Result<String, AppError> {
+    voice::tts::speak(app, text).await
+}
+
+#[tauri::command]
+async fn stop_speaking(
+    stream_id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, AppError> {
+    Ok(voice::tts::stop(&state, &stream_id).await)
 }
 
 fn main() {
@@ -142,28 +638,73 @@ fn main() {
     // Inicializace aplikace
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
-        .manage(AppState {
-            session: RwLock::new(None),
-            mcp_servers: RwLock::new(Vec::new()),
-            sys: Arc::new(RealSystemOps),
-        })
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .manage(AppState::new(Arc::new(RealSystemOps)))
         .invoke_handler(tauri::generate_handler![
             check_auth,
             login,
+            get_session,
+            refresh_session,
             get_mcp_servers,
             start_mcp_server,
             stop_mcp_server,
             load_mcp_config,
             save_mcp_config,
+            list_backups,
+            diff_backup,
+            restore_backup,
             get_app_version,
             get_system_info,
             open_config_dir,
             switch_view,
+            send_chat_message,
+            cancel_chat,
+            continue_last_session,
+            resume_session,
+            list_cli_sessions,
+            import_cli_history,
+            get_usage_summary,
+            get_usage_budget,
+            set_usage_budget,
             save_conversation,
             load_conversations,
+            load_conversations_page,
+            count_conversations,
+            search_conversations,
+            export_conversations,
+            export_audio,
+            import_conversations_file,
+            set_conversation_starred,
+            prune_conversations,
             clear_conversations,
+            get_encryption_status,
+            enable_encryption,
+            unlock_history,
+            lock_history,
+            rotate_encryption_key,
+            disable_encryption,
+            create_thread,
+            list_threads,
+            get_thread,
+            rename_thread,
+            set_thread_pinned,
+            set_thread_tags,
+            add_thread_message,
+            delete_thread,
+            fork_thread,
             get_voice_settings,
             save_voice_settings,
+            transcribe_audio,
+            list_tts_voices,
+            list_stt_models,
+            list_voice_models,
+            register_voice_model,
+            verify_voice_models,
+            get_voice_models_disk_usage,
+            delete_voice_model,
+            synthesize_speech,
+            speak_text,
+            stop_speaking,
         ])
         .setup(|app| {
             // Inicializace system tray
@@ -174,6 +715,15 @@ fn main() {
             // Pro teď jen logování
             println!("🔒 Link handling configured via Webview properties (if applicable)");
 
+            // Session se naplní při startu a dál se hlídá její expirace
+            session::spawn_monitor(app.handle().clone());
+
+            // Pravidelné pročišťování historie podle nastavení retence
+            voice::retention::spawn_pruner(app.handle().clone());
+
+            // Push-to-talk zkratka z nastavení hlasu
+            voice::ptt::spawn_setup(app.handle().clone());
+
             println!("🦀 Claude Desktop (Tauri) started!");
             println!("📦 Memory footprint: ~30-50 MB (vs Electron ~200-400 MB)");
 
@@ -5,7 +5,6 @@ use crate::error::AppError;
 use crate::state::AppState;
 use crate::system::SystemOps;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -64,7 +63,9 @@ pub async fn save_config(sys: &Arc<dyn SystemOps>, config: &str) -> Result<(), A
 
     let config_path = get_config_path(sys.as_ref()).await?;
 
-    sys.write(&config_path, config).await
+    crate::backup::snapshot(sys, crate::backup::BackupTarget::McpConfig, config).await?;
+    // Ruční úpravy konfigurace nechceme ztratit, proto i záloha
+    sys.write_atomic(&config_path, config, true).await
 }
 
 /// Spustí MCP server
@@ -1,6 +1,6 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
-    use super::*;
     use crate::mcp::{load_config, parse_config, save_config};
     use crate::mocks::MockSystemOps;
     use crate::system::SystemOps;
@@ -59,4 +59,35 @@ mod tests {
         assert_eq!(servers[0].command, "python3");
         assert_eq!(servers[0].args[0], "server.py");
     }
+
+    #[tokio::test]
+    async fn test_save_config_keeps_backup() {
+        let path = "/home/mockuser/.config/Claude/claude_desktop_config.json";
+        let mock = MockSystemOps::new().with_file(path, r#"{"old": true}"#);
+        let sys: Arc<dyn SystemOps> = Arc::new(mock);
+
+        save_config(&sys, r#"{"new": true}"#).await.unwrap();
+
+        let backup = PathBuf::from(format!("{}.bak", path));
+        assert_eq!(
+            sys.read_to_string(&backup).await.unwrap(),
+            r#"{"old": true}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_save_config_failure_keeps_original() {
+        let path = "/home/mockuser/.config/Claude/claude_desktop_config.json";
+        let mock = MockSystemOps::new()
+            .with_file(path, r#"{"old": true}"#)
+            .with_write_failure(path, std::io::ErrorKind::StorageFull);
+        let sys: Arc<dyn SystemOps> = Arc::new(mock);
+
+        let result = save_config(&sys, r#"{"new": true}"#).await;
+        assert!(matches!(result, Err(crate::error::AppError::Io(_))));
+        assert_eq!(
+            sys.read_to_string(&PathBuf::from(path)).await.unwrap(),
+            r#"{"old": true}"#
+        );
+    }
 }
@@ -1,7 +1,7 @@
 #[cfg(test)]
 use crate::error::AppError;
 #[cfg(test)]
-use crate::system::SystemOps;
+use crate::system::{backup_path, SystemOps};
 #[cfg(test)]
 use std::collections::HashMap;
 #[cfg(test)]
@@ -13,11 +13,19 @@ use std::process::{ExitStatus, Output};
 #[cfg(test)]
 use std::sync::Mutex;
 
+/// Výstup mockovaného příkazu: (success, stdout, stderr)
+#[cfg(test)]
+pub type CommandOutput = (bool, Vec<u8>, String);
+
 #[cfg(test)]
 pub struct MockSystemOps {
     pub files: Mutex<HashMap<PathBuf, String>>,
+    pub blobs: Mutex<HashMap<PathBuf, Vec<u8>>>, // Binární soubory
     pub commands: Mutex<Vec<(String, Vec<String>)>>, // Zaznamenané příkazy
-    pub command_outputs: Mutex<HashMap<String, (bool, String, String)>>, // (success, stdout, stderr)
+    pub command_outputs: Mutex<HashMap<String, CommandOutput>>,
+    pub env: Mutex<HashMap<String, String>>,
+    pub command_inputs: Mutex<Vec<String>>, // Data poslaná na stdin
+    pub write_failures: Mutex<HashMap<PathBuf, std::io::ErrorKind>>, // Zápisy, které selžou
 }
 
 #[cfg(test)]
@@ -25,8 +33,36 @@ impl MockSystemOps {
     pub fn new() -> Self {
         Self {
             files: Mutex::new(HashMap::new()),
+            blobs: Mutex::new(HashMap::new()),
             commands: Mutex::new(Vec::new()),
             command_outputs: Mutex::new(HashMap::new()),
+            env: Mutex::new(HashMap::new()),
+            command_inputs: Mutex::new(Vec::new()),
+            write_failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Zápisy do `path` selžou s danou chybou (např. plný disk)
+    pub fn with_write_failure(self, path: &str, kind: std::io::ErrorKind) -> Self {
+        self.fail_writes(path, kind);
+        self
+    }
+
+    /// Jako `with_write_failure`, ale i pro už sdílený mock
+    pub fn fail_writes(&self, path: &str, kind: std::io::ErrorKind) {
+        self.write_failures
+            .lock()
+            .unwrap()
+            .insert(PathBuf::from(path), kind);
+    }
+
+    fn check_write(&self, path: &Path) -> Result<(), AppError> {
+        match self.write_failures.lock().unwrap().get(path) {
+            Some(kind) => Err(AppError::Io(std::io::Error::new(
+                *kind,
+                "Injected write failure",
+            ))),
+            None => Ok(()),
         }
     }
 
@@ -38,6 +74,14 @@ impl MockSystemOps {
         self
     }
 
+    pub fn with_env(self, key: &str, value: &str) -> Self {
+        self.env
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_string());
+        self
+    }
+
     pub fn with_command_output(
         self,
         command: &str,
@@ -47,10 +91,26 @@ impl MockSystemOps {
     ) -> Self {
         self.command_outputs.lock().unwrap().insert(
             command.to_string(),
-            (success, stdout.to_string(), stderr.to_string()),
+            (success, stdout.as_bytes().to_vec(), stderr.to_string()),
         );
         self
     }
+
+    /// Binární stdout (např. audio z TTS)
+    pub fn with_command_bytes(self, command: &str, stdout: &[u8]) -> Self {
+        self.command_outputs
+            .lock()
+            .unwrap()
+            .insert(command.to_string(), (true, stdout.to_vec(), String::new()));
+        self
+    }
+}
+
+#[cfg(test)]
+impl Default for MockSystemOps {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(test)]
@@ -67,33 +127,152 @@ impl SystemOps for MockSystemOps {
     }
 
     async fn write(&self, path: &Path, content: &str) -> Result<(), AppError> {
+        self.check_write(path)?;
+        let mut files = self.files.lock().unwrap();
+        files.insert(path.to_path_buf(), content.to_string());
+        Ok(())
+    }
+
+    async fn read_bytes(&self, path: &Path) -> Result<Vec<u8>, AppError> {
+        if let Some(blob) = self.blobs.lock().unwrap().get(path) {
+            return Ok(blob.clone());
+        }
+        self.read_to_string(path).await.map(String::into_bytes)
+    }
+
+    async fn write_bytes(&self, path: &Path, content: &[u8]) -> Result<(), AppError> {
+        self.check_write(path)?;
+        let mut blobs = self.blobs.lock().unwrap();
+        blobs.insert(path.to_path_buf(), content.to_vec());
+        Ok(())
+    }
+
+    async fn write_atomic(&self, path: &Path, content: &str, backup: bool) -> Result<(), AppError> {
+        // Selhání nezmění nic, stejně jako u skutečného přejmenování
+        self.check_write(path)?;
         let mut files = self.files.lock().unwrap();
+        if backup {
+            if let Some(previous) = files.get(path).cloned() {
+                files.insert(backup_path(path), previous);
+            }
+        }
         files.insert(path.to_path_buf(), content.to_string());
         Ok(())
     }
 
+    async fn write_bytes_atomic(&self, path: &Path, content: &[u8]) -> Result<(), AppError> {
+        self.check_write(path)?;
+        let mut blobs = self.blobs.lock().unwrap();
+        blobs.insert(path.to_path_buf(), content.to_vec());
+        Ok(())
+    }
+
+    async fn append(&self, path: &Path, content: &str) -> Result<(), AppError> {
+        self.check_write(path)?;
+        let mut files = self.files.lock().unwrap();
+        files
+            .entry(path.to_path_buf())
+            .or_default()
+            .push_str(content);
+        Ok(())
+    }
+
     async fn create_dir_all(&self, _path: &Path) -> Result<(), AppError> {
         Ok(())
     }
 
     async fn exists(&self, path: &Path) -> bool {
+        // Adresář existuje, pokud pod ním leží nějaký soubor
         let files = self.files.lock().unwrap();
-        files.contains_key(path)
+        let blobs = self.blobs.lock().unwrap();
+        files
+            .keys()
+            .chain(blobs.keys())
+            .any(|k| k.starts_with(path))
     }
 
-    async fn remove_file(&self, path: &Path) -> Result<(), AppError> {
+    async fn file_size(&self, path: &Path) -> Result<u64, AppError> {
+        if let Some(blob) = self.blobs.lock().unwrap().get(path) {
+            return Ok(blob.len() as u64);
+        }
+        self.read_to_string(path).await.map(|c| c.len() as u64)
+    }
+
+    async fn sha256(&self, path: &Path) -> Result<String, AppError> {
+        use sha2::{Digest, Sha256};
+        let content = self.read_bytes(path).await?;
+        Ok(format!("{:x}", Sha256::digest(&content)))
+    }
+
+    async fn copy_file(&self, from: &Path, to: &Path) -> Result<(), AppError> {
+        let content = self.read_bytes(from).await?;
+        self.write_bytes(to, &content).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), AppError> {
+        self.check_write(to)?;
         let mut files = self.files.lock().unwrap();
-        files.remove(path);
+        if let Some(content) = files.remove(from) {
+            files.insert(to.to_path_buf(), content);
+            return Ok(());
+        }
+
+        let mut blobs = self.blobs.lock().unwrap();
+        match blobs.remove(from) {
+            Some(content) => {
+                blobs.insert(to.to_path_buf(), content);
+                Ok(())
+            }
+            None => Err(AppError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "File not found",
+            ))),
+        }
+    }
+
+    async fn remove_file(&self, path: &Path) -> Result<(), AppError> {
+        self.files.lock().unwrap().remove(path);
+        self.blobs.lock().unwrap().remove(path);
         Ok(())
     }
 
     async fn remove_dir_all(&self, path: &Path) -> Result<(), AppError> {
         // Zjednodušená implementace: smaže všechny soubory začínající touto cestou
-        let mut files = self.files.lock().unwrap();
-        files.retain(|k, _| !k.starts_with(path));
+        self.files
+            .lock()
+            .unwrap()
+            .retain(|k, _| !k.starts_with(path));
+        self.blobs
+            .lock()
+            .unwrap()
+            .retain(|k, _| !k.starts_with(path));
         Ok(())
     }
 
+    async fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, AppError> {
+        // Adresáře nejsou uložené zvlášť, odvodí se z cest souborů
+        let files = self.files.lock().unwrap();
+        let blobs = self.blobs.lock().unwrap();
+        let mut children: Vec<PathBuf> = files
+            .keys()
+            .chain(blobs.keys())
+            .filter_map(|k| k.strip_prefix(path).ok())
+            .filter_map(|rest| rest.components().next())
+            .map(|first| path.join(first))
+            .collect();
+
+        if children.is_empty() {
+            return Err(AppError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Directory not found",
+            )));
+        }
+
+        children.sort();
+        children.dedup();
+        Ok(children)
+    }
+
     fn home_dir(&self) -> Option<PathBuf> {
         Some(PathBuf::from("/home/mockuser"))
     }
@@ -102,6 +281,14 @@ impl SystemOps for MockSystemOps {
         Some(PathBuf::from("/home/mockuser/.config"))
     }
 
+    fn temp_dir(&self) -> PathBuf {
+        PathBuf::from("/tmp")
+    }
+
+    fn env_var(&self, key: &str) -> Option<String> {
+        self.env.lock().unwrap().get(key).cloned()
+    }
+
     async fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, AppError> {
         // Zaznamenat volání
         self.commands.lock().unwrap().push((
@@ -109,13 +296,18 @@ impl SystemOps for MockSystemOps {
             args.iter().map(|s| s.to_string()).collect(),
         ));
 
+        // Konkrétnější výstup "příkaz podpříkaz" má přednost před samotným příkazem
         let outputs = self.command_outputs.lock().unwrap();
-        if let Some((success, stdout, stderr)) = outputs.get(command) {
+        let subcommand = args.first().map(|arg| format!("{} {}", command, arg));
+        let output = subcommand
+            .and_then(|key| outputs.get(&key))
+            .or_else(|| outputs.get(command));
+        if let Some((success, stdout, stderr)) = output {
             // Vytvořit ExitStatus (hacky pro Unix)
             let status = ExitStatus::from_raw(if *success { 0 } else { 1 } << 8);
             Ok(Output {
                 status,
-                stdout: stdout.as_bytes().to_vec(),
+                stdout: stdout.clone(),
                 stderr: stderr.as_bytes().to_vec(),
             })
         } else {
@@ -128,4 +320,14 @@ impl SystemOps for MockSystemOps {
             })
         }
     }
+
+    async fn run_command_with_input(
+        &self,
+        command: &str,
+        args: &[&str],
+        input: &str,
+    ) -> Result<Output, AppError> {
+        self.command_inputs.lock().unwrap().push(input.to_string());
+        self.run_command(command, args).await
+    }
 }
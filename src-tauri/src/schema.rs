@@ -0,0 +1,166 @@
+// Schema module
+// Verze formátu ukládaných JSON dokumentů a jejich postupná migrace.
+// Každý dokument nese pole `version`; soubory bez něj jsou verze 0.
+// Starší dokument se při načtení převede krok za krokem na aktuální
+// verzi a původní obsah zůstane v záloze `<soubor>.v<verze>.bak`.
+
+use crate::error::AppError;
+use crate::system::SystemOps;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+#[path = "schema_tests.rs"]
+mod tests;
+
+pub const VERSION_FIELD: &str = "version";
+
+/// Krok migrace: převede dokument z verze N na N + 1
+pub type Migration = fn(Value) -> Result<Value, AppError>;
+
+/// Formát jednoho druhu dokumentu
+#[derive(Debug, Clone, Copy)]
+pub struct Schema {
+    /// Název pro logy a chybové hlášky
+    pub name: &'static str,
+    /// `migrations[n]` převádí verzi n na n + 1, aktuální verze je jejich počet
+    pub migrations: &'static [Migration],
+}
+
+/// Krok 0 -> 1 pro dokumenty, jejichž starší podoby pokrývají serde výchozí
+/// hodnoty: jen doplní číslo verze
+pub fn add_version(value: Value) -> Result<Value, AppError> {
+    Ok(value)
+}
+
+/// Verze dokumentu; bez pole `version` je to 0
+pub fn version_of(value: &Value) -> u32 {
+    value
+        .get(VERSION_FIELD)
+        .and_then(Value::as_u64)
+        .map_or(0, |v| v as u32)
+}
+
+fn set_version(value: &mut Value, version: u32) {
+    if let Some(object) = value.as_object_mut() {
+        object.insert(VERSION_FIELD.to_string(), Value::from(version));
+    }
+}
+
+/// Záloha původního souboru před migrací z dané verze
+pub fn migration_backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".v{}.bak", version));
+    PathBuf::from(name)
+}
+
+impl Schema {
+    /// Aktuální verze formátu
+    pub const fn version(&self) -> u32 {
+        self.migrations.len() as u32
+    }
+
+    /// Převede dokument na aktuální verzi. Vrací ho spolu s původní verzí.
+    /// Dokument z novější verze aplikace se odmítne, aby se nepřepsal.
+    pub fn upgrade(&self, mut value: Value) -> Result<(Value, u32), AppError> {
+        let from = version_of(&value);
+        if from > self.version() {
+            return Err(AppError::Config(format!(
+                "{} má verzi {}, tato verze aplikace zná nejvýše {}",
+                self.name,
+                from,
+                self.version()
+            )));
+        }
+
+        for (step, migrate) in self.migrations.iter().enumerate().skip(from as usize) {
+            value = migrate(value)?;
+            set_version(&mut value, step as u32 + 1);
+        }
+        Ok((value, from))
+    }
+
+    /// Načte dokument v libovolné známé verzi. Vrací i původní verzi.
+    pub fn parse<T: DeserializeOwned>(&self, json: &str) -> Result<(T, u32), AppError> {
+        let value: Value = serde_json::from_str(json).map_err(AppError::Json)?;
+        let (value, from) = self.upgrade(value)?;
+        let document = serde_json::from_value(value).map_err(AppError::Json)?;
+        Ok((document, from))
+    }
+
+    /// Dokument s aktuálním číslem verze
+    pub fn to_value<T: Serialize>(&self, document: &T) -> Result<Value, AppError> {
+        let mut value = serde_json::to_value(document).map_err(AppError::Json)?;
+        set_version(&mut value, self.version());
+        Ok(value)
+    }
+
+    /// Jednořádkový JSON (záznamy logu)
+    pub fn to_string<T: Serialize>(&self, document: &T) -> Result<String, AppError> {
+        serde_json::to_string(&self.to_value(document)?).map_err(AppError::Json)
+    }
+
+    pub fn to_string_pretty<T: Serialize>(&self, document: &T) -> Result<String, AppError> {
+        serde_json::to_string_pretty(&self.to_value(document)?).map_err(AppError::Json)
+    }
+}
+
+/// Uloží zálohu původního obsahu starší verze
+pub async fn backup_original(
+    sys: &dyn SystemOps,
+    schema: &Schema,
+    path: &Path,
+    content: &str,
+    version: u32,
+) -> Result<(), AppError> {
+    let backup = migration_backup_path(path, version);
+    sys.write_atomic(&backup, content, false).await?;
+    log::info!(
+        "📦 Migrated {} from version {} to {} (original in {})",
+        schema.name,
+        version,
+        schema.version(),
+        backup.display()
+    );
+    Ok(())
+}
+
+/// Načte dokument ze souboru, `None` když soubor neexistuje. Starší verze se
+/// převede, původní obsah zazálohuje a soubor přepíše v aktuální verzi.
+pub async fn load<T: Serialize + DeserializeOwned>(
+    sys: &dyn SystemOps,
+    schema: &Schema,
+    path: &Path,
+) -> Result<Option<T>, AppError> {
+    if !sys.exists(path).await {
+        return Ok(None);
+    }
+
+    let content = sys.read_to_string(path).await?;
+    let (document, from): (T, u32) = schema.parse(&content)?;
+
+    if from < schema.version() {
+        backup_original(sys, schema, path, &content, from).await?;
+        sys.write_atomic(path, &schema.to_string_pretty(&document)?, false)
+            .await?;
+    }
+    Ok(Some(document))
+}
+
+/// Smaže zálohy starších verzí souboru, např. když se maže historie nebo
+/// by nešifrovaná kopie obcházela šifrování
+pub async fn remove_backups(
+    sys: &dyn SystemOps,
+    schema: &Schema,
+    path: &Path,
+) -> Result<(), AppError> {
+    for version in 0..schema.version() {
+        let backup = migration_backup_path(path, version);
+        if sys.exists(&backup).await {
+            sys.remove_file(&backup).await?;
+        }
+    }
+    Ok(())
+}
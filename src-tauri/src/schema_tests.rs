@@ -0,0 +1,359 @@
+use crate::chat::usage;
+use crate::error::AppError;
+use crate::mocks::MockSystemOps;
+use crate::schema::{self, migration_backup_path, Schema};
+use crate::system::SystemOps;
+use crate::voice::crypto::{self, Encryption, KeySource};
+use crate::voice::models;
+use crate::voice::normalize::CodeBlockMode;
+use crate::voice::threads::ThreadStore;
+use crate::voice::{
+    load_conversations, load_voice_settings, save_voice_settings, ConversationStore,
+    LogConversationStore,
+};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const VOICE_DIR: &str = "/home/mockuser/.config/Claude/voice";
+const SETTINGS_PATH: &str = "/home/mockuser/.config/Claude/voice/voice_settings.json";
+const LOG_PATH: &str = "/home/mockuser/.config/Claude/voice/conversations.jsonl";
+const MANIFEST_PATH: &str = "/home/mockuser/.local/share/Claude/models/manifest.json";
+const USAGE_PATH: &str = "/home/mockuser/.config/Claude/usage/usage.json";
+const BUDGET_PATH: &str = "/home/mockuser/.config/Claude/usage/budget.json";
+
+macro_rules! fixture {
+    ($name:literal) => {
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fixtures/schema/",
+            $name
+        ))
+    };
+}
+
+fn stored(mock: &MockSystemOps, path: &str) -> String {
+    mock.files
+        .lock()
+        .unwrap()
+        .get(Path::new(path))
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn backup_of(mock: &MockSystemOps, path: &str) -> String {
+    let backup = migration_backup_path(Path::new(path), 0);
+    stored(mock, &backup.to_string_lossy())
+}
+
+fn version_in(content: &str) -> u32 {
+    schema::version_of(&serde_json::from_str::<Value>(content).unwrap())
+}
+
+fn push_one(mut value: Value) -> Result<Value, AppError> {
+    value["steps"].as_array_mut().unwrap().push(json!(1));
+    Ok(value)
+}
+
+fn push_two(mut value: Value) -> Result<Value, AppError> {
+    value["steps"].as_array_mut().unwrap().push(json!(2));
+    Ok(value)
+}
+
+const TWO_STEPS: Schema = Schema {
+    name: "test",
+    migrations: &[push_one, push_two],
+};
+
+#[test]
+fn test_migrations_run_step_by_step() {
+    assert_eq!(TWO_STEPS.version(), 2);
+
+    let (value, from) = TWO_STEPS.upgrade(json!({ "steps": [] })).unwrap();
+    assert_eq!(from, 0);
+    assert_eq!(value, json!({ "steps": [1, 2], "version": 2 }));
+
+    // Only the missing steps run
+    let (value, from) = TWO_STEPS
+        .upgrade(json!({ "steps": [], "version": 1 }))
+        .unwrap();
+    assert_eq!(from, 1);
+    assert_eq!(value["steps"], json!([2]));
+
+    let (value, _) = TWO_STEPS
+        .upgrade(json!({ "steps": [], "version": 2 }))
+        .unwrap();
+    assert_eq!(value["steps"], json!([]));
+
+    assert!(matches!(
+        TWO_STEPS.upgrade(json!({ "steps": [], "version": 3 })),
+        Err(AppError::Config(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_newer_file_is_left_alone() {
+    let newer = r#"{"input_language": "cs-CZ", "version": 99}"#;
+    let mock = Arc::new(MockSystemOps::new().with_file(SETTINGS_PATH, newer));
+    let sys: Arc<dyn SystemOps> = mock.clone();
+
+    assert!(matches!(
+        load_voice_settings(&sys).await,
+        Err(AppError::Config(_))
+    ));
+    assert_eq!(stored(&mock, SETTINGS_PATH), newer);
+    assert!(backup_of(&mock, SETTINGS_PATH).is_empty());
+}
+
+#[tokio::test]
+async fn test_voice_settings_fixtures() {
+    let fixtures = [
+        fixture!("voice_settings_v0_baseline.json"),
+        fixture!("voice_settings_v0_retention.json"),
+        fixture!("voice_settings_v0_stt_model.json"),
+        fixture!("voice_settings_v0_vad.json"),
+        fixture!("voice_settings_v0_profiles.json"),
+    ];
+
+    let mut loaded = Vec::new();
+    for fixture in fixtures {
+        let mock = Arc::new(MockSystemOps::new().with_file(SETTINGS_PATH, fixture));
+        let sys: Arc<dyn SystemOps> = mock.clone();
+
+        let settings = load_voice_settings(&sys).await.unwrap();
+        // Upgraded in place, the original kept next to it
+        assert_eq!(version_in(&stored(&mock, SETTINGS_PATH)), 1);
+        assert_eq!(backup_of(&mock, SETTINGS_PATH), fixture);
+
+        // The upgraded file reads back the same without another migration
+        let rewritten = stored(&mock, SETTINGS_PATH);
+        let reloaded = load_voice_settings(&sys).await.unwrap();
+        assert_eq!(
+            serde_json::to_value(&reloaded).unwrap(),
+            serde_json::to_value(&settings).unwrap()
+        );
+        assert_eq!(stored(&mock, SETTINGS_PATH), rewritten);
+        loaded.push(settings);
+    }
+
+    let [baseline, retention, stt_model, vad, profiles] = loaded.try_into().unwrap();
+
+    assert!(!baseline.auto_play);
+    assert_eq!(baseline.history_limit, 50);
+    let profile = baseline.profile("cs-CZ");
+    assert_eq!(profile.output_voice, "espeak:cs");
+    assert_eq!(profile.output_speed, 1.2);
+    assert_eq!(profile.stt_model, "ggml-base.bin");
+    assert!(!baseline.push_to_talk.enabled);
+
+    assert_eq!(retention.retention.max_age_days, Some(90));
+
+    assert_eq!(stt_model.input_language, "en-US");
+    assert_eq!(stt_model.profile("en-US").stt_model, "ggml-small.bin");
+    assert_eq!(stt_model.retention.max_total_bytes, Some(10_485_760));
+
+    assert!(vad.vad.enabled);
+    assert_eq!(vad.vad.min_silence_ms, 800);
+    assert!(vad.push_to_talk.enabled);
+    assert_eq!(vad.push_to_talk.shortcut, "Alt+Space");
+    assert_eq!(vad.code_blocks, CodeBlockMode::Skip);
+
+    assert_eq!(profiles.profiles.len(), 2);
+    assert_eq!(profiles.profile("en-US").stt_model, "ggml-base.en.bin");
+    assert_eq!(profiles.profile("cs-CZ").output_speed, 1.1);
+    assert!(!profiles.detect_response_language);
+}
+
+#[tokio::test]
+async fn test_saved_settings_carry_version() {
+    let mock = Arc::new(MockSystemOps::new());
+    let sys: Arc<dyn SystemOps> = mock.clone();
+
+    save_voice_settings(&sys, &Default::default())
+        .await
+        .unwrap();
+    assert_eq!(version_in(&stored(&mock, SETTINGS_PATH)), 1);
+    load_voice_settings(&sys).await.unwrap();
+    assert!(backup_of(&mock, SETTINGS_PATH).is_empty());
+}
+
+#[tokio::test]
+async fn test_conversation_log_fixture() {
+    let fixture = fixture!("conversations_v0.jsonl");
+    let mock = Arc::new(MockSystemOps::new().with_file(LOG_PATH, fixture));
+    let sys: Arc<dyn SystemOps> = mock.clone();
+    let store = LogConversationStore::new(sys.clone());
+
+    let entries = load_conversations(&store).await.unwrap();
+    let ids: Vec<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+    assert_eq!(ids, vec!["baseline", "tools", "starred"]);
+    assert!(entries[0].voice_used && entries[0].played_back);
+    assert!(!entries[0].starred);
+    assert_eq!(entries[1].tool_calls[0].name, "Bash");
+    assert_eq!(entries[1].tool_calls[0].result.as_deref(), Some("a.txt"));
+    assert!(entries[2].starred);
+
+    // Compacted into current-version records
+    let log = stored(&mock, LOG_PATH);
+    assert_eq!(log.lines().count(), 3);
+    assert!(log
+        .lines()
+        .all(|l| { serde_json::from_str::<serde_json::Value>(l).unwrap()["op"] == "put" }));
+    assert!(log.lines().all(|l| version_in(l) == 1));
+    assert_eq!(backup_of(&mock, LOG_PATH), fixture);
+
+    // Clearing the history drops the backup too
+    store.clear().await.unwrap();
+    assert!(backup_of(&mock, LOG_PATH).is_empty());
+}
+
+#[tokio::test]
+async fn test_entry_with_missing_fields() {
+    let line = r#"{"op":"put","entry":{"id":"sparse"}}"#;
+    let sys: Arc<dyn SystemOps> =
+        Arc::new(MockSystemOps::new().with_file(LOG_PATH, &format!("{}\n", line)));
+    let store = LogConversationStore::new(sys.clone());
+
+    let entries = load_conversations(&store).await.unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].timestamp, 0);
+    assert!(entries[0].user_input.is_empty());
+}
+
+#[tokio::test]
+async fn test_newer_log_record_is_an_error() {
+    let log = format!(
+        "{}\n{}\n",
+        r#"{"op":"put","entry":{"id":"1","timestamp":1},"version":1}"#,
+        r#"{"op":"put","entry":{"id":"2","timestamp":2},"version":2}"#
+    );
+    let mock = Arc::new(MockSystemOps::new().with_file(LOG_PATH, &log));
+    let sys: Arc<dyn SystemOps> = mock.clone();
+    let store = LogConversationStore::new(sys.clone());
+
+    assert!(matches!(
+        load_conversations(&store).await,
+        Err(AppError::Config(_))
+    ));
+    assert_eq!(stored(&mock, LOG_PATH), log);
+}
+
+#[tokio::test]
+async fn test_legacy_conversations_fixture() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new().with_file(
+        &format!("{}/conversations.json", VOICE_DIR),
+        fixture!("conversations_legacy.json"),
+    ));
+    let store = LogConversationStore::new(sys.clone());
+
+    let entries = load_conversations(&store).await.unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].assistant_response, "Dobrý den");
+    let log = sys.read_to_string(&PathBuf::from(LOG_PATH)).await.unwrap();
+    assert_eq!(version_in(log.lines().next().unwrap()), 1);
+}
+
+#[tokio::test]
+async fn test_thread_fixture() {
+    let path = format!("{}/threads/thread-1.json", VOICE_DIR);
+    let fixture = fixture!("thread_v0.json");
+    let mock = Arc::new(MockSystemOps::new().with_file(&path, fixture));
+    let sys: Arc<dyn SystemOps> = mock.clone();
+    let conversations: Arc<dyn ConversationStore> =
+        Arc::new(LogConversationStore::new(sys.clone()));
+    let threads = ThreadStore::new(sys.clone(), conversations);
+
+    let thread = threads.get("thread-1").await.unwrap();
+    assert_eq!(thread.title, "Staré vlákno");
+    assert!(thread.tags.is_empty());
+    assert!(!thread.pinned);
+    assert!(!thread.messages[0].voice_used);
+    assert!(thread.messages[1].played_back);
+
+    assert_eq!(version_in(&stored(&mock, &path)), 1);
+    assert_eq!(backup_of(&mock, &path), fixture);
+    // The backup isn't listed as a thread
+    assert_eq!(threads.list().await.unwrap().len(), 1);
+
+    threads.delete("thread-1").await.unwrap();
+    assert!(backup_of(&mock, &path).is_empty());
+}
+
+#[tokio::test]
+async fn test_encryption_config_without_version() {
+    let config_path = format!("{}/encryption.json", VOICE_DIR);
+    let mock = Arc::new(MockSystemOps::new());
+    let sys: Arc<dyn SystemOps> = mock.clone();
+    let encryption = Arc::new(Encryption::new(sys.clone()));
+    let store: Arc<dyn ConversationStore> = Arc::new(LogConversationStore::with_encryption(
+        sys.clone(),
+        encryption.clone(),
+    ));
+    let threads = ThreadStore::with_encryption(sys.clone(), store.clone(), encryption.clone());
+    crypto::enable_encryption(
+        &encryption,
+        store.as_ref(),
+        &threads,
+        KeySource::Passphrase,
+        Some("correct horse"),
+    )
+    .await
+    .unwrap();
+
+    // Turn the config back into the unversioned format
+    let mut config: Value = serde_json::from_str(&stored(&mock, &config_path)).unwrap();
+    assert_eq!(schema::version_of(&config), 1);
+    config.as_object_mut().unwrap().remove("version");
+    sys.write(
+        Path::new(&config_path),
+        &serde_json::to_string(&config).unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let reopened = Encryption::new(sys.clone());
+    assert!(reopened.status().await.unwrap().locked);
+    reopened.unlock(Some("correct horse")).await.unwrap();
+    assert_eq!(version_in(&stored(&mock, &config_path)), 1);
+    // No copy of the key material is left behind
+    assert!(backup_of(&mock, &config_path).is_empty());
+}
+
+#[tokio::test]
+async fn test_manifest_fixture() {
+    let fixture = fixture!("manifest_v0.json");
+    let mock = Arc::new(MockSystemOps::new().with_file(MANIFEST_PATH, fixture));
+
+    let models = models::list_models(mock.as_ref()).await.unwrap();
+    assert_eq!(models.len(), 1);
+    assert_eq!(models[0].name, "ggml-base.bin");
+    assert_eq!(models[0].source, None);
+    assert_eq!(version_in(&stored(&mock, MANIFEST_PATH)), 1);
+    assert_eq!(backup_of(&mock, MANIFEST_PATH), fixture);
+}
+
+#[tokio::test]
+async fn test_usage_fixtures() {
+    let mock = Arc::new(
+        MockSystemOps::new()
+            .with_file(USAGE_PATH, fixture!("usage_v0.json"))
+            .with_file(BUDGET_PATH, fixture!("budget_v0.json")),
+    );
+    let sys: Arc<dyn SystemOps> = mock.clone();
+
+    let records = usage::load_records(&sys).await.unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].usage.input_tokens, 120);
+    assert_eq!(records[0].cost_usd, Some(0.25));
+    assert_eq!(records[1].model, None);
+
+    // The bare array became a versioned object
+    let file: Value = serde_json::from_str(&stored(&mock, USAGE_PATH)).unwrap();
+    assert_eq!(schema::version_of(&file), 1);
+    assert_eq!(file["records"].as_array().unwrap().len(), 2);
+    assert_eq!(backup_of(&mock, USAGE_PATH), fixture!("usage_v0.json"));
+
+    let budget = usage::load_budget(&sys).await.unwrap();
+    assert_eq!(budget.daily_limit_usd, Some(5.0));
+    assert_eq!(version_in(&stored(&mock, BUDGET_PATH)), 1);
+}
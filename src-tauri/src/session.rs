@@ -0,0 +1,183 @@
+// Session module
+// Typovaný stav přihlášení Claude CLI a hlídání jeho expirace
+
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::system::{now_millis, SystemOps};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+#[cfg(test)]
+#[path = "session_tests.rs"]
+mod tests;
+
+/// Jak často se session znovu ověřuje na pozadí
+const VERIFY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Jak dlouho před expirací se posílá `session-expiring`
+pub const EXPIRY_WARNING_MS: i64 = 10 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMode {
+    /// Přihlášení přes claude.ai účet (`claude auth login`)
+    OAuth,
+    /// API klíč z `ANTHROPIC_API_KEY` nebo uložený v `~/.claude.json`
+    ApiKey,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Session {
+    /// E-mail nebo jméno účtu, pokud ho CLI zná
+    pub profile: Option<String>,
+    pub auth_mode: AuthMode,
+    /// Expirace access tokenu (ms od epochy), API klíče neexpirují
+    pub expires_at: Option<i64>,
+    /// Kdy byla session naposledy ověřena (ms od epochy)
+    pub last_verified: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionStatus {
+    Missing,
+    Valid,
+    Expiring,
+    Expired,
+}
+
+/// Cesta k OAuth credentials, které zapisuje Claude CLI
+fn get_credentials_path(sys: &dyn SystemOps) -> Option<PathBuf> {
+    sys.home_dir()
+        .map(|home| home.join(".claude").join(".credentials.json"))
+}
+
+/// Cesta ke globální konfiguraci Claude CLI (obsahuje info o účtu)
+fn get_cli_config_path(sys: &dyn SystemOps) -> Option<PathBuf> {
+    sys.home_dir().map(|home| home.join(".claude.json"))
+}
+
+/// Načte JSON soubor, pokud existuje
+async fn read_json(
+    sys: &dyn SystemOps,
+    path: Option<PathBuf>,
+) -> Result<Option<serde_json::Value>, AppError> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+
+    if !sys.exists(&path).await {
+        return Ok(None);
+    }
+
+    let content = sys.read_to_string(&path).await?;
+    Ok(Some(
+        serde_json::from_str(&content).map_err(AppError::Json)?,
+    ))
+}
+
+/// Zjistí aktuální session z credentials Claude CLI
+pub async fn verify(sys: &dyn SystemOps, now: i64) -> Result<Option<Session>, AppError> {
+    let credentials = read_json(sys, get_credentials_path(sys)).await?;
+    let cli_config = read_json(sys, get_cli_config_path(sys)).await?;
+
+    let profile = cli_config
+        .as_ref()
+        .and_then(|c| c.get("oauthAccount"))
+        .and_then(|account| {
+            account
+                .get("emailAddress")
+                .or_else(|| account.get("displayName"))
+        })
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    if let Some(oauth) = credentials.as_ref().and_then(|c| c.get("claudeAiOauth")) {
+        return Ok(Some(Session {
+            profile,
+            auth_mode: AuthMode::OAuth,
+            expires_at: oauth.get("expiresAt").and_then(|v| v.as_i64()),
+            last_verified: now,
+        }));
+    }
+
+    let env_key = sys
+        .env_var("ANTHROPIC_API_KEY")
+        .filter(|key| !key.trim().is_empty());
+    let stored_key = cli_config
+        .as_ref()
+        .and_then(|c| c.get("primaryApiKey"))
+        .and_then(|v| v.as_str())
+        .filter(|key| !key.is_empty());
+
+    if env_key.is_some() || stored_key.is_some() {
+        return Ok(Some(Session {
+            profile,
+            auth_mode: AuthMode::ApiKey,
+            expires_at: None,
+            last_verified: now,
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Vyhodnotí stav session vůči aktuálnímu času
+pub fn status(session: Option<&Session>, now: i64) -> SessionStatus {
+    let Some(session) = session else {
+        return SessionStatus::Missing;
+    };
+
+    match session.expires_at {
+        Some(expires_at) if expires_at <= now => SessionStatus::Expired,
+        Some(expires_at) if expires_at - now <= EXPIRY_WARNING_MS => SessionStatus::Expiring,
+        _ => SessionStatus::Valid,
+    }
+}
+
+/// Znovu ověří session a uloží ji do AppState
+pub async fn refresh(state: &AppState) -> Result<Option<Session>, AppError> {
+    let session = verify(state.sys.as_ref(), now_millis()).await?;
+    *state.session.write().await = session.clone();
+    Ok(session)
+}
+
+/// Spustí úlohu, která session pravidelně ověřuje a hlásí blížící se expiraci
+pub fn spawn_monitor<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(VERIFY_INTERVAL);
+        let mut last_status = None;
+
+        loop {
+            // První tick proběhne okamžitě, takže se session naplní hned po startu
+            interval.tick().await;
+
+            let state = app.state::<AppState>();
+            let session = match refresh(&state).await {
+                Ok(session) => session,
+                Err(e) => {
+                    log::warn!("⚠️  Ověření session selhalo: {}", e);
+                    continue;
+                }
+            };
+
+            let current = status(session.as_ref(), now_millis());
+            if last_status == Some(current) {
+                continue;
+            }
+            last_status = Some(current);
+
+            let event = match current {
+                SessionStatus::Expiring => "session-expiring",
+                SessionStatus::Expired => "session-expired",
+                SessionStatus::Missing | SessionStatus::Valid => continue,
+            };
+
+            log::info!("🔑 Session status: {:?}", current);
+            if let Err(e) = app.emit(event, &session) {
+                log::warn!("⚠️  Nelze odeslat {}: {}", event, e);
+            }
+        }
+    });
+}
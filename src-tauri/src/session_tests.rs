@@ -0,0 +1,84 @@
+use crate::mocks::MockSystemOps;
+use crate::session::{refresh, status, verify, AuthMode, Session, SessionStatus};
+use crate::state::AppState;
+use crate::system::SystemOps;
+use std::sync::Arc;
+
+const CREDENTIALS_PATH: &str = "/home/mockuser/.claude/.credentials.json";
+const CLI_CONFIG_PATH: &str = "/home/mockuser/.claude.json";
+
+fn session(expires_at: Option<i64>) -> Session {
+    Session {
+        profile: None,
+        auth_mode: AuthMode::OAuth,
+        expires_at,
+        last_verified: 0,
+    }
+}
+
+#[tokio::test]
+async fn test_verify_oauth_session() {
+    let mock = MockSystemOps::new()
+        .with_file(
+            CREDENTIALS_PATH,
+            r#"{"claudeAiOauth": {"accessToken": "x", "expiresAt": 5000}}"#,
+        )
+        .with_file(
+            CLI_CONFIG_PATH,
+            r#"{"oauthAccount": {"emailAddress": "dev@example.com"}}"#,
+        );
+
+    let session = verify(&mock, 1000).await.unwrap().unwrap();
+    assert_eq!(session.auth_mode, AuthMode::OAuth);
+    assert_eq!(session.profile.as_deref(), Some("dev@example.com"));
+    assert_eq!(session.expires_at, Some(5000));
+    assert_eq!(session.last_verified, 1000);
+}
+
+#[tokio::test]
+async fn test_verify_api_key_session() {
+    let mock = MockSystemOps::new().with_env("ANTHROPIC_API_KEY", "sk-ant-test");
+
+    let session = verify(&mock, 1000).await.unwrap().unwrap();
+    assert_eq!(session.auth_mode, AuthMode::ApiKey);
+    assert_eq!(session.expires_at, None);
+}
+
+#[tokio::test]
+async fn test_verify_no_session() {
+    let mock = MockSystemOps::new().with_env("ANTHROPIC_API_KEY", "  ");
+
+    assert!(verify(&mock, 1000).await.unwrap().is_none());
+}
+
+#[test]
+fn test_status() {
+    let now = 1_000_000_000;
+
+    assert_eq!(status(None, now), SessionStatus::Missing);
+    assert_eq!(status(Some(&session(None)), now), SessionStatus::Valid);
+    assert_eq!(
+        status(Some(&session(Some(now + 60 * 60 * 1000))), now),
+        SessionStatus::Valid
+    );
+    assert_eq!(
+        status(Some(&session(Some(now + 60 * 1000))), now),
+        SessionStatus::Expiring
+    );
+    assert_eq!(
+        status(Some(&session(Some(now))), now),
+        SessionStatus::Expired
+    );
+}
+
+#[tokio::test]
+async fn test_refresh_updates_state() {
+    let mock = MockSystemOps::new().with_env("ANTHROPIC_API_KEY", "sk-ant-test");
+    let sys: Arc<dyn SystemOps> = Arc::new(mock);
+    let state = AppState::new(sys);
+
+    refresh(&state).await.unwrap();
+
+    let session = state.session.read().await.clone().unwrap();
+    assert_eq!(session.auth_mode, AuthMode::ApiKey);
+}
@@ -1,12 +1,57 @@
 use crate::mcp::McpServer;
+use crate::session::Session;
 use crate::system::SystemOps;
+use crate::voice::crypto::Encryption;
+use crate::voice::{ConversationStore, LogConversationStore, ThreadStore};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{oneshot, Mutex, RwLock};
 
 // Globální stav aplikace
 pub struct AppState {
-    pub session: RwLock<Option<String>>,
+    pub session: RwLock<Option<Session>>,
     pub mcp_servers: RwLock<Vec<McpServer>>,
+    // Běžící chaty (ID běhu -> signál pro zrušení)
+    pub chats: RwLock<HashMap<String, oneshot::Sender<()>>>,
+    // Běžící syntézy řeči (ID streamu -> signál pro zrušení)
+    pub speech: RwLock<HashMap<String, oneshot::Sender<()>>>,
+    // Session CLI pro každou konverzaci (ID konverzace -> session_id),
+    // načtené z disku při prvním použití
+    pub chat_sessions: RwLock<Option<HashMap<String, String>>>,
+    // Zápisy do usage.json (načíst, přidat, přepsat) musí jít po jednom
+    pub usage_lock: Mutex<()>,
+    // Úložiště historie konverzací
+    pub conversations: Arc<dyn ConversationStore>,
+    // Vlákna konverzací
+    pub threads: ThreadStore,
+    // Šifrování historie (sdílené oběma úložišti)
+    pub encryption: Arc<Encryption>,
     // Abstrakce pro systémové operace (I/O, Process)
     pub sys: Arc<dyn SystemOps>,
 }
+
+impl AppState {
+    pub fn new(sys: Arc<dyn SystemOps>) -> Self {
+        let encryption = Arc::new(Encryption::new(sys.clone()));
+        let conversations: Arc<dyn ConversationStore> = Arc::new(
+            LogConversationStore::with_encryption(sys.clone(), encryption.clone()),
+        );
+
+        Self {
+            session: RwLock::new(None),
+            mcp_servers: RwLock::new(Vec::new()),
+            chats: RwLock::new(HashMap::new()),
+            speech: RwLock::new(HashMap::new()),
+            chat_sessions: RwLock::new(None),
+            usage_lock: Mutex::new(()),
+            threads: ThreadStore::with_encryption(
+                sys.clone(),
+                conversations.clone(),
+                encryption.clone(),
+            ),
+            conversations,
+            encryption,
+            sys,
+        }
+    }
+}
@@ -1,9 +1,16 @@
 use crate::error::AppError;
+use sha2::{Digest, Sha256};
+use std::ffi::OsString;
 use std::path::{Path, PathBuf};
-use std::process::Output;
+use std::process::{Output, Stdio};
 use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::process::Command;
 
+#[cfg(test)]
+#[path = "system_tests.rs"]
+mod tests;
+
 /// Abstraktní rozhraní pro systémové operace (Filesystem, Process, Environment)
 /// Umožňuje snadné mockování v testech.
 #[async_trait::async_trait]
@@ -14,31 +21,117 @@ pub trait SystemOps: Send + Sync {
     /// Zápis stringu do souboru
     async fn write(&self, path: &Path, content: &str) -> Result<(), AppError>;
 
+    /// Načtení binárního souboru (audio, modely)
+    async fn read_bytes(&self, path: &Path) -> Result<Vec<u8>, AppError>;
+
+    /// Zápis binárních dat do souboru
+    async fn write_bytes(&self, path: &Path, content: &[u8]) -> Result<(), AppError>;
+
+    /// Atomický zápis: dočasný soubor ve stejném adresáři, fsync a přejmenování.
+    /// Při pádu zůstane buď původní, nebo nový obsah. S `backup` se předchozí
+    /// verze zachová jako `<soubor>.bak`.
+    async fn write_atomic(&self, path: &Path, content: &str, backup: bool) -> Result<(), AppError>;
+
+    /// Atomický zápis binárních dat, jako `write_atomic` bez zálohy
+    async fn write_bytes_atomic(&self, path: &Path, content: &[u8]) -> Result<(), AppError>;
+
+    /// Připojení stringu na konec souboru (soubor se případně vytvoří)
+    async fn append(&self, path: &Path, content: &str) -> Result<(), AppError>;
+
     /// Vytvoření adresáře (včetně rodičů)
     async fn create_dir_all(&self, path: &Path) -> Result<(), AppError>;
 
     /// Kontrola existence cesty
     async fn exists(&self, path: &Path) -> bool;
 
+    /// Velikost souboru v bajtech
+    async fn file_size(&self, path: &Path) -> Result<u64, AppError>;
+
+    /// SHA-256 obsahu souboru jako hex (čte po blocích, i pro velké modely)
+    async fn sha256(&self, path: &Path) -> Result<String, AppError>;
+
+    /// Kopie souboru
+    async fn copy_file(&self, from: &Path, to: &Path) -> Result<(), AppError>;
+
+    /// Přejmenování/přesun v rámci jednoho souborového systému
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), AppError>;
+
     /// Smazání souboru
     async fn remove_file(&self, path: &Path) -> Result<(), AppError>;
 
     /// Smazání adresáře
     async fn remove_dir_all(&self, path: &Path) -> Result<(), AppError>;
 
+    /// Výpis položek adresáře (plné cesty, seřazené)
+    async fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, AppError>;
+
     /// Získání domovského adresáře
     fn home_dir(&self) -> Option<PathBuf>;
 
     /// Získání konfiguračního adresáře
     fn config_dir(&self) -> Option<PathBuf>;
 
+    /// Adresář pro dočasné soubory (vstupy externích nástrojů)
+    fn temp_dir(&self) -> PathBuf;
+
+    /// Hodnota proměnné prostředí (None pokud není nastavena)
+    fn env_var(&self, key: &str) -> Option<String>;
+
     /// Spuštění příkazu a čekání na výsledek
     async fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, AppError>;
+
+    /// Spuštění příkazu se vstupem na stdin (např. tajemství pro `secret-tool`)
+    async fn run_command_with_input(
+        &self,
+        command: &str,
+        args: &[&str],
+        input: &str,
+    ) -> Result<Output, AppError>;
 }
 
 /// Skutečná implementace využívající tokio a std
 pub struct RealSystemOps;
 
+impl RealSystemOps {
+    /// Společná implementace `write_atomic` a `write_bytes_atomic`
+    async fn replace_file(
+        &self,
+        path: &Path,
+        content: &[u8],
+        backup: bool,
+    ) -> Result<(), AppError> {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        if !self.exists(parent).await {
+            self.create_dir_all(parent).await?;
+        }
+
+        let tmp = temp_path(path);
+        let result = async {
+            let mut file = fs::File::create(&tmp).await?;
+            file.write_all(content).await?;
+            file.sync_all().await?;
+            drop(file);
+
+            if backup && fs::try_exists(path).await.unwrap_or(false) {
+                fs::copy(path, backup_path(path)).await?;
+            }
+            fs::rename(&tmp, path).await?;
+
+            // Přejmenování je trvalé až po fsync adresáře
+            fs::File::open(parent).await?.sync_all().await
+        }
+        .await;
+
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp).await;
+        }
+        result.map_err(AppError::Io)
+    }
+}
+
 #[async_trait::async_trait]
 impl SystemOps for RealSystemOps {
     async fn read_to_string(&self, path: &Path) -> Result<String, AppError> {
@@ -55,6 +148,47 @@ impl SystemOps for RealSystemOps {
         fs::write(path, content).await.map_err(AppError::Io)
     }
 
+    async fn read_bytes(&self, path: &Path) -> Result<Vec<u8>, AppError> {
+        fs::read(path).await.map_err(AppError::Io)
+    }
+
+    async fn write_bytes(&self, path: &Path, content: &[u8]) -> Result<(), AppError> {
+        if let Some(parent) = path.parent() {
+            if !self.exists(parent).await {
+                self.create_dir_all(parent).await?;
+            }
+        }
+        fs::write(path, content).await.map_err(AppError::Io)
+    }
+
+    async fn write_atomic(&self, path: &Path, content: &str, backup: bool) -> Result<(), AppError> {
+        self.replace_file(path, content.as_bytes(), backup).await
+    }
+
+    async fn write_bytes_atomic(&self, path: &Path, content: &[u8]) -> Result<(), AppError> {
+        self.replace_file(path, content, false).await
+    }
+
+    async fn append(&self, path: &Path, content: &str) -> Result<(), AppError> {
+        if let Some(parent) = path.parent() {
+            if !self.exists(parent).await {
+                self.create_dir_all(parent).await?;
+            }
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .map_err(AppError::Io)?;
+        file.write_all(content.as_bytes())
+            .await
+            .map_err(AppError::Io)?;
+        // Záznam musí být na disku dřív, než operaci ohlásíme jako hotovou
+        file.sync_data().await.map_err(AppError::Io)
+    }
+
     async fn create_dir_all(&self, path: &Path) -> Result<(), AppError> {
         fs::create_dir_all(path).await.map_err(AppError::Io)
     }
@@ -63,6 +197,37 @@ impl SystemOps for RealSystemOps {
         fs::try_exists(path).await.unwrap_or(false)
     }
 
+    async fn file_size(&self, path: &Path) -> Result<u64, AppError> {
+        Ok(fs::metadata(path).await.map_err(AppError::Io)?.len())
+    }
+
+    async fn sha256(&self, path: &Path) -> Result<String, AppError> {
+        let mut file = fs::File::open(path).await.map_err(AppError::Io)?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; 1 << 16];
+        loop {
+            let read = file.read(&mut buffer).await.map_err(AppError::Io)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    async fn copy_file(&self, from: &Path, to: &Path) -> Result<(), AppError> {
+        if let Some(parent) = to.parent() {
+            if !self.exists(parent).await {
+                self.create_dir_all(parent).await?;
+            }
+        }
+        fs::copy(from, to).await.map(|_| ()).map_err(AppError::Io)
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), AppError> {
+        fs::rename(from, to).await.map_err(AppError::Io)
+    }
+
     async fn remove_file(&self, path: &Path) -> Result<(), AppError> {
         fs::remove_file(path).await.map_err(AppError::Io)
     }
@@ -71,6 +236,16 @@ impl SystemOps for RealSystemOps {
         fs::remove_dir_all(path).await.map_err(AppError::Io)
     }
 
+    async fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, AppError> {
+        let mut entries = fs::read_dir(path).await.map_err(AppError::Io)?;
+        let mut paths = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(AppError::Io)? {
+            paths.push(entry.path());
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
     fn home_dir(&self) -> Option<PathBuf> {
         dirs::home_dir()
     }
@@ -79,6 +254,14 @@ impl SystemOps for RealSystemOps {
         dirs::config_dir()
     }
 
+    fn temp_dir(&self) -> PathBuf {
+        std::env::temp_dir()
+    }
+
+    fn env_var(&self, key: &str) -> Option<String> {
+        std::env::var(key).ok()
+    }
+
     async fn run_command(&self, command: &str, args: &[&str]) -> Result<Output, AppError> {
         Command::new(command)
             .args(args)
@@ -86,4 +269,55 @@ impl SystemOps for RealSystemOps {
             .await
             .map_err(AppError::Io)
     }
+
+    async fn run_command_with_input(
+        &self,
+        command: &str,
+        args: &[&str],
+        input: &str,
+    ) -> Result<Output, AppError> {
+        let mut child = Command::new(command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(AppError::Io)?;
+
+        // Vstup zapisujeme zvlášť, aby se zápis a čtení výstupu navzájem neblokovaly;
+        // zavřením stdin na konci úlohy příkaz pozná konec vstupu
+        if let Some(mut stdin) = child.stdin.take() {
+            let input = input.to_string();
+            tokio::spawn(async move {
+                if let Err(e) = stdin.write_all(input.as_bytes()).await {
+                    log::warn!("⚠️  Zápis na stdin příkazu selhal: {}", e);
+                }
+            });
+        }
+
+        child.wait_with_output().await.map_err(AppError::Io)
+    }
+}
+
+/// Cesta záložní kopie pro `write_atomic` (`config.json` -> `config.json.bak`)
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// Skrytý dočasný soubor vedle cíle, aby přejmenování zůstalo v jednom souborovém systému
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", uuid::Uuid::new_v4().simple()));
+    path.with_file_name(name)
+}
+
+/// Aktuální čas v milisekundách od UNIX epochy (stejná jednotka jako `Date.now()` ve frontendu)
+pub fn now_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
 }
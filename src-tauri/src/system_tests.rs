@@ -0,0 +1,89 @@
+use crate::system::{backup_path, RealSystemOps, SystemOps};
+use std::path::Path;
+
+#[tokio::test]
+async fn test_write_atomic_replaces_and_keeps_backup() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nested").join("config.json");
+    let sys = RealSystemOps;
+
+    sys.write_atomic(&path, "v1", true).await.unwrap();
+    assert_eq!(sys.read_to_string(&path).await.unwrap(), "v1");
+    assert!(!sys.exists(&backup_path(&path)).await);
+
+    sys.write_atomic(&path, "v2", true).await.unwrap();
+    assert_eq!(sys.read_to_string(&path).await.unwrap(), "v2");
+    assert_eq!(sys.read_to_string(&backup_path(&path)).await.unwrap(), "v1");
+
+    sys.write_atomic(&path, "v3", false).await.unwrap();
+    assert_eq!(sys.read_to_string(&backup_path(&path)).await.unwrap(), "v1");
+
+    sys.write_bytes_atomic(&path, b"v4").await.unwrap();
+    assert_eq!(sys.read_bytes(&path).await.unwrap(), b"v4");
+
+    // No temp files are left behind
+    let names = sys.read_dir(path.parent().unwrap()).await.unwrap();
+    assert_eq!(names, vec![path.clone(), backup_path(&path)]);
+}
+
+#[tokio::test]
+async fn test_write_atomic_failure_keeps_original() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    let sys = RealSystemOps;
+    sys.write_atomic(&path, "original", false).await.unwrap();
+
+    // A directory in place of the target makes the rename fail
+    let blocked = dir.path().join("blocked");
+    std::fs::create_dir(&blocked).unwrap();
+    std::fs::write(blocked.join("file"), "x").unwrap();
+    let err = sys.write_atomic(&blocked, "new", false).await;
+    assert!(matches!(err, Err(crate::error::AppError::Io(_))));
+    let err = sys.write_bytes_atomic(&blocked, b"new").await;
+    assert!(matches!(err, Err(crate::error::AppError::Io(_))));
+
+    assert_eq!(sys.read_to_string(&path).await.unwrap(), "original");
+    let names = sys.read_dir(dir.path()).await.unwrap();
+    assert_eq!(names, vec![blocked, path]);
+}
+
+#[test]
+fn test_backup_path() {
+    assert_eq!(
+        backup_path(Path::new("/a/claude_desktop_config.json")),
+        Path::new("/a/claude_desktop_config.json.bak")
+    );
+}
+
+#[tokio::test]
+async fn test_sha256_copy_and_rename() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("model.bin");
+    let sys = RealSystemOps;
+    sys.write_bytes(&source, b"abc").await.unwrap();
+
+    assert_eq!(
+        sys.sha256(&source).await.unwrap(),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(sys.file_size(&source).await.unwrap(), 3);
+
+    let copy = dir.path().join("nested").join("copy.bin");
+    sys.copy_file(&source, &copy).await.unwrap();
+    let moved = dir.path().join("moved.bin");
+    sys.rename(&copy, &moved).await.unwrap();
+    assert_eq!(sys.read_bytes(&moved).await.unwrap(), b"abc");
+    assert!(!sys.exists(&copy).await);
+}
+
+#[tokio::test]
+async fn test_command_input_larger_than_pipe_buffer() {
+    // `cat` vrací vstup hned na výstup; bez souběžného čtení by se zápis zasekl
+    let input = "x".repeat(1 << 20);
+    let output = RealSystemOps
+        .run_command_with_input("cat", &[], &input)
+        .await
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout.len(), input.len());
+}
@@ -0,0 +1,177 @@
+// Audio buffers for the backend speech pipeline
+// Decodes WAV or raw PCM sent by the webview into mono f32 samples, resamples
+// and encodes 16-bit WAV for external tools.
+
+use crate::error::AppError;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioFormat {
+    /// RIFF/WAVE file, any sample rate and channel count
+    Wav,
+    /// Raw little-endian 16-bit integers
+    PcmS16le,
+    /// Raw little-endian 32-bit floats (what Web Audio produces)
+    PcmF32le,
+}
+
+/// Audio sent from the webview
+#[derive(Debug, Clone, Deserialize)]
+pub struct AudioInput {
+    pub format: AudioFormat,
+    /// Base64-encoded bytes
+    pub data: String,
+    /// Required for raw PCM
+    #[serde(default)]
+    pub sample_rate: Option<u32>,
+    /// Interleaved channels in raw PCM, 1 when not given
+    #[serde(default)]
+    pub channels: Option<u16>,
+}
+
+/// Mono audio
+#[derive(Debug, Clone, PartialEq)]
+pub struct Audio {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+impl Audio {
+    pub fn duration_ms(&self) -> i64 {
+        if self.sample_rate == 0 {
+            return 0;
+        }
+        self.samples.len() as i64 * 1000 / i64::from(self.sample_rate)
+    }
+}
+
+fn invalid(message: impl std::fmt::Display) -> AppError {
+    AppError::Voice(format!("Neplatná zvuková data: {}", message))
+}
+
+/// Average interleaved channels into one
+fn to_mono(samples: Vec<f32>, channels: u16) -> Vec<f32> {
+    if channels <= 1 {
+        return samples;
+    }
+    samples
+        .chunks(usize::from(channels))
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect()
+}
+
+pub fn decode_wav(bytes: &[u8]) -> Result<Audio, AppError> {
+    let reader = hound::WavReader::new(Cursor::new(bytes)).map_err(invalid)?;
+    let spec = reader.spec();
+    if spec.sample_rate == 0 || spec.channels == 0 {
+        return Err(invalid("nulová vzorkovací frekvence nebo počet kanálů"));
+    }
+
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .into_samples::<f32>()
+            .collect::<Result<_, _>>()
+            .map_err(invalid)?,
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()
+                .map_err(invalid)?
+        }
+    };
+
+    Ok(Audio {
+        samples: to_mono(samples, spec.channels),
+        sample_rate: spec.sample_rate,
+    })
+}
+
+/// Decode audio from the webview into mono samples
+pub fn decode(input: &AudioInput) -> Result<Audio, AppError> {
+    let bytes = BASE64.decode(input.data.as_bytes()).map_err(invalid)?;
+    if input.format == AudioFormat::Wav {
+        return decode_wav(&bytes);
+    }
+
+    let sample_rate = input
+        .sample_rate
+        .filter(|r| *r > 0)
+        .ok_or_else(|| invalid("u PCM chybí vzorkovací frekvence"))?;
+    if input.channels == Some(0) {
+        return Err(invalid("nulový počet kanálů"));
+    }
+    let samples: Vec<f32> = match input.format {
+        AudioFormat::PcmS16le => bytes
+            .chunks_exact(2)
+            .map(|b| f32::from(i16::from_le_bytes([b[0], b[1]])) / 32768.0)
+            .collect(),
+        AudioFormat::PcmF32le => bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+        AudioFormat::Wav => unreachable!("handled above"),
+    };
+
+    Ok(Audio {
+        samples: to_mono(samples, input.channels.unwrap_or(1)),
+        sample_rate,
+    })
+}
+
+/// Linear-interpolation resampling; good enough for speech recognition
+pub fn resample(audio: &Audio, sample_rate: u32) -> Result<Audio, AppError> {
+    if audio.sample_rate == 0 || sample_rate == 0 {
+        return Err(invalid("nulová vzorkovací frekvence"));
+    }
+    if audio.sample_rate == sample_rate || audio.samples.is_empty() {
+        return Ok(Audio {
+            samples: audio.samples.clone(),
+            sample_rate,
+        });
+    }
+
+    let ratio = f64::from(audio.sample_rate) / f64::from(sample_rate);
+    let length = (audio.samples.len() as f64 / ratio).round() as usize;
+    let last = audio.samples.len() - 1;
+
+    let samples = (0..length)
+        .map(|i| {
+            let position = i as f64 * ratio;
+            let index = (position as usize).min(last);
+            let next = (index + 1).min(last);
+            let fraction = (position - index as f64) as f32;
+            audio.samples[index] * (1.0 - fraction) + audio.samples[next] * fraction
+        })
+        .collect();
+
+    Ok(Audio {
+        samples,
+        sample_rate,
+    })
+}
+
+/// 16-bit mono WAV
+pub fn encode_wav(audio: &Audio) -> Result<Vec<u8>, AppError> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: audio.sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+
+    let mut buffer = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut buffer, spec).map_err(invalid)?;
+    for sample in &audio.samples {
+        let value = (sample.clamp(-1.0, 1.0) * 32767.0).round() as i16;
+        writer.write_sample(value).map_err(invalid)?;
+    }
+    writer.finalize().map_err(invalid)?;
+
+    Ok(buffer.into_inner())
+}
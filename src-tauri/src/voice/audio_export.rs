@@ -0,0 +1,305 @@
+// Audio export of assistant responses
+// Each selected entry is synthesized with the TTS profile of its language
+// and becomes one chapter of a single file. WAV files carry the chapters as
+// `cue ` points with `labl` names, Ogg/Opus files (encoded by the `opusenc`
+// CLI, override with `OPUSENC`) as CHAPTERxxx comments.
+
+use super::audio::{self, Audio};
+use super::{normalize, tts, ConversationEntry, ConversationStore, VoiceSettings};
+use crate::error::AppError;
+use crate::system::SystemOps;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Silence between two entries
+const CHAPTER_GAP_MS: i64 = 800;
+/// Chapter titles are cut to this many characters of the question
+const TITLE_CHARS: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioExportFormat {
+    /// 16-bit mono PCM
+    Wav,
+    OggOpus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Chapter {
+    pub entry_id: String,
+    pub title: String,
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+/// Payload of `audio-export-progress`, sent after each synthesized entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AudioExportProgress {
+    pub entry_id: String,
+    pub processed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AudioExportReport {
+    pub path: PathBuf,
+    pub format: AudioExportFormat,
+    pub chapters: Vec<Chapter>,
+    pub duration_ms: i64,
+}
+
+fn chapter_title(entry: &ConversationEntry) -> String {
+    let question = entry.user_input.lines().next().unwrap_or_default().trim();
+    let mut title: String = question.chars().take(TITLE_CHARS).collect();
+    if question.chars().count() > TITLE_CHARS {
+        title.push('…');
+    }
+    format!("{} {}", super::export::format_time(entry.timestamp), title)
+        .trim()
+        .to_string()
+}
+
+/// Synthesize one normalized response sentence by sentence at `sample_rate`
+/// (`None` keeps the engine's rate)
+async fn synthesize_entry(
+    sys: &dyn SystemOps,
+    settings: &VoiceSettings,
+    text: &str,
+    sample_rate: Option<u32>,
+) -> Result<Option<Audio>, AppError> {
+    let language = settings.response_language(text);
+    let mut result: Option<Audio> = None;
+    for sentence in normalize::prepare(text, settings, &language) {
+        let audio = tts::synthesize(sys, settings, &language, &sentence).await?;
+        let rate = result
+            .as_ref()
+            .map(|a| a.sample_rate)
+            .or(sample_rate)
+            .unwrap_or(audio.sample_rate);
+        let audio = audio::resample(&audio, rate)?;
+        match result.as_mut() {
+            Some(combined) => combined.samples.extend(audio.samples),
+            None => result = Some(audio),
+        }
+    }
+    Ok(result)
+}
+
+/// Append `cue ` and `LIST/adtl` chunks naming each chapter to a WAV file
+pub fn add_wav_chapters(wav: &mut Vec<u8>, chapters: &[Chapter], sample_rate: u32) {
+    if chapters.is_empty() {
+        return;
+    }
+
+    let mut cue = Vec::new();
+    cue.extend((chapters.len() as u32).to_le_bytes());
+    for (index, chapter) in chapters.iter().enumerate() {
+        let id = index as u32 + 1;
+        let position = (chapter.start_ms * i64::from(sample_rate) / 1000) as u32;
+        cue.extend(id.to_le_bytes());
+        cue.extend(position.to_le_bytes());
+        cue.extend(b"data");
+        cue.extend(0u32.to_le_bytes());
+        cue.extend(0u32.to_le_bytes());
+        cue.extend(position.to_le_bytes());
+    }
+
+    let mut labels = b"adtl".to_vec();
+    for (index, chapter) in chapters.iter().enumerate() {
+        let mut text = chapter.title.as_bytes().to_vec();
+        text.push(0);
+        labels.extend(b"labl");
+        labels.extend((4 + text.len() as u32).to_le_bytes());
+        labels.extend((index as u32 + 1).to_le_bytes());
+        labels.extend(&text);
+        if text.len() % 2 == 1 {
+            labels.push(0);
+        }
+    }
+
+    for (id, body) in [(b"cue ", cue), (b"LIST", labels)] {
+        wav.extend(id);
+        wav.extend((body.len() as u32).to_le_bytes());
+        wav.extend(body);
+    }
+
+    let riff_size = (wav.len() - 8) as u32;
+    wav[4..8].copy_from_slice(&riff_size.to_le_bytes());
+}
+
+/// "HH:MM:SS.mmm" as used by Vorbis comment chapters
+fn chapter_time(ms: i64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+pub fn opusenc_program(sys: &dyn SystemOps) -> String {
+    sys.env_var("OPUSENC")
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| "opusenc".to_string())
+}
+
+/// `opusenc` arguments for the WAV at `input`
+pub fn opusenc_args(input: &Path, output: &Path, chapters: &[Chapter]) -> Vec<String> {
+    let mut args = vec![
+        "--quiet".to_string(),
+        "--speech".to_string(),
+        "--title".to_string(),
+        "Claude".to_string(),
+    ];
+    for (index, chapter) in chapters.iter().enumerate() {
+        let number = index + 1;
+        args.push("--comment".to_string());
+        args.push(format!(
+            "CHAPTER{:03}={}",
+            number,
+            chapter_time(chapter.start_ms)
+        ));
+        args.push("--comment".to_string());
+        args.push(format!("CHAPTER{:03}NAME={}", number, chapter.title));
+    }
+    args.push(input.to_string_lossy().into_owned());
+    args.push(output.to_string_lossy().into_owned());
+    args
+}
+
+async fn write_ogg_opus(
+    sys: &dyn SystemOps,
+    wav: &[u8],
+    chapters: &[Chapter],
+    path: &Path,
+) -> Result<(), AppError> {
+    let wav_path = sys.temp_dir().join(format!(
+        "claude-export-{}.wav",
+        uuid::Uuid::new_v4().simple()
+    ));
+    sys.write_bytes(&wav_path, wav).await?;
+
+    let args = opusenc_args(&wav_path, path, chapters);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = sys.run_command(&opusenc_program(sys), &args).await;
+    if let Err(e) = sys.remove_file(&wav_path).await {
+        log::warn!("⚠️  Cannot remove {}: {}", wav_path.display(), e);
+    }
+
+    let output = result?;
+    if !output.status.success() {
+        return Err(AppError::Voice(format!(
+            "Kódování do Ogg/Opus selhalo: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Synthesize the responses of the given entries, oldest first, into one
+/// audio file at `path` with a chapter per entry
+pub async fn export_audio(
+    sys: &Arc<dyn SystemOps>,
+    store: &dyn ConversationStore,
+    conversation_ids: &[String],
+    format: AudioExportFormat,
+    path: &Path,
+    mut on_progress: impl FnMut(AudioExportProgress),
+) -> Result<AudioExportReport, AppError> {
+    let entries: Vec<ConversationEntry> = super::load_conversations(store)
+        .await?
+        .into_iter()
+        .filter(|e| conversation_ids.contains(&e.id))
+        .collect();
+
+    if let Some(missing) = conversation_ids
+        .iter()
+        .find(|id| !entries.iter().any(|e| &e.id == *id))
+    {
+        return Err(AppError::Voice(format!(
+            "Konverzace {} neexistuje",
+            missing
+        )));
+    }
+    if entries.is_empty() {
+        return Err(AppError::Voice(
+            "Výběr neobsahuje žádné konverzace".to_string(),
+        ));
+    }
+
+    let settings = super::load_voice_settings(sys).await?;
+    let mut combined: Option<Audio> = None;
+    let mut chapters = Vec::new();
+
+    for (index, entry) in entries.iter().enumerate() {
+        let sample_rate = combined.as_ref().map(|a| a.sample_rate);
+        let speech = synthesize_entry(
+            sys.as_ref(),
+            &settings,
+            &entry.assistant_response,
+            sample_rate,
+        )
+        .await?;
+
+        if let Some(speech) = speech {
+            let combined = combined.get_or_insert_with(|| Audio {
+                samples: Vec::new(),
+                sample_rate: speech.sample_rate,
+            });
+            if !combined.samples.is_empty() {
+                let gap = CHAPTER_GAP_MS * i64::from(combined.sample_rate) / 1000;
+                combined
+                    .samples
+                    .extend(std::iter::repeat_n(0.0, gap as usize));
+            }
+
+            let start_ms = combined.duration_ms();
+            combined.samples.extend(speech.samples);
+            chapters.push(Chapter {
+                entry_id: entry.id.clone(),
+                title: chapter_title(entry),
+                start_ms,
+                end_ms: combined.duration_ms(),
+            });
+        }
+
+        on_progress(AudioExportProgress {
+            entry_id: entry.id.clone(),
+            processed: index + 1,
+            total: entries.len(),
+        });
+    }
+
+    let Some(combined) = combined else {
+        return Err(AppError::Voice(
+            "Vybrané odpovědi neobsahují žádný text".to_string(),
+        ));
+    };
+
+    let mut wav = audio::encode_wav(&combined)?;
+    match format {
+        AudioExportFormat::Wav => {
+            add_wav_chapters(&mut wav, &chapters, combined.sample_rate);
+            sys.write_bytes_atomic(path, &wav).await?;
+        }
+        AudioExportFormat::OggOpus => {
+            write_ogg_opus(sys.as_ref(), &wav, &chapters, path).await?;
+        }
+    }
+
+    log::info!(
+        "🔊 Exported {} responses ({} ms) to {}",
+        chapters.len(),
+        combined.duration_ms(),
+        path.display()
+    );
+
+    Ok(AudioExportReport {
+        path: path.to_path_buf(),
+        format,
+        chapters,
+        duration_ms: combined.duration_ms(),
+    })
+}
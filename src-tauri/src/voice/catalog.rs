@@ -0,0 +1,306 @@
+// Installed TTS voices and STT models
+// Piper voices and whisper models are read from the models directory,
+// espeak-ng voices from `espeak-ng --voices`. Each item carries the value to
+// store in a language profile and whether any profile uses it. `Catalog`
+// answers whether a profile value refers to something installed.
+
+use super::tts::{TtsEngineKind, VoiceSpec};
+use super::VoiceSettings;
+use crate::error::AppError;
+use crate::system::SystemOps;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VoiceInfo {
+    /// Value for `LanguageProfile::output_voice`
+    pub id: String,
+    pub engine: TtsEngineKind,
+    pub name: String,
+    /// BCP-47 style tag, e.g. "cs-CZ"
+    pub language: String,
+    /// Piper quality level (x_low, low, medium, high)
+    pub quality: Option<String>,
+    pub gender: Option<String>,
+    pub size_bytes: Option<u64>,
+    pub selected: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SttModelInfo {
+    /// Value for `LanguageProfile::stt_model`
+    pub id: String,
+    /// Model size (tiny, base, small, medium, large-v3, ...)
+    pub size_class: String,
+    /// False for the English-only `.en` models
+    pub multilingual: bool,
+    /// Quantization such as q5_0, `None` for full precision
+    pub quantization: Option<String>,
+    pub size_bytes: u64,
+    pub selected: bool,
+}
+
+/// "cs_CZ" -> "cs-CZ"
+fn language_tag(code: &str) -> String {
+    code.replace('_', "-")
+}
+
+async fn files_with_suffix(
+    sys: &dyn SystemOps,
+    dir: &Path,
+    suffix: &str,
+) -> Vec<std::path::PathBuf> {
+    match sys.read_dir(dir).await {
+        Ok(files) => files
+            .into_iter()
+            .filter(|p| p.to_string_lossy().ends_with(suffix))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Piper voices: `<lang>_<REGION>-<name>-<quality>.onnx` plus `.onnx.json`
+async fn piper_voices(sys: &dyn SystemOps) -> Result<Vec<VoiceInfo>, AppError> {
+    let dir = super::get_models_dir(sys)?.join("piper");
+    let mut voices = Vec::new();
+
+    for model in files_with_suffix(sys, &dir, ".onnx").await {
+        let Some(stem) = model.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let mut parts = stem.splitn(3, '-');
+        let (code, name, quality) = (parts.next(), parts.next(), parts.next());
+
+        let mut config_path = model.as_os_str().to_owned();
+        config_path.push(".json");
+        let config: serde_json::Value = sys
+            .read_to_string(Path::new(&config_path))
+            .await
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default();
+
+        let language = config["language"]["code"]
+            .as_str()
+            .or(code)
+            .map(language_tag)
+            .unwrap_or_default();
+        let quality = config["audio"]["quality"]
+            .as_str()
+            .or(quality)
+            .map(str::to_string);
+
+        voices.push(VoiceInfo {
+            id: format!("piper:{}", stem),
+            engine: TtsEngineKind::Piper,
+            name: config["dataset"]
+                .as_str()
+                .or(name)
+                .unwrap_or(stem)
+                .to_string(),
+            language,
+            quality,
+            gender: None,
+            size_bytes: sys.file_size(&model).await.ok(),
+            selected: false,
+        });
+    }
+
+    Ok(voices)
+}
+
+/// Parse the table printed by `espeak-ng --voices`:
+/// `Pty Language Age/Gender VoiceName File Other Languages`
+pub fn parse_espeak_voices(stdout: &str) -> Vec<VoiceInfo> {
+    stdout
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            let [_, language, age_gender, name, ..] = columns[..] else {
+                return None;
+            };
+
+            let gender = match age_gender.rsplit('/').next() {
+                Some("M") => Some("male".to_string()),
+                Some("F") => Some("female".to_string()),
+                _ => None,
+            };
+
+            Some(VoiceInfo {
+                id: format!("espeak:{}", language),
+                engine: TtsEngineKind::Espeak,
+                name: name.replace('_', " "),
+                language: language.to_string(),
+                quality: None,
+                gender,
+                size_bytes: None,
+                selected: false,
+            })
+        })
+        .collect()
+}
+
+async fn espeak_voices(sys: &dyn SystemOps) -> Vec<VoiceInfo> {
+    let program = sys
+        .env_var("ESPEAK_NG")
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| "espeak-ng".to_string());
+
+    match sys.run_command(&program, &["--voices"]).await {
+        Ok(output) if output.status.success() => {
+            parse_espeak_voices(&String::from_utf8_lossy(&output.stdout))
+        }
+        Ok(_) | Err(_) => {
+            log::warn!("⚠️  espeak-ng voices unavailable");
+            Vec::new()
+        }
+    }
+}
+
+/// Installed voices of both engines, Piper first
+pub async fn list_voices(
+    sys: &dyn SystemOps,
+    settings: &VoiceSettings,
+) -> Result<Vec<VoiceInfo>, AppError> {
+    let mut voices = piper_voices(sys).await?;
+    voices.sort_by(|a, b| a.language.cmp(&b.language).then(a.name.cmp(&b.name)));
+
+    let mut espeak = espeak_voices(sys).await;
+    espeak.sort_by(|a, b| a.language.cmp(&b.language));
+    voices.extend(espeak);
+
+    let in_use: Vec<VoiceSpec> = settings
+        .profiles
+        .iter()
+        .map(|(language, p)| VoiceSpec::parse(&p.output_voice, language))
+        .collect();
+    for voice in &mut voices {
+        voice.selected = in_use.contains(&VoiceSpec::parse(&voice.id, ""));
+    }
+    Ok(voices)
+}
+
+/// "ggml-small.en-q5_1.bin" -> ("small", false, Some("q5_1"))
+fn parse_whisper_name(file: &str) -> (String, bool, Option<String>) {
+    let stem = file
+        .strip_prefix("ggml-")
+        .unwrap_or(file)
+        .trim_end_matches(".bin");
+    let (model, quantization) = match stem.rsplit_once('-') {
+        Some((model, q)) if q.starts_with('q') => (model, Some(q.to_string())),
+        _ => (stem, None),
+    };
+    let (size_class, multilingual) = match model.strip_suffix(".en") {
+        Some(size) => (size, false),
+        None => (model, true),
+    };
+
+    (size_class.to_string(), multilingual, quantization)
+}
+
+/// whisper.cpp models (`ggml-*.bin`) in the whisper models directory
+pub async fn list_stt_models(
+    sys: &dyn SystemOps,
+    settings: &VoiceSettings,
+) -> Result<Vec<SttModelInfo>, AppError> {
+    let dir = super::get_models_dir(sys)?.join("whisper");
+    let mut models = Vec::new();
+
+    for path in files_with_suffix(sys, &dir, ".bin").await {
+        let Some(file) = path.file_name().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let (size_class, multilingual, quantization) = parse_whisper_name(file);
+
+        models.push(SttModelInfo {
+            selected: settings
+                .profiles
+                .values()
+                .any(|p| p.stt_model == file || Path::new(&p.stt_model) == path),
+            id: file.to_string(),
+            size_class,
+            multilingual,
+            quantization,
+            size_bytes: sys.file_size(&path).await.unwrap_or(0),
+        });
+    }
+
+    models.sort_by_key(|m| m.size_bytes);
+    Ok(models)
+}
+
+/// Installed voices and models, for checking profile values. The default is
+/// empty, for saves that change no voice or model.
+#[derive(Default)]
+pub struct Catalog {
+    voices: Vec<VoiceInfo>,
+    stt_models: Vec<SttModelInfo>,
+    stt_dir: PathBuf,
+    /// Absolute model paths used by the settings that exist
+    existing_paths: Vec<PathBuf>,
+}
+
+impl Catalog {
+    pub async fn load(sys: &dyn SystemOps, settings: &VoiceSettings) -> Result<Self, AppError> {
+        let mut existing_paths = Vec::new();
+        for profile in settings.profiles.values() {
+            let voice = profile.output_voice.trim();
+            let model = voice.strip_prefix("piper:").unwrap_or_default();
+            for path in [model, profile.stt_model.as_str()].map(Path::new) {
+                if path.is_absolute() && sys.exists(path).await {
+                    existing_paths.push(path.to_path_buf());
+                }
+            }
+        }
+
+        Ok(Self {
+            voices: list_voices(sys, settings).await?,
+            stt_models: list_stt_models(sys, settings).await?,
+            stt_dir: super::get_models_dir(sys)?.join("whisper"),
+            existing_paths,
+        })
+    }
+
+    /// Whether `output_voice` is a default voice or names an installed one.
+    /// espeak-ng variants ("espeak:cs+f2") count as their base voice.
+    pub fn has_voice(&self, output_voice: &str) -> bool {
+        let value = output_voice.trim();
+        let espeak = value
+            .strip_prefix("espeak-ng:")
+            .or_else(|| value.strip_prefix("espeak:"));
+        let is_default = |v: &str| v.is_empty() || v == "default";
+        if is_default(value) || espeak.is_some_and(is_default) {
+            return true;
+        }
+        // Unprefixed names (WebKit voices) aren't voices of either engine
+        if espeak.is_none() && !value.starts_with("piper:") {
+            return false;
+        }
+
+        let spec = VoiceSpec::parse(value, "");
+        match spec.engine {
+            TtsEngineKind::Piper if spec.voice.ends_with(".onnx") => self
+                .existing_paths
+                .iter()
+                .any(|p| *p == Path::new(&spec.voice)),
+            TtsEngineKind::Piper => self.voices.iter().any(|v| v.id == value),
+            TtsEngineKind::Espeak => {
+                let base = spec.voice.split('+').next().unwrap_or_default();
+                self.voices.iter().any(|v| {
+                    v.engine == TtsEngineKind::Espeak && VoiceSpec::parse(&v.id, "").voice == base
+                })
+            }
+        }
+    }
+
+    /// Whether `stt_model` is an installed model, by file name or path
+    pub fn has_stt_model(&self, stt_model: &str) -> bool {
+        let path = Path::new(stt_model);
+        self.existing_paths.iter().any(|p| p == path)
+            || self
+                .stt_models
+                .iter()
+                .any(|m| m.id == stt_model || self.stt_dir.join(&m.id) == path)
+    }
+}
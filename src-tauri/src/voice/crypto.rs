@@ -0,0 +1,692 @@
+// Encryption at rest for conversation history
+// Records are sealed with AES-256-GCM data keys. The data keys are stored in
+// voice/encryption.json wrapped by a key-encryption key derived from a
+// passphrase (argon2id) or kept in the desktop keyring via `secret-tool`.
+// Changing the passphrase only rewraps the data keys; rotating adds a new data
+// key, rewrites history with it and then drops the old one, so a crash at any
+// point leaves every record readable.
+
+use super::threads::ThreadStore;
+use super::ConversationStore;
+use crate::error::AppError;
+use crate::schema::{self, Schema};
+use crate::system::SystemOps;
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use zeroize::Zeroizing;
+
+/// Prefix of sealed records: `enc1:<key id>:<base64(nonce || ciphertext)>`
+const MAGIC: &str = "enc1:";
+const NONCE_LENGTH: usize = 12;
+const SALT_LENGTH: usize = 16;
+const MIN_PASSPHRASE_LENGTH: usize = 8;
+const KEYRING_SERVICE: &str = "claude-desktop";
+
+type KeyBytes = Zeroizing<[u8; 32]>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    Passphrase,
+    Keyring,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EncryptionStatus {
+    pub enabled: bool,
+    pub source: Option<KeySource>,
+    pub locked: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WrappedKey {
+    id: String,
+    /// Data key sealed with the key-encryption key
+    key: String,
+}
+
+/// Format of `encryption.json`
+const KEY_CONFIG_SCHEMA: Schema = Schema {
+    name: "encryption.json",
+    migrations: &[schema::add_version],
+};
+
+/// Contents of `encryption.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KeyConfig {
+    source: KeySource,
+    /// Argon2 salt for `Passphrase`
+    #[serde(default)]
+    salt: Option<String>,
+    /// `secret-tool` account holding the key for `Keyring`
+    #[serde(default)]
+    keyring_account: Option<String>,
+    /// Data key used for new records
+    current: String,
+    keys: Vec<WrappedKey>,
+}
+
+enum Mode {
+    /// Encryption is off
+    Plain,
+    /// Encryption is on but no key is loaded
+    Locked,
+    Unlocked {
+        config: KeyConfig,
+        kek: KeyBytes,
+        keys: HashMap<String, KeyBytes>,
+        /// Off while history is being decrypted for good
+        sealing: bool,
+    },
+}
+
+fn random_key() -> KeyBytes {
+    let mut key = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(key.as_mut());
+    key
+}
+
+fn random_id() -> String {
+    let mut bytes = [0u8; 4];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn encrypt(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, AppError> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| AppError::Voice("Šifrování selhalo".to_string()))?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    Ok(sealed)
+}
+
+/// `None` when the data is damaged or the key is wrong
+fn decrypt(key: &[u8; 32], sealed: &[u8]) -> Option<Vec<u8>> {
+    if sealed.len() < NONCE_LENGTH {
+        return None;
+    }
+
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    cipher.decrypt(Nonce::from_slice(nonce), ciphertext).ok()
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<KeyBytes, AppError> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| AppError::Voice(format!("Odvození klíče selhalo: {}", e)))?;
+    Ok(key)
+}
+
+fn to_key(bytes: &[u8]) -> Option<KeyBytes> {
+    let array: [u8; 32] = bytes.try_into().ok()?;
+    Some(Zeroizing::new(array))
+}
+
+fn locked() -> AppError {
+    AppError::Voice("Historie konverzací je zamčená".to_string())
+}
+
+/// Key state shared by the conversation log and the thread store
+pub struct Encryption {
+    sys: Arc<dyn SystemOps>,
+    /// `None` until the config has been read
+    mode: RwLock<Option<Mode>>,
+}
+
+impl Encryption {
+    pub fn new(sys: Arc<dyn SystemOps>) -> Self {
+        Self {
+            sys,
+            mode: RwLock::new(None),
+        }
+    }
+
+    async fn config_path(&self) -> Result<PathBuf, AppError> {
+        Ok(super::get_voice_dir(self.sys.as_ref())
+            .await?
+            .join("encryption.json"))
+    }
+
+    async fn load_config(&self) -> Result<Option<KeyConfig>, AppError> {
+        let path = self.config_path().await?;
+        if !self.sys.exists(&path).await {
+            return Ok(None);
+        }
+
+        // No migration backup for the same reason save_config keeps no .bak
+        let content = self.sys.read_to_string(&path).await?;
+        let (config, version) = KEY_CONFIG_SCHEMA.parse::<KeyConfig>(&content)?;
+        if version < KEY_CONFIG_SCHEMA.version() {
+            self.save_config(&config).await?;
+        }
+        Ok(Some(config))
+    }
+
+    async fn save_config(&self, config: &KeyConfig) -> Result<(), AppError> {
+        let path = self.config_path().await?;
+        let json = KEY_CONFIG_SCHEMA.to_string_pretty(config)?;
+        // No .bak: an old copy would keep retired keys around
+        self.sys.write_atomic(&path, &json, false).await
+    }
+
+    fn set_mode(&self, mode: Mode) {
+        *self.mode.write().unwrap_or_else(|e| e.into_inner()) = Some(mode);
+    }
+
+    /// Read the config on first use; keyring-backed keys unlock automatically
+    async fn init(&self) -> Result<(), AppError> {
+        if self
+            .mode
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+        {
+            return Ok(());
+        }
+
+        let mode = match self.load_config().await? {
+            None => Mode::Plain,
+            Some(config) if config.source == KeySource::Keyring => {
+                match self.unlocked(config, None).await {
+                    Ok(mode) => mode,
+                    Err(e) => {
+                        log::warn!("⚠️  Cannot read the key from the keyring: {}", e);
+                        Mode::Locked
+                    }
+                }
+            }
+            Some(_) => Mode::Locked,
+        };
+
+        self.set_mode(mode);
+        Ok(())
+    }
+
+    fn keyring_args<'a>(action: &'a str, account: &'a str) -> Vec<&'a str> {
+        vec![action, "service", KEYRING_SERVICE, "account", account]
+    }
+
+    async fn keyring_lookup(&self, account: &str) -> Result<KeyBytes, AppError> {
+        let output = self
+            .sys
+            .run_command("secret-tool", &Self::keyring_args("lookup", account))
+            .await?;
+        if !output.status.success() {
+            return Err(AppError::Voice("Klíč v klíčence nebyl nalezen".to_string()));
+        }
+
+        let encoded = Zeroizing::new(String::from_utf8_lossy(&output.stdout).trim().to_string());
+        BASE64
+            .decode(encoded.as_bytes())
+            .ok()
+            .and_then(|bytes| to_key(&bytes))
+            .ok_or_else(|| AppError::Voice("Klíč v klíčence je poškozený".to_string()))
+    }
+
+    async fn keyring_store(&self, account: &str, key: &KeyBytes) -> Result<(), AppError> {
+        let args = [
+            "store",
+            "--label=Claude Desktop history key",
+            "service",
+            KEYRING_SERVICE,
+            "account",
+            account,
+        ];
+
+        let encoded = Zeroizing::new(BASE64.encode(key.as_ref()));
+        let output = self
+            .sys
+            .run_command_with_input("secret-tool", &args, &encoded)
+            .await?;
+        if !output.status.success() {
+            return Err(AppError::Voice(format!(
+                "Uložení klíče do klíčenky selhalo: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+
+    async fn keyring_clear(&self, account: &str) {
+        let args = Self::keyring_args("clear", account);
+        if let Err(e) = self.sys.run_command("secret-tool", &args).await {
+            log::warn!("⚠️  Cannot remove the key from the keyring: {}", e);
+        }
+    }
+
+    /// Key-encryption key for an existing config
+    async fn kek_for(
+        &self,
+        config: &KeyConfig,
+        passphrase: Option<&str>,
+    ) -> Result<KeyBytes, AppError> {
+        match config.source {
+            KeySource::Passphrase => {
+                let passphrase =
+                    passphrase.ok_or_else(|| AppError::Voice("Zadejte heslo".to_string()))?;
+                let salt = config
+                    .salt
+                    .as_deref()
+                    .and_then(|s| BASE64.decode(s).ok())
+                    .ok_or_else(|| AppError::Voice("Chybí sůl klíče".to_string()))?;
+                derive_key(passphrase, &salt)
+            }
+            KeySource::Keyring => {
+                let account = config
+                    .keyring_account
+                    .as_deref()
+                    .ok_or_else(|| AppError::Voice("Chybí účet v klíčence".to_string()))?;
+                self.keyring_lookup(account).await
+            }
+        }
+    }
+
+    /// Fresh key-encryption key; returns it with a config carrying no data keys yet
+    async fn new_kek(
+        &self,
+        source: KeySource,
+        passphrase: Option<&str>,
+    ) -> Result<(KeyBytes, KeyConfig), AppError> {
+        let mut config = KeyConfig {
+            source,
+            salt: None,
+            keyring_account: None,
+            current: String::new(),
+            keys: Vec::new(),
+        };
+
+        let kek = match source {
+            KeySource::Passphrase => {
+                let passphrase = passphrase
+                    .filter(|p| p.chars().count() >= MIN_PASSPHRASE_LENGTH)
+                    .ok_or_else(|| {
+                        AppError::Voice(format!(
+                            "Heslo musí mít alespoň {} znaků",
+                            MIN_PASSPHRASE_LENGTH
+                        ))
+                    })?;
+                let mut salt = [0u8; SALT_LENGTH];
+                OsRng.fill_bytes(&mut salt);
+                config.salt = Some(BASE64.encode(salt));
+                derive_key(passphrase, &salt)?
+            }
+            KeySource::Keyring => {
+                let account = format!("conversation-history-{}", random_id());
+                let kek = random_key();
+                self.keyring_store(&account, &kek).await?;
+                config.keyring_account = Some(account);
+                kek
+            }
+        };
+
+        Ok((kek, config))
+    }
+
+    fn wrap(kek: &KeyBytes, keys: &HashMap<String, KeyBytes>) -> Result<Vec<WrappedKey>, AppError> {
+        let mut wrapped: Vec<WrappedKey> = keys
+            .iter()
+            .map(|(id, key)| {
+                Ok(WrappedKey {
+                    id: id.clone(),
+                    key: BASE64.encode(encrypt(kek, key.as_ref())?),
+                })
+            })
+            .collect::<Result<_, AppError>>()?;
+        wrapped.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(wrapped)
+    }
+
+    /// Unwrap the data keys; fails on a wrong passphrase
+    async fn unlocked(
+        &self,
+        config: KeyConfig,
+        passphrase: Option<&str>,
+    ) -> Result<Mode, AppError> {
+        let kek = self.kek_for(&config, passphrase).await?;
+
+        let mut keys = HashMap::new();
+        for wrapped in &config.keys {
+            let key = BASE64
+                .decode(&wrapped.key)
+                .ok()
+                .and_then(|sealed| decrypt(&kek, &sealed))
+                .and_then(|bytes| to_key(&bytes))
+                .ok_or_else(|| AppError::Voice("Nesprávné heslo nebo klíč".to_string()))?;
+            keys.insert(wrapped.id.clone(), key);
+        }
+
+        Ok(Mode::Unlocked {
+            config,
+            kek,
+            keys,
+            sealing: true,
+        })
+    }
+
+    pub async fn status(&self) -> Result<EncryptionStatus, AppError> {
+        self.init().await?;
+        let source = self.load_config().await?.map(|c| c.source);
+        let mode = self.mode.read().unwrap_or_else(|e| e.into_inner());
+
+        Ok(EncryptionStatus {
+            enabled: source.is_some(),
+            source,
+            locked: matches!(mode.as_ref(), Some(Mode::Locked)),
+        })
+    }
+
+    /// Whether new records get encrypted
+    pub async fn is_sealing(&self) -> Result<bool, AppError> {
+        self.init().await?;
+        let mode = self.mode.read().unwrap_or_else(|e| e.into_inner());
+        Ok(matches!(
+            mode.as_ref(),
+            Some(Mode::Locked | Mode::Unlocked { sealing: true, .. })
+        ))
+    }
+
+    /// Encrypt a record for storage (returned as is when encryption is off)
+    pub async fn seal(&self, plaintext: &str) -> Result<String, AppError> {
+        self.init().await?;
+        let mode = self.mode.read().unwrap_or_else(|e| e.into_inner());
+
+        match mode.as_ref() {
+            Some(Mode::Unlocked {
+                config,
+                keys,
+                sealing: true,
+                ..
+            }) => {
+                let key = keys.get(&config.current).ok_or_else(locked)?;
+                let sealed = encrypt(key, plaintext.as_bytes())?;
+                Ok(format!(
+                    "{}{}:{}",
+                    MAGIC,
+                    config.current,
+                    BASE64.encode(sealed)
+                ))
+            }
+            Some(Mode::Locked) => Err(locked()),
+            _ => Ok(plaintext.to_string()),
+        }
+    }
+
+    /// Decrypt a stored record. Plain records pass through; `None` means the
+    /// record is damaged.
+    pub async fn open(&self, stored: &str) -> Result<Option<String>, AppError> {
+        let Some(rest) = stored.strip_prefix(MAGIC) else {
+            return Ok(Some(stored.to_string()));
+        };
+
+        self.init().await?;
+        let mode = self.mode.read().unwrap_or_else(|e| e.into_inner());
+        let Some(Mode::Unlocked { keys, .. }) = mode.as_ref() else {
+            return Err(locked());
+        };
+
+        let Some((id, payload)) = rest.split_once(':') else {
+            return Ok(None);
+        };
+        let key = keys
+            .get(id)
+            .ok_or_else(|| AppError::Voice(format!("Neznámý šifrovací klíč {}", id)))?;
+
+        Ok(BASE64
+            .decode(payload.trim_end())
+            .ok()
+            .and_then(|sealed| decrypt(key, &sealed))
+            .and_then(|bytes| String::from_utf8(bytes).ok()))
+    }
+
+    async fn enable(&self, source: KeySource, passphrase: Option<&str>) -> Result<(), AppError> {
+        self.init().await?;
+        if self.load_config().await?.is_some() {
+            return Err(AppError::Voice("Šifrování je už zapnuté".to_string()));
+        }
+
+        let (kek, mut config) = self.new_kek(source, passphrase).await?;
+        let id = random_id();
+        let keys = HashMap::from([(id.clone(), random_key())]);
+        config.current = id;
+        config.keys = Self::wrap(&kek, &keys)?;
+
+        // Config first: a crash before the rewrite leaves plain records, which still load
+        self.save_config(&config).await?;
+        self.set_mode(Mode::Unlocked {
+            config,
+            kek,
+            keys,
+            sealing: true,
+        });
+        Ok(())
+    }
+
+    pub async fn unlock(&self, passphrase: Option<&str>) -> Result<(), AppError> {
+        let config = self
+            .load_config()
+            .await?
+            .ok_or_else(|| AppError::Voice("Šifrování není zapnuté".to_string()))?;
+
+        let mode = self.unlocked(config, passphrase).await?;
+        self.set_mode(mode);
+        log::info!("🔓 Conversation history unlocked");
+        Ok(())
+    }
+
+    pub async fn lock(&self) -> Result<(), AppError> {
+        if self.load_config().await?.is_none() {
+            return Err(AppError::Voice("Šifrování není zapnuté".to_string()));
+        }
+
+        // Dropping the mode zeroizes the keys
+        self.set_mode(Mode::Locked);
+        log::info!("🔒 Conversation history locked");
+        Ok(())
+    }
+
+    /// Take the unlocked state out for a change; puts it back on error
+    fn take_unlocked(&self) -> Result<(KeyConfig, KeyBytes, HashMap<String, KeyBytes>), AppError> {
+        let mut mode = self.mode.write().unwrap_or_else(|e| e.into_inner());
+        match mode.take() {
+            Some(Mode::Unlocked {
+                config, kek, keys, ..
+            }) => Ok((config, kek, keys)),
+            other => {
+                *mode = other;
+                Err(locked())
+            }
+        }
+    }
+
+    /// Switch to a new key-encryption key and a new data key. Old data keys
+    /// stay in the config until `finish_rotation`.
+    async fn rotate(&self, source: KeySource, passphrase: Option<&str>) -> Result<(), AppError> {
+        self.init().await?;
+        let (old_config, old_kek, keys) = self.take_unlocked()?;
+
+        let result = async {
+            let (kek, mut config) = self.new_kek(source, passphrase).await?;
+            let id = random_id();
+            let mut all = keys.clone();
+            all.insert(id.clone(), random_key());
+            config.current = id;
+            config.keys = Self::wrap(&kek, &all)?;
+            self.save_config(&config).await?;
+            Ok::<_, AppError>((kek, config, all))
+        }
+        .await;
+
+        match result {
+            Ok((kek, config, keys)) => {
+                if let Some(account) = old_config.keyring_account.as_deref() {
+                    if config.keyring_account.as_deref() != Some(account) {
+                        self.keyring_clear(account).await;
+                    }
+                }
+                self.set_mode(Mode::Unlocked {
+                    config,
+                    kek,
+                    keys,
+                    sealing: true,
+                });
+                Ok(())
+            }
+            Err(e) => {
+                self.set_mode(Mode::Unlocked {
+                    config: old_config,
+                    kek: old_kek,
+                    keys,
+                    sealing: true,
+                });
+                Err(e)
+            }
+        }
+    }
+
+    /// Drop data keys other than the current one once history is rewritten
+    async fn finish_rotation(&self) -> Result<(), AppError> {
+        let (mut config, kek, mut keys) = self.take_unlocked()?;
+        keys.retain(|id, _| *id == config.current);
+
+        let saved = match Self::wrap(&kek, &keys) {
+            Ok(wrapped) => {
+                config.keys = wrapped;
+                self.save_config(&config).await
+            }
+            Err(e) => Err(e),
+        };
+
+        self.set_mode(Mode::Unlocked {
+            config,
+            kek,
+            keys,
+            sealing: true,
+        });
+        saved
+    }
+
+    /// Stop sealing new records while keeping keys to read existing ones
+    fn begin_disable(&self) -> Result<(), AppError> {
+        let (config, kek, keys) = self.take_unlocked()?;
+        self.set_mode(Mode::Unlocked {
+            config,
+            kek,
+            keys,
+            sealing: false,
+        });
+        Ok(())
+    }
+
+    /// Resume sealing after a failed disable
+    fn cancel_disable(&self) -> Result<(), AppError> {
+        let (config, kek, keys) = self.take_unlocked()?;
+        self.set_mode(Mode::Unlocked {
+            config,
+            kek,
+            keys,
+            sealing: true,
+        });
+        Ok(())
+    }
+
+    async fn finish_disable(&self) -> Result<(), AppError> {
+        let path = self.config_path().await?;
+        if let Some(config) = self.load_config().await? {
+            if let Some(account) = config.keyring_account.as_deref() {
+                self.keyring_clear(account).await;
+            }
+            self.sys.remove_file(&path).await?;
+        }
+
+        self.set_mode(Mode::Plain);
+        Ok(())
+    }
+}
+
+/// Rewrite all history with the current keys
+async fn rewrite(store: &dyn ConversationStore, threads: &ThreadStore) -> Result<(), AppError> {
+    store.rewrite().await?;
+    threads.rewrite().await
+}
+
+/// Turn encryption on and encrypt existing history
+pub async fn enable_encryption(
+    encryption: &Encryption,
+    store: &dyn ConversationStore,
+    threads: &ThreadStore,
+    source: KeySource,
+    passphrase: Option<&str>,
+) -> Result<(), AppError> {
+    encryption.enable(source, passphrase).await?;
+    rewrite(store, threads).await?;
+
+    log::info!("🔐 Conversation history encrypted ({:?})", source);
+    Ok(())
+}
+
+/// Switch to a new passphrase or keyring key and re-encrypt history with a
+/// fresh data key
+pub async fn rotate_key(
+    encryption: &Encryption,
+    store: &dyn ConversationStore,
+    threads: &ThreadStore,
+    source: KeySource,
+    passphrase: Option<&str>,
+) -> Result<(), AppError> {
+    encryption.rotate(source, passphrase).await?;
+    rewrite(store, threads).await?;
+    encryption.finish_rotation().await?;
+
+    log::info!("🔁 Conversation history key rotated");
+    Ok(())
+}
+
+/// Decrypt history and turn encryption off
+pub async fn disable_encryption(
+    encryption: &Encryption,
+    store: &dyn ConversationStore,
+    threads: &ThreadStore,
+) -> Result<(), AppError> {
+    encryption.begin_disable()?;
+    if let Err(e) = rewrite(store, threads).await {
+        // Whatever was already rewritten stays readable, new records are
+        // sealed again
+        encryption.cancel_disable()?;
+        return Err(e);
+    }
+    encryption.finish_disable().await?;
+
+    log::info!("🔓 Conversation history decrypted");
+    Ok(())
+}
+
+/// Forget keys and the decrypted history held in memory
+pub async fn lock(encryption: &Encryption, store: &dyn ConversationStore) -> Result<(), AppError> {
+    encryption.lock().await?;
+    store.unload().await;
+    Ok(())
+}
+
+pub async fn unlock(
+    encryption: &Encryption,
+    store: &dyn ConversationStore,
+    passphrase: Option<&str>,
+) -> Result<(), AppError> {
+    encryption.unlock(passphrase).await?;
+    store.unload().await;
+    Ok(())
+}
@@ -0,0 +1,311 @@
+// Conversation export
+// Renders selected entries as Markdown, standalone HTML, JSON or plain text.
+// Responses are Markdown already, so HTML and text are derived from it.
+
+use super::{ConversationEntry, ConversationFilter, ConversationStore};
+use crate::error::AppError;
+use crate::system::SystemOps;
+use chrono::TimeZone;
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+
+/// Theme used for code blocks in HTML exports
+const HIGHLIGHT_THEME: &str = "InspiredGitHub";
+/// Link schemes kept as clickable links in HTML exports
+const LINK_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Json,
+    Text,
+}
+
+/// Which entries to export: explicit IDs, or everything in a date range
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportSelection {
+    pub ids: Option<Vec<String>>,
+    /// Inclusive lower bound, ms since epoch
+    pub from: Option<i64>,
+    /// Inclusive upper bound, ms since epoch
+    pub to: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportReport {
+    pub path: PathBuf,
+    pub format: ExportFormat,
+    pub exported: usize,
+}
+
+pub(crate) fn format_time(timestamp: i64) -> String {
+    chrono::Local
+        .timestamp_millis_opt(timestamp)
+        .single()
+        .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+fn tool_names(entry: &ConversationEntry) -> String {
+    entry
+        .tool_calls
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Markdown document; response Markdown (and its code fences) is kept as is
+pub fn to_markdown(entries: &[ConversationEntry]) -> String {
+    let mut out = String::from("# Konverzace s Claude\n");
+
+    for entry in entries {
+        out.push_str(&format!("\n## {}\n\n", format_time(entry.timestamp)));
+        out.push_str("**Dotaz:**\n\n");
+        out.push_str(entry.user_input.trim_end());
+        out.push_str("\n\n**Odpověď:**\n\n");
+        out.push_str(entry.assistant_response.trim_end());
+        out.push('\n');
+
+        if !entry.tool_calls.is_empty() {
+            out.push_str(&format!("\n_Nástroje: {}_\n", tool_names(entry)));
+        }
+    }
+
+    out
+}
+
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme() -> &'static Theme {
+    static THEME: OnceLock<Theme> = OnceLock::new();
+    THEME.get_or_init(|| {
+        let mut themes = ThemeSet::load_defaults().themes;
+        themes
+            .remove(HIGHLIGHT_THEME)
+            .unwrap_or_else(|| themes.into_values().next().unwrap_or_default())
+    })
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn highlight_code(code: &str, language: &str) -> String {
+    let syntaxes = syntax_set();
+    let syntax = syntaxes
+        .find_syntax_by_token(language)
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+
+    syntect::html::highlighted_html_for_string(code, syntaxes, syntax, theme())
+        .unwrap_or_else(|_| format!("<pre><code>{}</code></pre>", escape_html(code)))
+}
+
+/// Whether the link target uses an allowed scheme. Relative targets and
+/// anything like `javascript:` are refused.
+fn is_safe_link(url: &str) -> bool {
+    url.split_once(':').is_some_and(|(scheme, _)| {
+        LINK_SCHEMES
+            .iter()
+            .any(|allowed| scheme.trim().eq_ignore_ascii_case(allowed))
+    })
+}
+
+/// Markdown -> HTML with highlighted code blocks. Raw HTML in the source is
+/// escaped so an exported answer can't run scripts in the browser.
+fn markdown_to_html(markdown: &str) -> String {
+    let mut events = Vec::new();
+    let mut code: Option<(String, String)> = None;
+    // Links don't nest, so one flag is enough to drop the matching end tag
+    let mut dropped_link = false;
+
+    for event in Parser::new_ext(markdown, Options::all()) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().unwrap_or("").to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code = Some((language, String::new()));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((language, text)) = code.take() {
+                    events.push(Event::Html(highlight_code(&text, &language).into()));
+                }
+            }
+            Event::Text(text) if code.is_some() => {
+                if let Some((_, buffer)) = code.as_mut() {
+                    buffer.push_str(&text);
+                }
+            }
+            // Unsafe links keep their text, only the anchor is dropped
+            Event::Start(Tag::Link { ref dest_url, .. }) if !is_safe_link(dest_url) => {
+                dropped_link = true;
+            }
+            Event::End(TagEnd::Link) if dropped_link => dropped_link = false,
+            Event::Html(html) | Event::InlineHtml(html) => events.push(Event::Text(html)),
+            event => events.push(event),
+        }
+    }
+
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events.into_iter());
+    html
+}
+
+/// Standalone HTML page, styles inlined
+pub fn to_html(entries: &[ConversationEntry]) -> String {
+    let mut body = String::new();
+
+    for entry in entries {
+        body.push_str("<article>\n");
+        body.push_str(&format!(
+            "<h2>{}</h2>\n",
+            escape_html(&format_time(entry.timestamp))
+        ));
+        body.push_str("<section class=\"user\">\n<h3>Dotaz</h3>\n");
+        body.push_str(&markdown_to_html(&entry.user_input));
+        body.push_str("</section>\n<section class=\"assistant\">\n<h3>Odpověď</h3>\n");
+        body.push_str(&markdown_to_html(&entry.assistant_response));
+        body.push_str("</section>\n");
+
+        if !entry.tool_calls.is_empty() {
+            body.push_str(&format!(
+                "<p class=\"tools\">Nástroje: {}</p>\n",
+                escape_html(&tool_names(entry))
+            ));
+        }
+        body.push_str("</article>\n");
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="cs">
+<head>
+<meta charset="utf-8">
+<title>Konverzace s Claude</title>
+<style>
+body {{ font-family: system-ui, sans-serif; max-width: 50rem; margin: 2rem auto; padding: 0 1rem; line-height: 1.5; color: #1f2328; }}
+article {{ border-bottom: 1px solid #d0d7de; padding-bottom: 1.5rem; margin-bottom: 1.5rem; }}
+h2 {{ font-size: 0.9rem; color: #656d76; font-weight: normal; }}
+h3 {{ font-size: 1rem; margin-bottom: 0.25rem; }}
+section.user {{ background: #f6f8fa; border-radius: 6px; padding: 0.5rem 1rem; }}
+pre {{ padding: 0.75rem; border-radius: 6px; overflow-x: auto; }}
+code {{ font-family: ui-monospace, monospace; font-size: 0.9em; }}
+.tools {{ font-size: 0.85rem; color: #656d76; font-style: italic; }}
+</style>
+</head>
+<body>
+<h1>Konverzace s Claude</h1>
+{body}</body>
+</html>
+"#
+    )
+}
+
+/// Plain text of a Markdown document; code blocks keep their content
+fn markdown_to_text(markdown: &str) -> String {
+    let mut out = String::new();
+
+    for event in Parser::new_ext(markdown, Options::all()) {
+        match event {
+            Event::Text(text) | Event::Code(text) => out.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => out.push('\n'),
+            Event::Start(Tag::Item) => out.push_str("- "),
+            Event::End(
+                TagEnd::Paragraph
+                | TagEnd::Heading(_)
+                | TagEnd::Item
+                | TagEnd::CodeBlock
+                | TagEnd::TableRow
+                | TagEnd::TableHead,
+            ) if !out.ends_with('\n') => out.push('\n'),
+            Event::End(TagEnd::TableCell) => out.push('\t'),
+            _ => {}
+        }
+    }
+
+    out.trim_end().to_string()
+}
+
+pub fn to_text(entries: &[ConversationEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "[{}]\nDotaz:\n{}\n\nOdpověď:\n{}\n",
+                format_time(entry.timestamp),
+                markdown_to_text(&entry.user_input),
+                markdown_to_text(&entry.assistant_response)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n----------------------------------------\n\n")
+}
+
+pub fn render(entries: &[ConversationEntry], format: ExportFormat) -> Result<String, AppError> {
+    Ok(match format {
+        ExportFormat::Markdown => to_markdown(entries),
+        ExportFormat::Html => to_html(entries),
+        ExportFormat::Json => serde_json::to_string_pretty(entries).map_err(AppError::Json)?,
+        ExportFormat::Text => to_text(entries),
+    })
+}
+
+/// Export the selected entries, oldest first, to `path`
+pub async fn export_conversations(
+    sys: &Arc<dyn SystemOps>,
+    store: &dyn ConversationStore,
+    selection: &ExportSelection,
+    format: ExportFormat,
+    path: &Path,
+) -> Result<ExportReport, AppError> {
+    let filter = ConversationFilter {
+        from: selection.from,
+        to: selection.to,
+        ..Default::default()
+    };
+
+    let entries: Vec<ConversationEntry> = super::load_conversations(store)
+        .await?
+        .into_iter()
+        .filter(|e| filter.matches(e))
+        .filter(|e| selection.ids.as_ref().is_none_or(|ids| ids.contains(&e.id)))
+        .collect();
+
+    if entries.is_empty() {
+        return Err(AppError::Voice(
+            "Výběr neobsahuje žádné konverzace".to_string(),
+        ));
+    }
+
+    let content = render(&entries, format)?;
+    sys.write_atomic(path, &content, false).await?;
+
+    log::info!(
+        "📤 Exported {} conversations to {}",
+        entries.len(),
+        path.display()
+    );
+
+    Ok(ExportReport {
+        path: path.to_path_buf(),
+        format,
+        exported: entries.len(),
+    })
+}
@@ -0,0 +1,346 @@
+// Import of conversation history exported by other clients
+// Supports the claude.ai data export (conversations.json) and generic JSON Lines.
+
+use super::{ConversationEntry, ConversationStore, ThreadStore, ToolCall};
+use crate::chat::transcript::parse_timestamp;
+use crate::error::AppError;
+use crate::system::SystemOps;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    /// `conversations.json` from the claude.ai data export
+    ClaudeAi,
+    /// One JSON object per line
+    Jsonl,
+}
+
+/// What to do with entries whose ID is already in history
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    #[default]
+    Skip,
+    Replace,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportOptions {
+    /// Detected from the content when not given
+    pub format: Option<ImportFormat>,
+    pub conflicts: ConflictPolicy,
+    /// Only compute statistics, don't touch history
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportStats {
+    pub format: ImportFormat,
+    pub dry_run: bool,
+    /// Entries mapped from the file
+    pub found: usize,
+    /// Records that couldn't be mapped (bad JSON, missing text, no answer)
+    pub invalid: usize,
+    /// Entries repeating an ID seen earlier in the same file
+    pub duplicates: usize,
+    /// Entries whose ID is not in history yet
+    pub new: usize,
+    /// Entries whose ID is already in history
+    pub conflicts: usize,
+    /// Entries written to history (0 on a dry run)
+    pub imported: usize,
+}
+
+/// Guess the format: the claude.ai export is a single JSON array
+pub fn detect_format(content: &str) -> ImportFormat {
+    if content.trim_start().starts_with('[') {
+        ImportFormat::ClaudeAi
+    } else {
+        ImportFormat::Jsonl
+    }
+}
+
+#[derive(Deserialize)]
+struct ClaudeAiConversation {
+    #[serde(default)]
+    chat_messages: Vec<ClaudeAiMessage>,
+}
+
+#[derive(Deserialize)]
+struct ClaudeAiMessage {
+    #[serde(default)]
+    uuid: Option<String>,
+    #[serde(default)]
+    sender: String,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    content: Vec<Value>,
+    #[serde(default)]
+    created_at: Option<String>,
+}
+
+impl ClaudeAiMessage {
+    /// Older exports only have `text`, newer ones split it into content blocks
+    fn body(&self) -> String {
+        if !self.text.trim().is_empty() {
+            return self.text.clone();
+        }
+
+        self.content
+            .iter()
+            .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    fn tool_calls(&self) -> impl Iterator<Item = ToolCall> + '_ {
+        self.content
+            .iter()
+            .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+            .map(|b| ToolCall {
+                id: str_field(b, "id").unwrap_or_default(),
+                name: str_field(b, "name").unwrap_or_default(),
+                input: b.get("input").cloned().unwrap_or(Value::Null),
+                result: None,
+                is_error: false,
+            })
+    }
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+/// Map a claude.ai export. Each human message and the assistant replies after it
+/// form one entry identified by the first reply's UUID.
+/// Returns the entries and the number of human messages left without an answer.
+fn parse_claude_ai(content: &str) -> Result<(Vec<ConversationEntry>, usize), AppError> {
+    let conversations: Vec<ClaudeAiConversation> =
+        serde_json::from_str(content).map_err(AppError::Json)?;
+
+    let mut entries = Vec::new();
+    let mut unanswered = 0;
+
+    for conversation in conversations {
+        let mut current: Option<ConversationEntry> = None;
+
+        let mut finish = |entry: Option<ConversationEntry>| match entry {
+            Some(entry) if !entry.id.is_empty() => entries.push(entry),
+            Some(_) => unanswered += 1,
+            None => {}
+        };
+
+        for message in &conversation.chat_messages {
+            let body = message.body();
+
+            if message.sender == "human" {
+                if body.trim().is_empty() {
+                    continue;
+                }
+                finish(current.take());
+                current = Some(ConversationEntry {
+                    id: String::new(),
+                    timestamp: message
+                        .created_at
+                        .as_deref()
+                        .and_then(parse_timestamp)
+                        .unwrap_or_default(),
+                    user_input: body,
+                    assistant_response: String::new(),
+                    voice_used: false,
+                    played_back: false,
+                    starred: false,
+                    tool_calls: Vec::new(),
+                });
+                continue;
+            }
+
+            let Some(entry) = current.as_mut() else {
+                continue;
+            };
+            if entry.id.is_empty() {
+                entry.id = message.uuid.clone().unwrap_or_default();
+            }
+            if !body.trim().is_empty() {
+                if !entry.assistant_response.is_empty() {
+                    entry.assistant_response.push_str("\n\n");
+                }
+                entry.assistant_response.push_str(&body);
+            }
+            entry.tool_calls.extend(message.tool_calls());
+        }
+
+        finish(current);
+    }
+
+    Ok((entries, unanswered))
+}
+
+/// Stable ID for records that don't carry one, so re-importing the same file
+/// hits the same IDs (FNV-1a over timestamp and text)
+fn content_id(timestamp: i64, user_input: &str, response: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in timestamp
+        .to_le_bytes()
+        .iter()
+        .chain(user_input.as_bytes())
+        .chain([0u8].iter())
+        .chain(response.as_bytes())
+    {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("jsonl-{:016x}", hash)
+}
+
+/// Milliseconds from a number (seconds or ms) or an ISO 8601 string
+fn timestamp_value(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) => {
+            let n = n.as_f64()?;
+            // Anything below ~1973 in ms is taken as seconds
+            Some(if n.abs() < 1e11 { n * 1000.0 } else { n } as i64)
+        }
+        Value::String(s) => parse_timestamp(s),
+        _ => None,
+    }
+}
+
+/// First non-empty string among the field aliases
+fn text_field(record: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .filter_map(|k| record.get(*k).and_then(|v| v.as_str()))
+        .find(|s| !s.trim().is_empty())
+        .map(|s| s.to_string())
+}
+
+/// Map one JSONL record. Our own field names are accepted as well as
+/// common aliases (`prompt`/`response`, `question`/`answer`, ...).
+fn parse_jsonl_record(line: &str) -> Option<ConversationEntry> {
+    let record: Value = serde_json::from_str(line).ok()?;
+
+    let user_input = text_field(
+        &record,
+        &["user_input", "prompt", "question", "input", "user"],
+    )?;
+    let assistant_response = text_field(
+        &record,
+        &[
+            "assistant_response",
+            "response",
+            "answer",
+            "output",
+            "assistant",
+        ],
+    )?;
+    let timestamp = ["timestamp", "created_at", "time", "date"]
+        .iter()
+        .find_map(|k| record.get(*k).and_then(timestamp_value))
+        .unwrap_or_default();
+    let id = str_field(&record, "id")
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(|| content_id(timestamp, &user_input, &assistant_response));
+    let flag = |key: &str| record.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+
+    Some(ConversationEntry {
+        id,
+        timestamp,
+        voice_used: flag("voice_used"),
+        played_back: flag("played_back"),
+        starred: flag("starred"),
+        tool_calls: record
+            .get("tool_calls")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default(),
+        user_input,
+        assistant_response,
+    })
+}
+
+fn parse_jsonl(content: &str) -> (Vec<ConversationEntry>, usize) {
+    let mut entries = Vec::new();
+    let mut invalid = 0;
+
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        match parse_jsonl_record(line) {
+            Some(entry) => entries.push(entry),
+            None => invalid += 1,
+        }
+    }
+
+    (entries, invalid)
+}
+
+/// Import a file exported by another client into history
+pub async fn import_file(
+    sys: &Arc<dyn SystemOps>,
+    store: &dyn ConversationStore,
+    threads: &ThreadStore,
+    path: &Path,
+    options: &ImportOptions,
+) -> Result<ImportStats, AppError> {
+    let content = sys.read_to_string(path).await?;
+    let format = options.format.unwrap_or_else(|| detect_format(&content));
+
+    let (parsed, invalid) = match format {
+        ImportFormat::ClaudeAi => parse_claude_ai(&content)?,
+        ImportFormat::Jsonl => parse_jsonl(&content),
+    };
+    let found = parsed.len();
+
+    // First occurrence of an ID wins within the file
+    let mut seen = HashSet::new();
+    let entries: Vec<ConversationEntry> = parsed
+        .into_iter()
+        .filter(|e| seen.insert(e.id.clone()))
+        .collect();
+
+    let stored: HashSet<String> = store.load_all().await?.into_iter().map(|e| e.id).collect();
+    let conflicting = entries.iter().filter(|e| stored.contains(&e.id)).count();
+
+    let mut stats = ImportStats {
+        format,
+        dry_run: options.dry_run,
+        found,
+        invalid,
+        duplicates: found - entries.len(),
+        new: entries.len() - conflicting,
+        conflicts: conflicting,
+        imported: 0,
+    };
+
+    if options.dry_run {
+        return Ok(stats);
+    }
+
+    // A put replaces the stored entry by ID, so nothing is removed first and
+    // a failure midway can't lose the existing entries
+    let (replacing, entries): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .partition(|e| options.conflicts == ConflictPolicy::Replace && stored.contains(&e.id));
+    for entry in replacing {
+        store.insert(entry.clone()).await?;
+        threads.record_entries(&[entry]).await?;
+        stats.imported += 1;
+    }
+    stats.imported += super::import_conversations(store, threads, entries).await?;
+
+    log::info!(
+        "📥 Imported {} of {} entries from {}",
+        stats.imported,
+        found,
+        path.display()
+    );
+    Ok(stats)
+}
@@ -0,0 +1,489 @@
+// Voice module - Conversation history and voice settings management
+// Handles storage of conversations and user preferences for voice features
+
+use crate::error::AppError;
+use crate::schema::{self, Schema};
+use crate::system::SystemOps;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub mod audio;
+pub mod audio_export;
+pub mod catalog;
+pub mod crypto;
+pub mod export;
+pub mod import;
+pub mod models;
+pub mod normalize;
+pub mod profiles;
+pub mod ptt;
+pub mod retention;
+pub mod search;
+pub mod store;
+pub mod stt;
+pub mod threads;
+pub mod tts;
+pub mod vad;
+pub mod validation;
+
+pub use retention::RetentionPolicy;
+pub use search::SearchHit;
+pub use store::{ConversationStore, LogConversationStore};
+pub use threads::ThreadStore;
+
+/// Results returned by `search_conversations` when no limit is given
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+/// Page size when none is given
+const DEFAULT_PAGE_SIZE: usize = 50;
+/// Largest page a single IPC call may return
+const MAX_PAGE_SIZE: usize = 500;
+
+#[cfg(test)]
+#[path = "tests.rs"]
+mod tests;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationEntry {
+    pub id: String,
+    #[serde(default)]
+    pub timestamp: i64,
+    #[serde(default)]
+    pub user_input: String,
+    #[serde(default)]
+    pub assistant_response: String,
+    #[serde(default)]
+    pub voice_used: bool,
+    #[serde(default)]
+    pub played_back: bool,
+    /// Starred entries are exempt from pruning
+    #[serde(default)]
+    pub starred: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+/// Tool call made while producing the response (imported from CLI transcripts)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub input: serde_json::Value,
+    #[serde(default)]
+    pub result: Option<String>,
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+/// Conversation filter; unset fields match everything
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConversationFilter {
+    /// Inclusive lower bound, ms since epoch
+    pub from: Option<i64>,
+    /// Inclusive upper bound, ms since epoch
+    pub to: Option<i64>,
+    pub voice_used: Option<bool>,
+    pub played_back: Option<bool>,
+}
+
+impl ConversationFilter {
+    pub fn matches(&self, entry: &ConversationEntry) -> bool {
+        self.from.is_none_or(|from| entry.timestamp >= from)
+            && self.to.is_none_or(|to| entry.timestamp <= to)
+            && self.voice_used.is_none_or(|v| entry.voice_used == v)
+            && self.played_back.is_none_or(|p| entry.played_back == p)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    NewestFirst,
+    OldestFirst,
+}
+
+/// Position in the timeline to continue from. Entries are ordered by
+/// timestamp and then ID, so the ID keeps paging stable when several
+/// entries share a timestamp. Without an ID the cursor marks the whole
+/// millisecond, e.g. "everything before this timestamp".
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageCursor {
+    pub timestamp: i64,
+    #[serde(default)]
+    pub id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PageRequest {
+    pub limit: Option<usize>,
+    /// Continue after this cursor in the chosen order (older entries for
+    /// `newest_first`, newer ones for `oldest_first`)
+    pub before: Option<PageCursor>,
+    pub order: SortOrder,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationPage {
+    pub entries: Vec<ConversationEntry>,
+    /// Cursor for the next page, `None` on the last one
+    pub next: Option<PageCursor>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    from = "profiles::StoredVoiceSettings",
+    into = "profiles::StoredVoiceSettings"
+)]
+pub struct VoiceSettings {
+    /// Language the user speaks; its profile is used for speech recognition
+    pub input_language: String,
+    pub auto_play: bool,
+    pub history_limit: usize,
+    pub retention: RetentionPolicy,
+    /// Silence detection before transcription
+    pub vad: vad::VadSettings,
+    pub push_to_talk: ptt::PushToTalk,
+    /// How code blocks in responses are spoken
+    pub code_blocks: normalize::CodeBlockMode,
+    /// STT model, TTS voice and speed per language tag
+    pub profiles: BTreeMap<String, profiles::LanguageProfile>,
+    /// Speak each response with the profile of its detected language
+    pub detect_response_language: bool,
+}
+
+impl Default for VoiceSettings {
+    fn default() -> Self {
+        Self {
+            input_language: "cs-CZ".to_string(),
+            auto_play: false,
+            history_limit: 100,
+            retention: RetentionPolicy::default(),
+            vad: vad::VadSettings::default(),
+            push_to_talk: ptt::PushToTalk::default(),
+            code_blocks: normalize::CodeBlockMode::default(),
+            profiles: profiles::default_profiles(),
+            detect_response_language: true,
+        }
+    }
+}
+
+/// Get path to voice data directory
+async fn get_voice_dir(sys: &dyn SystemOps) -> Result<PathBuf, AppError> {
+    let config_dir = sys
+        .config_dir()
+        .ok_or(AppError::Config("Cannot find config directory".to_string()))?;
+
+    let voice_dir = config_dir.join("Claude").join("voice");
+
+    if !sys.exists(&voice_dir).await {
+        sys.create_dir_all(&voice_dir).await?;
+    }
+
+    Ok(voice_dir)
+}
+
+/// Directory with voice models (`$XDG_DATA_HOME/Claude/models`)
+pub(crate) fn get_models_dir(sys: &dyn SystemOps) -> Result<PathBuf, AppError> {
+    let data_dir = sys
+        .env_var("XDG_DATA_HOME")
+        .filter(|d| !d.trim().is_empty())
+        .map(PathBuf::from)
+        .or_else(|| sys.home_dir().map(|h| h.join(".local").join("share")))
+        .ok_or(AppError::Config("Cannot find data directory".to_string()))?;
+
+    Ok(data_dir.join("Claude").join("models"))
+}
+
+/// Get path to voice settings file
+pub(crate) async fn get_settings_path(sys: &dyn SystemOps) -> Result<PathBuf, AppError> {
+    Ok(get_voice_dir(sys).await?.join("voice_settings.json"))
+}
+
+/// Load all conversations, oldest first
+pub async fn load_conversations(
+    store: &dyn ConversationStore,
+) -> Result<Vec<ConversationEntry>, AppError> {
+    let mut conversations = store.load_all().await?;
+
+    // Imported entries can be older than ones saved before them
+    conversations.sort_by_key(|c| c.timestamp);
+
+    Ok(conversations)
+}
+
+/// Drop the oldest entries above the configured history limit.
+/// Starred entries neither count nor get dropped unless retention says so.
+async fn apply_history_limit(
+    sys: &Arc<dyn SystemOps>,
+    store: &dyn ConversationStore,
+) -> Result<usize, AppError> {
+    let settings = load_voice_settings(sys).await?;
+
+    let count = store.count().await?;
+    if count <= settings.history_limit {
+        return Ok(0);
+    }
+
+    let protected = |c: &ConversationEntry| c.starred && settings.retention.keep_pinned;
+    let prunable: Vec<ConversationEntry> = load_conversations(store)
+        .await?
+        .into_iter()
+        .filter(|c| !protected(c))
+        .collect();
+
+    let oldest: Vec<String> = prunable
+        .iter()
+        .take(prunable.len().saturating_sub(settings.history_limit))
+        .map(|c| c.id.clone())
+        .collect();
+
+    store.remove(&oldest).await
+}
+
+/// Save conversation entry together with its thread
+pub async fn save_conversation(
+    sys: &Arc<dyn SystemOps>,
+    store: &dyn ConversationStore,
+    threads: &ThreadStore,
+    entry: ConversationEntry,
+) -> Result<(), AppError> {
+    store.insert(entry.clone()).await?;
+    threads.record_entries(&[entry]).await?;
+
+    // Keep only the most recent entries
+    apply_history_limit(sys, store).await?;
+
+    log::info!("💾 Saved conversation entry: {}", store.count().await?);
+    Ok(())
+}
+
+/// Star or unstar an entry
+pub async fn set_conversation_starred(
+    store: &dyn ConversationStore,
+    id: &str,
+    starred: bool,
+) -> Result<ConversationEntry, AppError> {
+    let mut entry = store
+        .load_all()
+        .await?
+        .into_iter()
+        .find(|e| e.id == id)
+        .ok_or_else(|| AppError::Voice(format!("Konverzace {} neexistuje", id)))?;
+
+    entry.starred = starred;
+    store.insert(entry.clone()).await?;
+    Ok(entry)
+}
+
+/// Merge many entries into history.
+/// Entries whose ID is already stored are skipped; returns how many were added.
+/// The history limit isn't applied here: an import is an explicit request to
+/// keep those entries, and trimming them right away would make the next
+/// import see them as new again.
+pub async fn import_conversations(
+    store: &dyn ConversationStore,
+    threads: &ThreadStore,
+    entries: Vec<ConversationEntry>,
+) -> Result<usize, AppError> {
+    // Only the entries actually added get a thread
+    let mut seen: HashSet<String> = store.load_all().await?.into_iter().map(|e| e.id).collect();
+    let entries: Vec<ConversationEntry> = entries
+        .into_iter()
+        .filter(|e| seen.insert(e.id.clone()))
+        .collect();
+
+    let added = store.insert_new(entries.clone()).await?;
+    threads.record_entries(&entries).await?;
+
+    if added > 0 {
+        log::info!("📥 Imported {} conversation entries", added);
+    }
+
+    Ok(added)
+}
+
+/// Load one page of conversations
+pub async fn load_conversations_page(
+    store: &dyn ConversationStore,
+    filter: &ConversationFilter,
+    request: &PageRequest,
+) -> Result<ConversationPage, AppError> {
+    let limit = request
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    store
+        .page(filter, request.before.as_ref(), request.order, limit)
+        .await
+}
+
+/// Number of conversations matching the filter
+pub async fn count_conversations(
+    store: &dyn ConversationStore,
+    filter: &ConversationFilter,
+) -> Result<usize, AppError> {
+    if filter.is_empty() {
+        return store.count().await;
+    }
+
+    store.count_matching(filter).await
+}
+
+/// Full-text search; best matches first
+pub async fn search_conversations(
+    store: &dyn ConversationStore,
+    query: &str,
+    filter: &ConversationFilter,
+    limit: Option<usize>,
+) -> Result<Vec<SearchHit>, AppError> {
+    store
+        .search(query, filter, limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+        .await
+}
+
+/// Clear all conversations
+pub async fn clear_conversations(store: &dyn ConversationStore) -> Result<(), AppError> {
+    store.clear().await?;
+
+    log::info!("🗑️  Cleared conversation history");
+    Ok(())
+}
+
+/// Format of `voice_settings.json`. Fields added before versioning have
+/// serde defaults, so version 0 only gains the version number.
+pub const SETTINGS_SCHEMA: Schema = Schema {
+    name: "voice_settings.json",
+    migrations: &[schema::add_version],
+};
+
+/// Load voice settings
+pub async fn load_voice_settings(sys: &Arc<dyn SystemOps>) -> Result<VoiceSettings, AppError> {
+    let path = get_settings_path(sys.as_ref()).await?;
+
+    // Return defaults if file doesn't exist
+    Ok(schema::load(sys.as_ref(), &SETTINGS_SCHEMA, &path)
+        .await?
+        .unwrap_or_default())
+}
+
+/// Settings currently on disk, for comparing before a save. Unlike
+/// `load_voice_settings` nothing is migrated or rewritten, and a damaged file
+/// counts as defaults so it can be overwritten.
+async fn saved_voice_settings(sys: &dyn SystemOps) -> VoiceSettings {
+    let Ok(path) = get_settings_path(sys).await else {
+        return VoiceSettings::default();
+    };
+    if !sys.exists(&path).await {
+        return VoiceSettings::default();
+    }
+
+    match sys.read_to_string(&path).await {
+        Ok(content) => SETTINGS_SCHEMA
+            .parse(&content)
+            .map(|(settings, _)| settings)
+            .unwrap_or_default(),
+        Err(_) => VoiceSettings::default(),
+    }
+}
+
+/// Settings as `save_voice_settings` would save them, with speeds clamped
+/// and language tags normalized; nothing is written
+pub async fn validate_voice_settings(
+    sys: &Arc<dyn SystemOps>,
+    settings: &VoiceSettings,
+) -> Result<VoiceSettings, AppError> {
+    let current = saved_voice_settings(sys.as_ref()).await;
+    let catalog = if validation::needs_catalog(settings, &current) {
+        catalog::Catalog::load(sys.as_ref(), settings).await?
+    } else {
+        catalog::Catalog::default()
+    };
+    validation::validate(settings, &current, &catalog).map_err(validation::into_error)
+}
+
+/// Write settings that already went through `validate_voice_settings`
+pub async fn write_voice_settings(
+    sys: &Arc<dyn SystemOps>,
+    settings: &VoiceSettings,
+) -> Result<(), AppError> {
+    let path = get_settings_path(sys.as_ref()).await?;
+
+    let json = SETTINGS_SCHEMA.to_string_pretty(settings)?;
+
+    crate::backup::snapshot(sys, crate::backup::BackupTarget::VoiceSettings, &json).await?;
+    sys.write_atomic(&path, &json, true).await?;
+
+    log::info!("💾 Saved voice settings");
+    Ok(())
+}
+
+/// Save voice settings after validation; returns them as saved, with
+/// speeds clamped and language tags normalized
+pub async fn save_voice_settings(
+    sys: &Arc<dyn SystemOps>,
+    settings: &VoiceSettings,
+) -> Result<VoiceSettings, AppError> {
+    let settings = validate_voice_settings(sys, settings).await?;
+    write_voice_settings(sys, &settings).await?;
+    Ok(settings)
+}
+
+/// Merge a partial settings object over the saved settings and save the
+/// result; returns the settings as saved
+pub async fn update_voice_settings(
+    sys: &Arc<dyn SystemOps>,
+    patch: &serde_json::Value,
+) -> Result<VoiceSettings, AppError> {
+    let current = load_voice_settings(sys).await?;
+    let settings = profiles::apply_patch(&current, patch)?;
+    save_voice_settings(sys, &settings).await
+}
+
+/// Transcribe audio from the webview with the configured whisper model,
+/// skipping silence when VAD is enabled
+pub async fn transcribe_audio(
+    sys: &Arc<dyn SystemOps>,
+    input: &audio::AudioInput,
+) -> Result<stt::Transcript, AppError> {
+    let settings = load_voice_settings(sys).await?;
+    let audio = audio::decode(input)?;
+    stt::transcribe_speech(sys.as_ref(), &settings, &audio).await
+}
+
+/// Synthesize text (Markdown is normalized first) to WAV with the voice and
+/// speed of its language's profile
+pub async fn synthesize_speech(
+    sys: &Arc<dyn SystemOps>,
+    text: &str,
+) -> Result<tts::SpeechAudio, AppError> {
+    let settings = load_voice_settings(sys).await?;
+    let language = settings.response_language(text);
+    let spoken = normalize::prepare(text, &settings, &language).join(" ");
+    let audio = tts::synthesize(sys.as_ref(), &settings, &language, &spoken).await?;
+    tts::SpeechAudio::encode(&audio)
+}
+
+/// Installed TTS voices, marking those used by a language profile
+pub async fn list_tts_voices(
+    sys: &Arc<dyn SystemOps>,
+) -> Result<Vec<catalog::VoiceInfo>, AppError> {
+    let settings = load_voice_settings(sys).await?;
+    catalog::list_voices(sys.as_ref(), &settings).await
+}
+
+/// Installed whisper models, marking those used by a language profile
+pub async fn list_stt_models(
+    sys: &Arc<dyn SystemOps>,
+) -> Result<Vec<catalog::SttModelInfo>, AppError> {
+    let settings = load_voice_settings(sys).await?;
+    catalog::list_stt_models(sys.as_ref(), &settings).await
+}
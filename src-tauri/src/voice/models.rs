@@ -0,0 +1,435 @@
+// Voice model manager
+// Models live in `~/.local/share/Claude/models/{whisper,piper}` and are
+// recorded in `manifest.json` with their SHA-256 checksums. A `ModelFetcher`
+// stages model files for registration; `LocalFetcher` copies them from disk,
+// a mirror fetcher can plug in later without touching the rest.
+
+use crate::error::AppError;
+use crate::schema::{self, Schema};
+use crate::system::{now_millis, SystemOps};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+const MANIFEST: &str = "manifest.json";
+/// Staged files land here before being moved into place
+const STAGING_DIR: &str = ".staging";
+
+/// Format of the manifest
+const MANIFEST_SCHEMA: Schema = Schema {
+    name: MANIFEST,
+    migrations: &[schema::add_version],
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelKind {
+    /// whisper.cpp `ggml-*.bin`
+    Whisper,
+    /// Piper `.onnx` voice with its `.onnx.json` config
+    Piper,
+}
+
+impl ModelKind {
+    fn dir_name(self) -> &'static str {
+        match self {
+            Self::Whisper => "whisper",
+            Self::Piper => "piper",
+        }
+    }
+
+    /// Kind from the main model file's extension
+    fn of(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "bin" => Some(Self::Whisper),
+            "onnx" => Some(Self::Piper),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelFile {
+    /// Path relative to the models directory
+    pub path: String,
+    pub sha256: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelEntry {
+    pub kind: ModelKind,
+    /// Value used in `VoiceSettings`: whisper file name or Piper voice name
+    pub name: String,
+    pub files: Vec<ModelFile>,
+    /// ms since epoch
+    pub registered_at: i64,
+    /// Where the files came from
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+impl ModelEntry {
+    pub fn size(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    models: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerifyResult {
+    pub kind: ModelKind,
+    pub name: String,
+    pub ok: bool,
+    /// Human-readable problems (missing file, checksum mismatch)
+    pub problems: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DiskUsage {
+    pub total_bytes: u64,
+    pub whisper_bytes: u64,
+    pub piper_bytes: u64,
+    /// Files in the model directories that aren't in the manifest
+    pub unregistered_bytes: u64,
+}
+
+/// Puts model files for `source` into the staging directory
+#[async_trait::async_trait]
+pub trait ModelFetcher: Send + Sync {
+    /// Returns the staged files
+    async fn fetch(
+        &self,
+        sys: &dyn SystemOps,
+        source: &str,
+        staging: &Path,
+    ) -> Result<Vec<PathBuf>, AppError>;
+}
+
+/// Copies a model file or a directory of model files from local disk
+pub struct LocalFetcher;
+
+#[async_trait::async_trait]
+impl ModelFetcher for LocalFetcher {
+    async fn fetch(
+        &self,
+        sys: &dyn SystemOps,
+        source: &str,
+        staging: &Path,
+    ) -> Result<Vec<PathBuf>, AppError> {
+        let source = PathBuf::from(source);
+        if !sys.exists(&source).await {
+            return Err(AppError::Voice(format!(
+                "Zdroj modelu neexistuje: {}",
+                source.display()
+            )));
+        }
+
+        // A directory contributes its model files and configs, a file itself
+        let files = match sys.read_dir(&source).await {
+            Ok(children) => children
+                .into_iter()
+                .filter(|c| {
+                    ModelKind::of(c).is_some() || c.extension().is_some_and(|e| e == "json")
+                })
+                .collect(),
+            Err(_) => vec![source.clone()],
+        };
+
+        let mut staged = Vec::new();
+        for file in files {
+            let Some(name) = file.file_name() else {
+                continue;
+            };
+            let target = staging.join(name);
+            sys.copy_file(&file, &target).await?;
+            staged.push(target);
+        }
+        Ok(staged)
+    }
+}
+
+async fn manifest_path(sys: &dyn SystemOps) -> Result<PathBuf, AppError> {
+    Ok(super::get_models_dir(sys)?.join(MANIFEST))
+}
+
+async fn load_manifest(sys: &dyn SystemOps) -> Result<Manifest, AppError> {
+    let path = manifest_path(sys).await?;
+    Ok(schema::load(sys, &MANIFEST_SCHEMA, &path)
+        .await?
+        .unwrap_or_default())
+}
+
+async fn save_manifest(sys: &dyn SystemOps, manifest: &Manifest) -> Result<(), AppError> {
+    let json = MANIFEST_SCHEMA.to_string_pretty(manifest)?;
+    sys.write_atomic(&manifest_path(sys).await?, &json, false)
+        .await
+}
+
+/// Manifest paths must stay inside the models directory
+fn model_file_path(models_dir: &Path, file: &ModelFile) -> Result<PathBuf, AppError> {
+    let path = Path::new(&file.path);
+    if path.as_os_str().is_empty()
+        || !path
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)))
+    {
+        return Err(AppError::Voice(format!(
+            "Neplatná cesta modelu v manifestu: {}",
+            file.path
+        )));
+    }
+    Ok(models_dir.join(path))
+}
+
+fn not_registered(kind: ModelKind, name: &str) -> AppError {
+    AppError::Voice(format!("Model {} ({:?}) není registrovaný", name, kind))
+}
+
+/// Registered models, by kind and name
+pub async fn list_models(sys: &dyn SystemOps) -> Result<Vec<ModelEntry>, AppError> {
+    let mut models = load_manifest(sys).await?.models;
+    models.sort_by(|a, b| (a.kind.dir_name(), &a.name).cmp(&(b.kind.dir_name(), &b.name)));
+    Ok(models)
+}
+
+/// Group staged files into models: each `.bin`/`.onnx` plus its companions
+/// (`<model>.json` for Piper)
+fn group_staged(staged: &[PathBuf]) -> Vec<(ModelKind, String, Vec<PathBuf>)> {
+    staged
+        .iter()
+        .filter_map(|main| {
+            let kind = ModelKind::of(main)?;
+            let file_name = main.file_name()?.to_str()?;
+            let name = match kind {
+                ModelKind::Whisper => file_name.to_string(),
+                ModelKind::Piper => file_name.trim_end_matches(".onnx").to_string(),
+            };
+
+            let mut files = vec![main.clone()];
+            let companion = format!("{}.json", file_name);
+            files.extend(
+                staged
+                    .iter()
+                    .filter(|p| p.file_name().is_some_and(|n| *n == *companion))
+                    .cloned(),
+            );
+            Some((kind, name, files))
+        })
+        .collect()
+}
+
+/// Register models from `source` through the fetcher. With `expected_sha256`
+/// the main file must match it (only when a single model is registered).
+/// Without it the manifest just records whatever was fetched, so `verify`
+/// later catches changes on disk but not a bad or tampered download.
+/// Existing models with the same name are replaced.
+pub async fn register(
+    sys: &dyn SystemOps,
+    fetcher: &dyn ModelFetcher,
+    source: &str,
+    expected_sha256: Option<&str>,
+) -> Result<Vec<ModelEntry>, AppError> {
+    let models_dir = super::get_models_dir(sys)?;
+    let staging = models_dir
+        .join(STAGING_DIR)
+        .join(uuid::Uuid::new_v4().simple().to_string());
+
+    // Files moved into the model directories, removed again on failure
+    let mut moved = Vec::new();
+    let result = async {
+        let staged = fetcher.fetch(sys, source, &staging).await?;
+        let groups = group_staged(&staged);
+        if groups.is_empty() {
+            return Err(AppError::Voice(format!(
+                "V {} není žádný model (.bin nebo .onnx)",
+                source
+            )));
+        }
+        if expected_sha256.is_some() && groups.len() > 1 {
+            return Err(AppError::Voice(
+                "Kontrolní součet lze ověřit jen u jednoho modelu".to_string(),
+            ));
+        }
+
+        // Hash everything before anything is moved into place
+        let mut hashed = Vec::new();
+        for (kind, name, files) in groups {
+            let mut model_files = Vec::new();
+            for file in &files {
+                let sha256 = sys.sha256(file).await?;
+                let size = sys.file_size(file).await?;
+                model_files.push((file.clone(), sha256, size));
+            }
+
+            if let Some(expected) = expected_sha256 {
+                let actual = &model_files[0].1;
+                if !actual.eq_ignore_ascii_case(expected.trim()) {
+                    return Err(AppError::Voice(format!(
+                        "Kontrolní součet modelu {} nesouhlasí: očekáváno {}, spočteno {}",
+                        name, expected, actual
+                    )));
+                }
+            }
+            hashed.push((kind, name, model_files));
+        }
+
+        let mut manifest = load_manifest(sys).await?;
+        let mut registered = Vec::new();
+        for (kind, name, model_files) in hashed {
+            let dir = models_dir.join(kind.dir_name());
+            if !sys.exists(&dir).await {
+                sys.create_dir_all(&dir).await?;
+            }
+
+            let mut files = Vec::new();
+            for (staged_file, sha256, size) in model_files {
+                let file_name = staged_file.file_name().unwrap_or_default();
+                let target = dir.join(file_name);
+                sys.rename(&staged_file, &target).await?;
+                moved.push(target);
+                files.push(ModelFile {
+                    path: format!("{}/{}", kind.dir_name(), file_name.to_string_lossy()),
+                    sha256,
+                    size,
+                });
+            }
+
+            let entry = ModelEntry {
+                kind,
+                name,
+                files,
+                registered_at: now_millis(),
+                source: Some(source.to_string()),
+            };
+            manifest
+                .models
+                .retain(|m| !(m.kind == entry.kind && m.name == entry.name));
+            manifest.models.push(entry.clone());
+            registered.push(entry);
+        }
+
+        save_manifest(sys, &manifest).await?;
+        Ok(registered)
+    }
+    .await;
+
+    if result.is_err() {
+        for path in &moved {
+            if let Err(e) = sys.remove_file(path).await {
+                log::warn!("⚠️  Cannot clean up {}: {}", path.display(), e);
+            }
+        }
+    }
+    if sys.exists(&staging).await {
+        if let Err(e) = sys.remove_dir_all(&staging).await {
+            log::warn!("⚠️  Cannot clean up {}: {}", staging.display(), e);
+        }
+    }
+
+    let registered = result?;
+    log::info!(
+        "📦 Registered {} voice models from {}",
+        registered.len(),
+        source
+    );
+    Ok(registered)
+}
+
+/// Re-hash registered files and compare with the manifest. This proves the
+/// files are unchanged since registration, and that they are the expected
+/// download only for models registered with a checksum.
+pub async fn verify(sys: &dyn SystemOps) -> Result<Vec<VerifyResult>, AppError> {
+    let models_dir = super::get_models_dir(sys)?;
+    let mut results = Vec::new();
+
+    for model in list_models(sys).await? {
+        let mut problems = Vec::new();
+        for file in &model.files {
+            let path = match model_file_path(&models_dir, file) {
+                Ok(path) => path,
+                Err(e) => {
+                    problems.push(e.to_string());
+                    continue;
+                }
+            };
+            if !sys.exists(&path).await {
+                problems.push(format!("Chybí soubor {}", file.path));
+                continue;
+            }
+            if sys.sha256(&path).await? != file.sha256 {
+                problems.push(format!("Kontrolní součet {} nesouhlasí", file.path));
+            }
+        }
+
+        results.push(VerifyResult {
+            kind: model.kind,
+            name: model.name,
+            ok: problems.is_empty(),
+            problems,
+        });
+    }
+    Ok(results)
+}
+
+/// Space taken by the model directories
+pub async fn disk_usage(sys: &dyn SystemOps) -> Result<DiskUsage, AppError> {
+    let models_dir = super::get_models_dir(sys)?;
+    let registered: HashSet<PathBuf> = list_models(sys)
+        .await?
+        .iter()
+        .flat_map(|m| m.files.iter().map(|f| models_dir.join(&f.path)))
+        .collect();
+
+    let mut usage = DiskUsage::default();
+    for kind in [ModelKind::Whisper, ModelKind::Piper] {
+        let Ok(files) = sys.read_dir(&models_dir.join(kind.dir_name())).await else {
+            continue;
+        };
+        for file in files {
+            let size = sys.file_size(&file).await.unwrap_or(0);
+            match kind {
+                ModelKind::Whisper => usage.whisper_bytes += size,
+                ModelKind::Piper => usage.piper_bytes += size,
+            }
+            if !registered.contains(&file) {
+                usage.unregistered_bytes += size;
+            }
+        }
+    }
+
+    usage.total_bytes = usage.whisper_bytes + usage.piper_bytes;
+    Ok(usage)
+}
+
+/// Delete a registered model's files and its manifest entry
+pub async fn delete(sys: &dyn SystemOps, kind: ModelKind, name: &str) -> Result<(), AppError> {
+    let models_dir = super::get_models_dir(sys)?;
+    let mut manifest = load_manifest(sys).await?;
+    let position = manifest
+        .models
+        .iter()
+        .position(|m| m.kind == kind && m.name == name)
+        .ok_or_else(|| not_registered(kind, name))?;
+
+    let model = manifest.models.remove(position);
+    let paths = model
+        .files
+        .iter()
+        .map(|f| model_file_path(&models_dir, f))
+        .collect::<Result<Vec<_>, _>>()?;
+    for path in paths {
+        if sys.exists(&path).await {
+            sys.remove_file(&path).await?;
+        }
+    }
+    save_manifest(sys, &manifest).await?;
+
+    log::info!("🗑️  Deleted voice model {} ({} bytes)", name, model.size());
+    Ok(())
+}
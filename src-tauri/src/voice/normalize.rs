@@ -0,0 +1,735 @@
+// Text normalization for speech
+// Assistant responses are Markdown: formatting is dropped, headings and list
+// items become sentences, code blocks are skipped, summarized or read
+// depending on `VoiceSettings::code_blocks`. Numbers, units and common
+// abbreviations are then spelled out in Czech or English and the result is
+// split into sentence-sized chunks for the TTS engine.
+
+use super::{tts, VoiceSettings};
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+
+/// What to do with fenced and indented code blocks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeBlockMode {
+    /// Leave them out
+    Skip,
+    /// Say that there is code, in which language and how long
+    #[default]
+    Summarize,
+    /// Read every line
+    Read,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeechLanguage {
+    Cs,
+    En,
+}
+
+impl SpeechLanguage {
+    /// Czech (and Slovak) tags read as Czech, everything else as English
+    pub fn from_tag(tag: &str) -> Self {
+        let primary = tag.split(['-', '_']).next().unwrap_or_default();
+        match primary.to_ascii_lowercase().as_str() {
+            "cs" | "sk" => Self::Cs,
+            _ => Self::En,
+        }
+    }
+}
+
+/// Full pipeline: Markdown to spoken text in `language` (a tag such as
+/// "en-US"), split into chunks
+pub fn prepare(text: &str, settings: &VoiceSettings, language: &str) -> Vec<String> {
+    let language = SpeechLanguage::from_tag(language);
+    let spoken = markdown_to_speech(text, language, settings.code_blocks);
+    tts::split_sentences(&expand(&spoken, language))
+}
+
+/// Close the current sentence so the TTS engine pauses after it
+fn end_sentence(out: &mut String) {
+    out.truncate(out.trim_end().len());
+    match out.chars().last() {
+        None => return,
+        Some(c) if !".!?…:;".contains(c) => out.push('.'),
+        Some(_) => {}
+    }
+    out.push('\n');
+}
+
+fn summarize_code(info: &str, code: &str, language: SpeechLanguage) -> String {
+    let name = info
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .next()
+        .unwrap_or_default();
+    let lines = code.lines().filter(|l| !l.trim().is_empty()).count();
+
+    let lines_en = if lines == 1 { "line" } else { "lines" };
+    match (language, name.is_empty()) {
+        (SpeechLanguage::Cs, false) => {
+            format!("Ukázka kódu v jazyce {}, počet řádků: {}.", name, lines)
+        }
+        (SpeechLanguage::Cs, true) => format!("Ukázka kódu, počet řádků: {}.", lines),
+        (SpeechLanguage::En, false) => {
+            format!("Code sample in {}, {} {}.", name, lines, lines_en)
+        }
+        (SpeechLanguage::En, true) => format!("Code sample, {} {}.", lines, lines_en),
+    }
+}
+
+/// Plain text for speech: no Markdown syntax, one sentence or block per line
+pub fn markdown_to_speech(markdown: &str, language: SpeechLanguage, code: CodeBlockMode) -> String {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut out = String::new();
+    // Info string and collected text of the code block being parsed
+    let mut code_block: Option<(String, String)> = None;
+
+    for event in Parser::new_ext(markdown, options) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                end_sentence(&mut out);
+                let info = match kind {
+                    CodeBlockKind::Fenced(info) => info.to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code_block = Some((info, String::new()));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                let Some((info, text)) = code_block.take() else {
+                    continue;
+                };
+                match code {
+                    CodeBlockMode::Skip => {}
+                    CodeBlockMode::Summarize => {
+                        out.push_str(&summarize_code(&info, &text, language));
+                        end_sentence(&mut out);
+                    }
+                    CodeBlockMode::Read => {
+                        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+                            out.push_str(line.trim());
+                            end_sentence(&mut out);
+                        }
+                    }
+                }
+            }
+            Event::Text(text) => match code_block.as_mut() {
+                Some((_, code)) => code.push_str(&text),
+                None => out.push_str(&text),
+            },
+            Event::Code(text) => out.push_str(&text),
+            Event::SoftBreak => out.push(' '),
+            Event::HardBreak => out.push('\n'),
+            Event::Start(Tag::Item) | Event::Rule => end_sentence(&mut out),
+            Event::End(TagEnd::TableCell) => out.push_str(", "),
+            Event::End(TagEnd::TableRow | TagEnd::TableHead) => {
+                out.truncate(out.trim_end_matches([',', ' ']).len());
+                end_sentence(&mut out);
+            }
+            Event::End(
+                TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item | TagEnd::BlockQuote(_),
+            ) => end_sentence(&mut out),
+            // HTML, footnote references and task markers aren't spoken
+            _ => {}
+        }
+    }
+
+    out.trim_end().to_string()
+}
+
+/// Grammatical gender of the counted noun; Czech "one" and "two" agree with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Gender {
+    Masculine,
+    Feminine,
+    Neuter,
+    /// Plain counting: "jedna, dva, tři"
+    Counting,
+}
+
+const CS_SMALL: [&str; 20] = [
+    "nula",
+    "jedna",
+    "dva",
+    "tři",
+    "čtyři",
+    "pět",
+    "šest",
+    "sedm",
+    "osm",
+    "devět",
+    "deset",
+    "jedenáct",
+    "dvanáct",
+    "třináct",
+    "čtrnáct",
+    "patnáct",
+    "šestnáct",
+    "sedmnáct",
+    "osmnáct",
+    "devatenáct",
+];
+const CS_TENS: [&str; 10] = [
+    "",
+    "",
+    "dvacet",
+    "třicet",
+    "čtyřicet",
+    "padesát",
+    "šedesát",
+    "sedmdesát",
+    "osmdesát",
+    "devadesát",
+];
+const CS_HUNDREDS: [&str; 10] = [
+    "",
+    "sto",
+    "dvě stě",
+    "tři sta",
+    "čtyři sta",
+    "pět set",
+    "šest set",
+    "sedm set",
+    "osm set",
+    "devět set",
+];
+/// Scale nouns with their gender and forms for 1, 2-4 and 5+
+const CS_SCALES: [(u64, Gender, [&str; 3]); 4] = [
+    (
+        1_000_000_000_000,
+        Gender::Masculine,
+        ["bilion", "biliony", "bilionů"],
+    ),
+    (
+        1_000_000_000,
+        Gender::Feminine,
+        ["miliarda", "miliardy", "miliard"],
+    ),
+    (
+        1_000_000,
+        Gender::Masculine,
+        ["milion", "miliony", "milionů"],
+    ),
+    (1_000, Gender::Masculine, ["tisíc", "tisíce", "tisíc"]),
+];
+
+const EN_SMALL: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+const EN_TENS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+const EN_SCALES: [(u64, &str); 4] = [
+    (1_000_000_000_000, "trillion"),
+    (1_000_000_000, "billion"),
+    (1_000_000, "million"),
+    (1_000, "thousand"),
+];
+
+/// Longer numbers are read digit by digit
+const MAX_NUMBER_DIGITS: usize = 15;
+
+/// Index of the Czech plural form: 1, 2-4, 5+
+fn cs_form(count: u64) -> usize {
+    match count {
+        1 => 0,
+        2..=4 => 1,
+        _ => 2,
+    }
+}
+
+fn cs_small(n: u64, gender: Gender) -> &'static str {
+    match (n, gender) {
+        (1, Gender::Masculine) => "jeden",
+        (1, Gender::Neuter) => "jedno",
+        (2, Gender::Feminine | Gender::Neuter) => "dvě",
+        _ => CS_SMALL[n as usize],
+    }
+}
+
+fn cs_below_thousand(n: u64, gender: Gender, parts: &mut Vec<&'static str>) {
+    let (hundreds, rest) = (n / 100, n % 100);
+    if hundreds > 0 {
+        parts.push(CS_HUNDREDS[hundreds as usize]);
+    }
+    match rest {
+        0 => {}
+        1..=19 => parts.push(cs_small(rest, gender)),
+        _ => {
+            parts.push(CS_TENS[(rest / 10) as usize]);
+            if rest % 10 > 0 {
+                parts.push(cs_small(rest % 10, gender));
+            }
+        }
+    }
+}
+
+fn cs_number(n: u64, gender: Gender) -> String {
+    if n == 0 {
+        return CS_SMALL[0].to_string();
+    }
+
+    let mut parts = Vec::new();
+    for (scale, scale_gender, forms) in CS_SCALES {
+        let count = n / scale % 1000;
+        match count {
+            0 => {}
+            1 => parts.push(forms[0]),
+            _ => {
+                cs_below_thousand(count, scale_gender, &mut parts);
+                parts.push(forms[cs_form(count)]);
+            }
+        }
+    }
+    cs_below_thousand(n % 1000, gender, &mut parts);
+    parts.join(" ")
+}
+
+fn en_below_thousand(n: u64, parts: &mut Vec<String>) {
+    let (hundreds, rest) = (n / 100, n % 100);
+    if hundreds > 0 {
+        parts.push(format!("{} hundred", EN_SMALL[hundreds as usize]));
+    }
+    match rest {
+        0 => {}
+        1..=19 => parts.push(EN_SMALL[rest as usize].to_string()),
+        _ if rest % 10 == 0 => parts.push(EN_TENS[(rest / 10) as usize].to_string()),
+        _ => parts.push(format!(
+            "{}-{}",
+            EN_TENS[(rest / 10) as usize],
+            EN_SMALL[(rest % 10) as usize]
+        )),
+    }
+}
+
+fn en_number(n: u64) -> String {
+    if n == 0 {
+        return EN_SMALL[0].to_string();
+    }
+
+    let mut parts = Vec::new();
+    for (scale, name) in EN_SCALES {
+        let count = n / scale % 1000;
+        if count > 0 {
+            en_below_thousand(count, &mut parts);
+            parts.push(name.to_string());
+        }
+    }
+    en_below_thousand(n % 1000, &mut parts);
+    parts.join(" ")
+}
+
+/// Whole number in words
+pub fn number_to_words(n: u64, language: SpeechLanguage) -> String {
+    match language {
+        SpeechLanguage::Cs => cs_number(n, Gender::Counting),
+        SpeechLanguage::En => en_number(n),
+    }
+}
+
+fn spell_digits(digits: &str, language: SpeechLanguage) -> String {
+    digits
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .map(|d| number_to_words(u64::from(d), language))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Integer in words; leading zeros and very long numbers are spelled out
+fn integer_words(digits: &str, language: SpeechLanguage, gender: Gender) -> String {
+    let spell = digits.len() > MAX_NUMBER_DIGITS || (digits.len() > 1 && digits.starts_with('0'));
+    match digits.parse::<u64>() {
+        Ok(n) if !spell => match language {
+            SpeechLanguage::Cs => cs_number(n, gender),
+            SpeechLanguage::En => en_number(n),
+        },
+        _ => spell_digits(digits, language),
+    }
+}
+
+struct Unit {
+    symbol: &'static str,
+    /// Forms for 1, 2-4, 5+ and decimals
+    cs: [&'static str; 4],
+    cs_gender: Gender,
+    /// Singular and plural
+    en: [&'static str; 2],
+}
+
+const fn unit(
+    symbol: &'static str,
+    cs: [&'static str; 4],
+    cs_gender: Gender,
+    en: [&'static str; 2],
+) -> Unit {
+    Unit {
+        symbol,
+        cs,
+        cs_gender,
+        en,
+    }
+}
+
+const UNITS: &[Unit] = &[
+    unit(
+        "%",
+        ["procento", "procenta", "procent", "procenta"],
+        Gender::Neuter,
+        ["percent", "percent"],
+    ),
+    unit(
+        "°C",
+        [
+            "stupeň Celsia",
+            "stupně Celsia",
+            "stupňů Celsia",
+            "stupně Celsia",
+        ],
+        Gender::Masculine,
+        ["degree Celsius", "degrees Celsius"],
+    ),
+    unit(
+        "km",
+        ["kilometr", "kilometry", "kilometrů", "kilometru"],
+        Gender::Masculine,
+        ["kilometer", "kilometers"],
+    ),
+    unit(
+        "cm",
+        ["centimetr", "centimetry", "centimetrů", "centimetru"],
+        Gender::Masculine,
+        ["centimeter", "centimeters"],
+    ),
+    unit(
+        "mm",
+        ["milimetr", "milimetry", "milimetrů", "milimetru"],
+        Gender::Masculine,
+        ["millimeter", "millimeters"],
+    ),
+    unit(
+        "m",
+        ["metr", "metry", "metrů", "metru"],
+        Gender::Masculine,
+        ["meter", "meters"],
+    ),
+    unit(
+        "kg",
+        ["kilogram", "kilogramy", "kilogramů", "kilogramu"],
+        Gender::Masculine,
+        ["kilogram", "kilograms"],
+    ),
+    unit(
+        "g",
+        ["gram", "gramy", "gramů", "gramu"],
+        Gender::Masculine,
+        ["gram", "grams"],
+    ),
+    unit(
+        "ms",
+        ["milisekunda", "milisekundy", "milisekund", "milisekundy"],
+        Gender::Feminine,
+        ["millisecond", "milliseconds"],
+    ),
+    unit(
+        "s",
+        ["sekunda", "sekundy", "sekund", "sekundy"],
+        Gender::Feminine,
+        ["second", "seconds"],
+    ),
+    unit(
+        "min",
+        ["minuta", "minuty", "minut", "minuty"],
+        Gender::Feminine,
+        ["minute", "minutes"],
+    ),
+    unit(
+        "h",
+        ["hodina", "hodiny", "hodin", "hodiny"],
+        Gender::Feminine,
+        ["hour", "hours"],
+    ),
+    unit(
+        "kB",
+        ["kilobajt", "kilobajty", "kilobajtů", "kilobajtu"],
+        Gender::Masculine,
+        ["kilobyte", "kilobytes"],
+    ),
+    unit(
+        "KB",
+        ["kilobajt", "kilobajty", "kilobajtů", "kilobajtu"],
+        Gender::Masculine,
+        ["kilobyte", "kilobytes"],
+    ),
+    unit(
+        "MB",
+        ["megabajt", "megabajty", "megabajtů", "megabajtu"],
+        Gender::Masculine,
+        ["megabyte", "megabytes"],
+    ),
+    unit(
+        "GB",
+        ["gigabajt", "gigabajty", "gigabajtů", "gigabajtu"],
+        Gender::Masculine,
+        ["gigabyte", "gigabytes"],
+    ),
+    unit(
+        "TB",
+        ["terabajt", "terabajty", "terabajtů", "terabajtu"],
+        Gender::Masculine,
+        ["terabyte", "terabytes"],
+    ),
+    unit(
+        "px",
+        ["pixel", "pixely", "pixelů", "pixelu"],
+        Gender::Masculine,
+        ["pixel", "pixels"],
+    ),
+    unit(
+        "Kč",
+        ["koruna", "koruny", "korun", "koruny"],
+        Gender::Feminine,
+        ["crown", "crowns"],
+    ),
+    unit(
+        "€",
+        ["euro", "eura", "eur", "eura"],
+        Gender::Neuter,
+        ["euro", "euros"],
+    ),
+];
+
+/// Abbreviation, spoken form, and whether it can end a sentence (titles like
+/// "Dr." are followed by a capitalized name instead)
+type Abbreviation = (&'static str, &'static str, bool);
+
+const CS_ABBREVIATIONS: &[Abbreviation] = &[
+    ("např.", "například", false),
+    ("tzn.", "to znamená", false),
+    ("tj.", "to jest", false),
+    ("atd.", "a tak dále", true),
+    ("apod.", "a podobně", true),
+    ("resp.", "respektive", false),
+    ("popř.", "popřípadě", false),
+    ("tzv.", "takzvaný", false),
+    ("mj.", "mimo jiné", false),
+    ("cca", "cirka", false),
+    ("str.", "strana", true),
+    ("č.", "číslo", false),
+    ("&", "a", false),
+];
+
+const EN_ABBREVIATIONS: &[Abbreviation] = &[
+    ("e.g.", "for example", false),
+    ("i.e.", "that is", false),
+    ("etc.", "et cetera", true),
+    ("vs.", "versus", false),
+    ("approx.", "approximately", false),
+    ("Dr.", "Doctor", false),
+    ("Mr.", "Mister", false),
+    ("Mrs.", "Missus", false),
+    ("&", "and", false),
+];
+
+fn starts_with(chars: &[char], prefix: &str) -> bool {
+    prefix
+        .chars()
+        .enumerate()
+        .all(|(index, p)| chars.get(index) == Some(&p))
+}
+
+fn is_boundary(chars: &[char], index: usize) -> bool {
+    chars.get(index).is_none_or(|c| !c.is_alphanumeric())
+}
+
+/// Abbreviation at the start of `chars`: (chars consumed, spoken form)
+fn abbreviation_at(chars: &[char], language: SpeechLanguage) -> Option<(usize, String)> {
+    let table = match language {
+        SpeechLanguage::Cs => CS_ABBREVIATIONS,
+        SpeechLanguage::En => EN_ABBREVIATIONS,
+    };
+    let (abbreviation, spoken, can_end) = table.iter().find(|(abbreviation, _, _)| {
+        starts_with(chars, abbreviation) && is_boundary(chars, abbreviation.chars().count())
+    })?;
+
+    let len = abbreviation.chars().count();
+    let mut spoken = spoken.to_string();
+    // "... atd." at the end of a sentence still ends it
+    let ends_sentence = match chars.get(len) {
+        None | Some('\n') => true,
+        Some(c) if c.is_whitespace() => chars.get(len + 1).is_some_and(|n| n.is_uppercase()),
+        Some(_) => false,
+    };
+    if *can_end && ends_sentence {
+        spoken.push('.');
+    }
+    Some((len, spoken))
+}
+
+/// Unit right after a number, with at most one space in between (single
+/// letter units must be attached: "5 s" may be the Czech preposition)
+fn unit_at(chars: &[char]) -> Option<(usize, &'static Unit)> {
+    let gap = usize::from(matches!(chars.first(), Some(' ' | '\u{a0}')));
+    UNITS
+        .iter()
+        .filter(|u| gap == 0 || u.symbol.len() > 1 || !u.symbol.starts_with(char::is_alphabetic))
+        .find(|u| {
+            let len = u.symbol.chars().count();
+            starts_with(&chars[gap..], u.symbol) && is_boundary(chars, gap + len)
+        })
+        .map(|u| (gap + u.symbol.chars().count(), u))
+}
+
+/// Number (with an optional unit) at the start of `chars`: (chars consumed,
+/// spoken form)
+fn number_at(chars: &[char], language: SpeechLanguage) -> (usize, String) {
+    // Digit groups and the separators between them
+    let mut groups = vec![String::new()];
+    let mut separators = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        if c.is_ascii_digit() {
+            groups.last_mut().unwrap().push(c);
+        } else if (c == '.' || c == ',') && chars.get(index + 1).is_some_and(char::is_ascii_digit) {
+            separators.push(c);
+            groups.push(String::new());
+        } else {
+            break;
+        }
+        index += 1;
+    }
+
+    // English "1,000,000" groups thousands
+    if language == SpeechLanguage::En {
+        let mut merged = vec![groups[0].clone()];
+        let mut kept = Vec::new();
+        for (separator, group) in separators.iter().zip(&groups[1..]) {
+            if *separator == ',' && group.len() == 3 && kept.is_empty() {
+                merged.last_mut().unwrap().push_str(group);
+            } else {
+                kept.push(*separator);
+                merged.push(group.clone());
+            }
+        }
+        groups = merged;
+        separators = kept;
+    }
+
+    let (whole, fraction) = match (separators.as_slice(), language) {
+        ([], _) => (groups[0].as_str(), None),
+        (['.'], _) | ([','], SpeechLanguage::Cs) => (groups[0].as_str(), Some(groups[1].as_str())),
+        _ => {
+            // Versions, dates and the like: read the parts one by one
+            let dot = match language {
+                SpeechLanguage::Cs => " tečka ",
+                SpeechLanguage::En => " dot ",
+            };
+            let spoken = groups
+                .iter()
+                .map(|g| integer_words(g, language, Gender::Counting))
+                .collect::<Vec<_>>()
+                .join(dot);
+            return (index, spoken);
+        }
+    };
+
+    let unit = unit_at(&chars[index..]);
+    let value: u64 = whole.parse().unwrap_or(u64::MAX);
+    let mut spoken = match (fraction, language) {
+        (None, _) => integer_words(
+            whole,
+            language,
+            unit.map_or(Gender::Counting, |(_, u)| u.cs_gender),
+        ),
+        (Some(fraction), SpeechLanguage::Cs) => {
+            let whole_form = ["celá", "celé", "celých"][cs_form(value)];
+            format!(
+                "{} {} {}",
+                integer_words(whole, language, Gender::Feminine),
+                whole_form,
+                integer_words(fraction, language, Gender::Counting)
+            )
+        }
+        (Some(fraction), SpeechLanguage::En) => format!(
+            "{} point {}",
+            integer_words(whole, language, Gender::Counting),
+            spell_digits(fraction, language)
+        ),
+    };
+
+    if let Some((len, unit)) = unit {
+        let name = match language {
+            SpeechLanguage::Cs if fraction.is_some() => unit.cs[3],
+            SpeechLanguage::Cs => unit.cs[cs_form(value)],
+            SpeechLanguage::En if fraction.is_none() && value == 1 => unit.en[0],
+            SpeechLanguage::En => unit.en[1],
+        };
+        spoken.push(' ');
+        spoken.push_str(name);
+        index += len;
+    }
+    (index, spoken)
+}
+
+/// Spell out numbers, units and abbreviations
+pub fn expand(text: &str, language: SpeechLanguage) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+        // Numbers glued to words ("mp3", "v2") are left to the engine
+        let word_start = index == 0 || !chars[index - 1].is_alphanumeric();
+
+        if word_start {
+            if let Some((len, spoken)) = abbreviation_at(&chars[index..], language) {
+                out.push_str(&spoken);
+                index += len;
+                continue;
+            }
+            if c == '-'
+                && (index == 0 || chars[index - 1].is_whitespace())
+                && chars.get(index + 1).is_some_and(char::is_ascii_digit)
+            {
+                out.push_str(match language {
+                    SpeechLanguage::Cs => "mínus ",
+                    SpeechLanguage::En => "minus ",
+                });
+                index += 1;
+                continue;
+            }
+            if c.is_ascii_digit() {
+                let (len, spoken) = number_at(&chars[index..], language);
+                out.push_str(&spoken);
+                index += len;
+                continue;
+            }
+        }
+
+        out.push(c);
+        index += 1;
+    }
+    out
+}
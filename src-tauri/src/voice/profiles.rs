@@ -0,0 +1,287 @@
+// Per-language voice profiles
+// Each language tag ("cs-CZ", "en-US") has its own whisper model, TTS voice
+// and speed. Speech recognition uses the profile of `input_language`, speech
+// output the profile of the response's detected language.
+//
+// Settings are stored with the input-language profile mirrored into the old
+// flat `output_voice` / `output_speed` / `stt_model` fields, so files and
+// clients from before profiles keep working: when present, the flat fields
+// win for the input-language profile. Clients may also save just the fields
+// they know about; `apply_patch` merges them over the current settings.
+
+use super::normalize::{self, CodeBlockMode, SpeechLanguage};
+use super::ptt::PushToTalk;
+use super::retention::RetentionPolicy;
+use super::vad::VadSettings;
+use super::VoiceSettings;
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LanguageProfile {
+    /// whisper.cpp model: file name in the models directory or absolute path
+    pub stt_model: String,
+    /// "piper:<model>", "espeak:<voice>" or "default"
+    pub output_voice: String,
+    pub output_speed: f32,
+}
+
+impl Default for LanguageProfile {
+    fn default() -> Self {
+        Self {
+            stt_model: "ggml-base.bin".to_string(),
+            output_voice: "default".to_string(),
+            output_speed: 1.0,
+        }
+    }
+}
+
+/// Czech and English with the default voices
+pub fn default_profiles() -> BTreeMap<String, LanguageProfile> {
+    ["cs-CZ", "en-US"]
+        .into_iter()
+        .map(|tag| (tag.to_string(), LanguageProfile::default()))
+        .collect()
+}
+
+fn primary_subtag(tag: &str) -> String {
+    tag.split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// ISO 639-1 code of a language tag ("cs-CZ" -> "cs"), as whisper and
+/// espeak-ng take it; "auto" when the tag is empty
+pub fn language_code(tag: &str) -> String {
+    let primary = primary_subtag(tag.trim());
+    if primary.is_empty() {
+        "auto".to_string()
+    } else {
+        primary
+    }
+}
+
+impl VoiceSettings {
+    /// Profile for a language: exact tag, then the same primary language
+    /// ("en-GB" uses "en-US"), then the input language's profile
+    pub fn profile(&self, language: &str) -> LanguageProfile {
+        let primary = primary_subtag(language);
+        let find = |matches: &dyn Fn(&str) -> bool| {
+            self.profiles
+                .iter()
+                .find(|(tag, _)| matches(tag))
+                .map(|(_, p)| p.clone())
+        };
+
+        find(&|tag| tag.eq_ignore_ascii_case(language))
+            .or_else(|| find(&|tag| primary_subtag(tag) == primary))
+            .or_else(|| find(&|tag| tag.eq_ignore_ascii_case(&self.input_language)))
+            .unwrap_or_default()
+    }
+
+    /// Language to speak a response in: its detected language when it has a
+    /// profile, the input language otherwise
+    pub fn response_language(&self, text: &str) -> String {
+        if !self.detect_response_language || self.profiles.len() < 2 {
+            return self.input_language.clone();
+        }
+        let candidates: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+        detect_language(text, &candidates)
+            .map(str::to_string)
+            .unwrap_or_else(|| self.input_language.clone())
+    }
+}
+
+const CS_WORDS: &[&str] = &[
+    "a", "je", "se", "na", "to", "že", "v", "s", "z", "do", "pro", "jak", "ale", "nebo", "jsou",
+    "není", "také", "by", "si", "když", "který", "která", "které", "co", "už", "jako", "tak",
+];
+const EN_WORDS: &[&str] = &[
+    "the", "and", "is", "are", "to", "of", "in", "that", "it", "for", "with", "on", "as", "this",
+    "be", "you", "not", "or", "can", "will", "an", "if", "have",
+];
+const DE_WORDS: &[&str] = &[
+    "der", "die", "das", "und", "ist", "nicht", "ein", "eine", "zu", "mit", "auf", "für", "sie",
+    "es", "den", "von", "auch", "sich", "wird",
+];
+/// Letters that only occur in Czech among the supported languages
+const CS_LETTERS: &str = "ěščřžůťďň";
+
+/// Guess which of the candidate tags the text is written in, from common
+/// words and characteristic letters. Code blocks are ignored. `None` when
+/// nothing matches or the guess is a tie.
+pub fn detect_language<'a>(text: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let plain =
+        normalize::markdown_to_speech(text, SpeechLanguage::En, CodeBlockMode::Skip).to_lowercase();
+    let words: Vec<&str> = plain
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let score = |tag: &str| -> usize {
+        let (stopwords, letters) = match primary_subtag(tag).as_str() {
+            "cs" | "sk" => (CS_WORDS, CS_LETTERS),
+            "en" => (EN_WORDS, ""),
+            "de" => (DE_WORDS, "äöüß"),
+            _ => return 0,
+        };
+        words.iter().filter(|w| stopwords.contains(w)).count()
+            + plain.chars().filter(|c| letters.contains(*c)).count()
+    };
+
+    let scored: Vec<(&str, usize)> = candidates.iter().map(|tag| (*tag, score(tag))).collect();
+    let best = scored.iter().map(|(_, s)| *s).max().filter(|s| *s > 0)?;
+    let mut winners = scored.iter().filter(|(_, s)| *s == best);
+    match (winners.next(), winners.next()) {
+        (Some((tag, _)), None) => Some(tag),
+        _ => None,
+    }
+}
+
+/// On-disk and wire form of `VoiceSettings`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StoredVoiceSettings {
+    input_language: String,
+    /// Mirror of the input-language profile
+    output_voice: Option<String>,
+    output_speed: Option<f32>,
+    stt_model: Option<String>,
+    auto_play: bool,
+    history_limit: usize,
+    retention: RetentionPolicy,
+    vad: VadSettings,
+    push_to_talk: PushToTalk,
+    code_blocks: CodeBlockMode,
+    /// Missing in files from before profiles
+    profiles: Option<BTreeMap<String, LanguageProfile>>,
+    detect_response_language: bool,
+}
+
+impl Default for StoredVoiceSettings {
+    fn default() -> Self {
+        // Flat fields and profiles stay unset so a missing key can be told apart
+        let defaults = VoiceSettings::default();
+        Self {
+            input_language: defaults.input_language,
+            output_voice: None,
+            output_speed: None,
+            stt_model: None,
+            auto_play: defaults.auto_play,
+            history_limit: defaults.history_limit,
+            retention: defaults.retention,
+            vad: defaults.vad,
+            push_to_talk: defaults.push_to_talk,
+            code_blocks: defaults.code_blocks,
+            profiles: None,
+            detect_response_language: defaults.detect_response_language,
+        }
+    }
+}
+
+impl From<StoredVoiceSettings> for VoiceSettings {
+    fn from(stored: StoredVoiceSettings) -> Self {
+        let flat = stored.output_voice.is_some()
+            || stored.output_speed.is_some()
+            || stored.stt_model.is_some();
+
+        // Old files become a single profile for their input language
+        let mut profiles = match stored.profiles {
+            Some(profiles) => profiles,
+            None if flat => BTreeMap::new(),
+            None => default_profiles(),
+        };
+        if flat {
+            let profile = profiles.entry(stored.input_language.clone()).or_default();
+            if let Some(voice) = stored.output_voice {
+                profile.output_voice = voice;
+            }
+            if let Some(speed) = stored.output_speed {
+                profile.output_speed = speed;
+            }
+            if let Some(model) = stored.stt_model {
+                profile.stt_model = model;
+            }
+        }
+
+        Self {
+            input_language: stored.input_language,
+            auto_play: stored.auto_play,
+            history_limit: stored.history_limit,
+            retention: stored.retention,
+            vad: stored.vad,
+            push_to_talk: stored.push_to_talk,
+            code_blocks: stored.code_blocks,
+            profiles,
+            detect_response_language: stored.detect_response_language,
+        }
+    }
+}
+
+impl From<VoiceSettings> for StoredVoiceSettings {
+    fn from(settings: VoiceSettings) -> Self {
+        let active = settings.profile(&settings.input_language);
+        Self {
+            input_language: settings.input_language,
+            output_voice: Some(active.output_voice),
+            output_speed: Some(active.output_speed),
+            stt_model: Some(active.stt_model),
+            auto_play: settings.auto_play,
+            history_limit: settings.history_limit,
+            retention: settings.retention,
+            vad: settings.vad,
+            push_to_talk: settings.push_to_talk,
+            code_blocks: settings.code_blocks,
+            profiles: Some(settings.profiles),
+            detect_response_language: settings.detect_response_language,
+        }
+    }
+}
+
+/// Flat fields mirrored from the input-language profile
+const FLAT_FIELDS: [&str; 3] = ["output_voice", "output_speed", "stt_model"];
+
+/// JSON merge patch (RFC 7386): objects merge key by key, `null` removes
+/// a key and anything else replaces the value
+fn merge(target: &mut Value, patch: &Value) {
+    let (Value::Object(target), Value::Object(patch)) = (&mut *target, patch) else {
+        *target = patch.clone();
+        return;
+    };
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Settings with a partial object from the webview merged over them.
+/// Fields missing from the patch keep their current values, so a form that
+/// only knows the flat fields doesn't drop profiles, retention or VAD.
+pub fn apply_patch(settings: &VoiceSettings, patch: &Value) -> Result<VoiceSettings, AppError> {
+    if !patch.is_object() {
+        return Err(AppError::Voice(
+            "Nastavení musí být objekt JSON".to_string(),
+        ));
+    }
+
+    let mut merged = serde_json::to_value(StoredVoiceSettings::from(settings.clone()))
+        .map_err(AppError::Json)?;
+    // The mirror would overwrite profile changes from the patch; flat fields
+    // only count when the patch itself sends them
+    if let Some(object) = merged.as_object_mut() {
+        for field in FLAT_FIELDS {
+            object.remove(field);
+        }
+    }
+    merge(&mut merged, patch);
+
+    serde_json::from_value(merged).map_err(AppError::Json)
+}
@@ -0,0 +1,102 @@
+// Push-to-talk
+// Registers the shortcut from `VoiceSettings::push_to_talk` as a global
+// shortcut. Pressing and releasing it emits `push-to-talk` events; the webview
+// records while it is held and sends the audio to `transcribe_audio`.
+
+use super::VoiceSettings;
+use crate::error::AppError;
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PushToTalk {
+    pub enabled: bool,
+    /// Accelerator such as "CommandOrControl+Shift+Space"
+    pub shortcut: String,
+}
+
+impl Default for PushToTalk {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            shortcut: "CommandOrControl+Shift+Space".to_string(),
+        }
+    }
+}
+
+/// Payload of `push-to-talk`
+#[derive(Debug, Clone, Serialize)]
+pub struct PushToTalkEvent {
+    pub pressed: bool,
+}
+
+pub fn parse_shortcut(shortcut: &str) -> Result<Shortcut, AppError> {
+    Shortcut::from_str(shortcut.trim())
+        .map_err(|e| AppError::Voice(format!("Neplatná klávesová zkratka {}: {}", shortcut, e)))
+}
+
+/// Register (or drop) the push-to-talk shortcut according to the settings
+pub fn apply<R: Runtime>(app: &AppHandle<R>, settings: &VoiceSettings) -> Result<(), AppError> {
+    let shortcuts = app.global_shortcut();
+    // We're the only user of global shortcuts, so start from a clean slate
+    shortcuts
+        .unregister_all()
+        .map_err(|e| AppError::Voice(e.to_string()))?;
+
+    let config = &settings.push_to_talk;
+    if !config.enabled {
+        return Ok(());
+    }
+
+    let shortcut = parse_shortcut(&config.shortcut)?;
+    shortcuts
+        .on_shortcut(shortcut, |app, _, event| {
+            let payload = PushToTalkEvent {
+                pressed: event.state == ShortcutState::Pressed,
+            };
+            if let Err(e) = app.emit("push-to-talk", payload) {
+                log::warn!("⚠️  Cannot emit push-to-talk: {}", e);
+            }
+        })
+        .map_err(|e| AppError::Voice(e.to_string()))?;
+
+    log::info!("🎙️  Push-to-talk bound to {}", config.shortcut);
+    Ok(())
+}
+
+/// Switch the shortcut from `from` to `to` settings. Nothing happens when the
+/// push-to-talk config is the same; if `to` can't be bound, `from` is bound
+/// again and the error returned.
+pub fn switch<R: Runtime>(
+    app: &AppHandle<R>,
+    from: &VoiceSettings,
+    to: &VoiceSettings,
+) -> Result<(), AppError> {
+    if from.push_to_talk == to.push_to_talk {
+        return Ok(());
+    }
+
+    apply(app, to).inspect_err(|_| {
+        if let Err(e) = apply(app, from) {
+            log::warn!("⚠️  Cannot restore push-to-talk: {}", e);
+        }
+    })
+}
+
+/// Bind the shortcut from the saved settings at startup
+pub fn spawn_setup<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let result = match super::load_voice_settings(&state.sys).await {
+            Ok(settings) => apply(&app, &settings),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            log::warn!("⚠️  Push-to-talk unavailable: {}", e);
+        }
+    });
+}
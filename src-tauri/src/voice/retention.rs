@@ -0,0 +1,284 @@
+// Retention of conversation history
+// Prunes flat entries and threads by age, total size and the legacy
+// `history_limit` count. Starred entries and pinned threads are kept unless
+// `keep_pinned` is turned off. An entry and the thread built from it share
+// the ID and are pruned as one item.
+
+use super::threads::ThreadStore;
+use super::{ConversationStore, VoiceSettings};
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::system::{now_millis, SystemOps};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Delete conversations older than this many days
+    pub max_age_days: Option<u32>,
+    /// Keep the history (entries + threads) under this many bytes
+    pub max_total_bytes: Option<u64>,
+    /// Never prune starred entries and pinned threads
+    pub keep_pinned: bool,
+    /// How often the background task prunes, 0 = never
+    pub prune_interval_hours: u32,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age_days: None,
+            max_total_bytes: None,
+            keep_pinned: true,
+            prune_interval_hours: 24,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    Entry,
+    Thread,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PruneReason {
+    /// Over `history_limit`
+    Count,
+    /// Older than `max_age_days`
+    Age,
+    /// Over `max_total_bytes`
+    Size,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PrunedItem {
+    pub kind: ItemKind,
+    pub id: String,
+    /// Thread title or the start of the question
+    pub title: String,
+    /// Last activity, ms since epoch
+    pub timestamp: i64,
+    /// Approximate size on disk in bytes
+    pub size: u64,
+    pub reason: PruneReason,
+}
+
+/// Payload of `conversations-pruned` and result of `prune_conversations`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PruneReport {
+    pub dry_run: bool,
+    pub deleted: Vec<PrunedItem>,
+    pub freed_bytes: u64,
+    /// Items kept only because they are starred or pinned
+    pub kept_pinned: usize,
+}
+
+struct Candidate {
+    item: PrunedItem,
+    keep: bool,
+}
+
+fn serialized_size<T: Serialize>(value: &T) -> u64 {
+    serde_json::to_string(value)
+        .map(|s| s.len() as u64)
+        .unwrap_or(0)
+}
+
+/// Decide what to delete. Items are considered oldest first; each is
+/// tagged with the first rule that removes it.
+fn plan(
+    mut candidates: Vec<Candidate>,
+    settings: &VoiceSettings,
+    now: i64,
+    dry_run: bool,
+) -> PruneReport {
+    let policy = &settings.retention;
+    candidates.sort_by_key(|c| c.item.timestamp);
+
+    let cutoff = policy
+        .max_age_days
+        .map(|days| now - i64::from(days) * DAY_MS);
+    let protected = |c: &Candidate| c.keep && policy.keep_pinned;
+    // Starred entries don't count towards the limit
+    let mut entries_left = candidates
+        .iter()
+        .filter(|c| c.item.kind == ItemKind::Entry && !protected(c))
+        .count();
+    let mut total: u64 = candidates.iter().map(|c| c.item.size).sum();
+
+    let mut report = PruneReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    for candidate in candidates {
+        let is_protected = protected(&candidate);
+        let mut item = candidate.item;
+
+        let reason = if !is_protected
+            && item.kind == ItemKind::Entry
+            && entries_left > settings.history_limit
+        {
+            Some(PruneReason::Count)
+        } else if cutoff.is_some_and(|cutoff| item.timestamp < cutoff) {
+            Some(PruneReason::Age)
+        } else if policy.max_total_bytes.is_some_and(|max| total > max) {
+            Some(PruneReason::Size)
+        } else {
+            None
+        };
+
+        let Some(reason) = reason else {
+            continue;
+        };
+        if is_protected {
+            report.kept_pinned += 1;
+            continue;
+        }
+
+        if item.kind == ItemKind::Entry {
+            entries_left -= 1;
+        }
+        total -= item.size;
+        report.freed_bytes += item.size;
+        item.reason = reason;
+        report.deleted.push(item);
+    }
+
+    report
+}
+
+/// Prune history according to the settings. With `dry_run` nothing is deleted
+/// and the report previews what would be.
+pub async fn prune(
+    sys: &Arc<dyn SystemOps>,
+    store: &dyn ConversationStore,
+    threads: &ThreadStore,
+    now: i64,
+    dry_run: bool,
+) -> Result<PruneReport, AppError> {
+    let settings = super::load_voice_settings(sys).await?;
+
+    let mut candidates: Vec<Candidate> = store
+        .load_all()
+        .await?
+        .iter()
+        .map(|e| Candidate {
+            item: PrunedItem {
+                kind: ItemKind::Entry,
+                id: e.id.clone(),
+                title: super::threads::derive_title(&e.user_input),
+                timestamp: e.timestamp,
+                size: serialized_size(e),
+                reason: PruneReason::Count,
+            },
+            keep: e.starred,
+        })
+        .collect();
+    let entries: HashMap<String, usize> = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.item.id.clone(), i))
+        .collect();
+
+    let mut thread_ids = HashSet::new();
+    for thread in threads.list_all().await? {
+        let size = serialized_size(&thread);
+        thread_ids.insert(thread.id.clone());
+
+        // The thread copy of an entry: both files go, so both sizes count
+        if let Some(candidate) = entries.get(&thread.id).map(|&i| &mut candidates[i]) {
+            candidate.item.title = thread.title;
+            candidate.item.timestamp = candidate.item.timestamp.max(thread.updated_at);
+            candidate.item.size += size;
+            candidate.keep |= thread.pinned;
+            continue;
+        }
+
+        let candidate = Candidate {
+            item: PrunedItem {
+                kind: ItemKind::Thread,
+                id: thread.id.clone(),
+                title: thread.title,
+                timestamp: thread.updated_at,
+                size,
+                reason: PruneReason::Count,
+            },
+            keep: thread.pinned,
+        };
+        candidates.push(candidate);
+    }
+
+    let report = plan(candidates, &settings, now, dry_run);
+    if dry_run || report.deleted.is_empty() {
+        return Ok(report);
+    }
+
+    let entry_ids: Vec<String> = report
+        .deleted
+        .iter()
+        .filter(|i| i.kind == ItemKind::Entry)
+        .map(|i| i.id.clone())
+        .collect();
+    store.remove(&entry_ids).await?;
+
+    for item in report.deleted.iter().filter(|i| thread_ids.contains(&i.id)) {
+        threads.delete(&item.id).await?;
+    }
+
+    log::info!(
+        "🧹 Pruned {} conversations ({} bytes)",
+        report.deleted.len(),
+        report.freed_bytes
+    );
+    Ok(report)
+}
+
+/// Start the task that prunes history on the configured interval
+pub fn spawn_pruner<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let state = app.state::<AppState>();
+            let interval = match super::load_voice_settings(&state.sys).await {
+                Ok(settings) => settings.retention.prune_interval_hours,
+                Err(e) => {
+                    log::warn!("⚠️  Cannot load retention settings: {}", e);
+                    24
+                }
+            };
+
+            if interval > 0 {
+                match prune(
+                    &state.sys,
+                    state.conversations.as_ref(),
+                    &state.threads,
+                    now_millis(),
+                    false,
+                )
+                .await
+                {
+                    Ok(report) if !report.deleted.is_empty() => {
+                        if let Err(e) = app.emit("conversations-pruned", &report) {
+                            log::warn!("⚠️  Cannot emit conversations-pruned: {}", e);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => log::warn!("⚠️  History pruning failed: {}", e),
+                }
+            }
+
+            // While disabled, look again in an hour in case it gets enabled
+            let hours = if interval == 0 { 1 } else { interval };
+            tokio::time::sleep(Duration::from_secs(u64::from(hours) * 3600)).await;
+        }
+    });
+}
@@ -0,0 +1,378 @@
+// Full-text search over conversation history
+// Inverted index of normalized terms kept in sync with the conversation store.
+// Plain query words match as prefixes, quoted phrases match consecutive words.
+
+use super::{ConversationEntry, ConversationFilter};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Characters of context shown before the first match in a snippet
+const SNIPPET_CONTEXT: usize = 60;
+/// Maximum snippet length in characters
+const SNIPPET_LENGTH: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchField {
+    UserInput,
+    AssistantResponse,
+}
+
+/// Piece of snippet text, either plain or a highlighted match
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SnippetPart {
+    pub text: String,
+    pub highlight: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Snippet {
+    pub field: SearchField,
+    pub parts: Vec<SnippetPart>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub entry: ConversationEntry,
+    /// Number of matches across both fields
+    pub score: usize,
+    pub snippets: Vec<Snippet>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Clause {
+    /// Any word starting with the term
+    Term(String),
+    /// Exact consecutive words
+    Phrase(Vec<String>),
+}
+
+/// Parsed query; every clause has to match somewhere in the entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    clauses: Vec<Clause>,
+}
+
+impl Query {
+    /// Parse `word "exact phrase" other` style input
+    pub fn parse(input: &str) -> Self {
+        let mut clauses = Vec::new();
+
+        // Odd segments are inside quotes; an unterminated quote runs to the end
+        for (i, segment) in input.split('"').enumerate() {
+            let terms: Vec<String> = tokenize(segment).into_iter().map(|t| t.term).collect();
+
+            if i % 2 == 1 {
+                if !terms.is_empty() {
+                    clauses.push(Clause::Phrase(terms));
+                }
+            } else {
+                clauses.extend(terms.into_iter().map(Clause::Term));
+            }
+        }
+
+        Self { clauses }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+}
+
+struct Token {
+    term: String,
+    /// Byte range in the original text
+    start: usize,
+    end: usize,
+}
+
+/// Lowercase and strip diacritics so "Příliš" matches "prilis"
+fn normalize(word: &str) -> String {
+    word.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'á' | 'ä' => 'a',
+            'č' => 'c',
+            'ď' => 'd',
+            'é' | 'ě' => 'e',
+            'í' => 'i',
+            'ĺ' | 'ľ' => 'l',
+            'ň' => 'n',
+            'ó' | 'ô' | 'ö' => 'o',
+            'ŕ' | 'ř' => 'r',
+            'š' => 's',
+            'ť' => 't',
+            'ú' | 'ů' | 'ü' => 'u',
+            'ý' => 'y',
+            'ž' => 'z',
+            'ß' => 's',
+            c => c,
+        })
+        .collect()
+}
+
+/// Split text into alphanumeric words
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+
+    let mut push = |start: usize, end: usize| {
+        tokens.push(Token {
+            term: normalize(&text[start..end]),
+            start,
+            end,
+        })
+    };
+
+    for (i, c) in text.char_indices() {
+        if c.is_alphanumeric() {
+            start.get_or_insert(i);
+        } else if let Some(s) = start.take() {
+            push(s, i);
+        }
+    }
+    if let Some(s) = start {
+        push(s, text.len());
+    }
+
+    tokens
+}
+
+/// Inverted index: term -> IDs of entries containing it
+#[derive(Default)]
+pub struct SearchIndex {
+    postings: BTreeMap<String, HashSet<String>>,
+    /// Entry ID -> its indexed terms, so removal doesn't need the entry
+    terms: HashMap<String, HashSet<String>>,
+}
+
+impl SearchIndex {
+    /// Index an entry, replacing whatever was indexed under its ID
+    pub fn add(&mut self, entry: &ConversationEntry) {
+        self.remove(&entry.id);
+
+        let terms: HashSet<String> = tokenize(&entry.user_input)
+            .into_iter()
+            .chain(tokenize(&entry.assistant_response))
+            .map(|t| t.term)
+            .collect();
+
+        for term in &terms {
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .insert(entry.id.clone());
+        }
+        self.terms.insert(entry.id.clone(), terms);
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        let Some(terms) = self.terms.remove(id) else {
+            return;
+        };
+
+        for term in terms {
+            if let Some(ids) = self.postings.get_mut(&term) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+    }
+
+    fn with_prefix(&self, prefix: &str) -> HashSet<String> {
+        self.postings
+            .range(prefix.to_string()..)
+            .take_while(|(term, _)| term.starts_with(prefix))
+            .flat_map(|(_, ids)| ids.iter().cloned())
+            .collect()
+    }
+
+    fn with_term(&self, term: &str) -> HashSet<String> {
+        self.postings.get(term).cloned().unwrap_or_default()
+    }
+
+    /// IDs of entries containing every query word. Phrases still need to be
+    /// verified against the text, which `hit` does.
+    pub fn candidates(&self, query: &Query) -> HashSet<String> {
+        let mut result: Option<HashSet<String>> = None;
+
+        for clause in &query.clauses {
+            let ids = match clause {
+                Clause::Term(term) => self.with_prefix(term),
+                Clause::Phrase(terms) => terms
+                    .iter()
+                    .map(|t| self.with_term(t))
+                    .reduce(|a, b| a.intersection(&b).cloned().collect())
+                    .unwrap_or_default(),
+            };
+
+            let narrowed = match result {
+                Some(prev) => prev.intersection(&ids).cloned().collect(),
+                None => ids,
+            };
+            if narrowed.is_empty() {
+                return narrowed;
+            }
+            result = Some(narrowed);
+        }
+
+        result.unwrap_or_default()
+    }
+}
+
+/// Which clauses matched in a field and where
+fn match_field(query: &Query, text: &str) -> (Vec<bool>, Vec<(usize, usize)>) {
+    let tokens = tokenize(text);
+    let mut satisfied = vec![false; query.clauses.len()];
+    let mut spans = Vec::new();
+
+    for (i, clause) in query.clauses.iter().enumerate() {
+        match clause {
+            Clause::Term(term) => {
+                for token in tokens.iter().filter(|t| t.term.starts_with(term.as_str())) {
+                    satisfied[i] = true;
+                    spans.push((token.start, token.end));
+                }
+            }
+            Clause::Phrase(terms) => {
+                for window in tokens.windows(terms.len()) {
+                    if window.iter().zip(terms).all(|(t, term)| t.term == *term) {
+                        satisfied[i] = true;
+                        spans.push((window[0].start, window[window.len() - 1].end));
+                    }
+                }
+            }
+        }
+    }
+
+    // Merge overlapping spans (a word can match several clauses)
+    spans.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in spans {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    (satisfied, merged)
+}
+
+/// Byte offset `n` characters before `from`
+fn chars_before(text: &str, from: usize, n: usize) -> usize {
+    text[..from]
+        .char_indices()
+        .rev()
+        .nth(n.saturating_sub(1))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// Byte offset `n` characters after `from`
+fn chars_after(text: &str, from: usize, n: usize) -> usize {
+    text[from..]
+        .char_indices()
+        .nth(n)
+        .map(|(i, _)| from + i)
+        .unwrap_or(text.len())
+}
+
+fn snippet(field: SearchField, text: &str, spans: &[(usize, usize)]) -> Snippet {
+    let start = chars_before(text, spans[0].0, SNIPPET_CONTEXT);
+    let end = chars_after(text, start, SNIPPET_LENGTH).max(spans[0].1);
+
+    let mut parts = Vec::new();
+    let mut push = |text: &str, highlight: bool| {
+        if !text.is_empty() {
+            parts.push(SnippetPart {
+                text: text.to_string(),
+                highlight,
+            });
+        }
+    };
+
+    if start > 0 {
+        push("…", false);
+    }
+
+    let mut pos = start;
+    for &(s, e) in spans.iter().filter(|(s, e)| *s >= start && *e <= end) {
+        push(&text[pos..s], false);
+        push(&text[s..e], true);
+        pos = e;
+    }
+    push(&text[pos..end], false);
+
+    if end < text.len() {
+        push("…", false);
+    }
+
+    Snippet { field, parts }
+}
+
+/// Build a hit for an entry, or `None` if it doesn't match the query.
+/// An empty query matches everything, without snippets.
+pub fn hit(entry: &ConversationEntry, query: &Query) -> Option<SearchHit> {
+    let fields = [
+        (SearchField::UserInput, entry.user_input.as_str()),
+        (
+            SearchField::AssistantResponse,
+            entry.assistant_response.as_str(),
+        ),
+    ];
+
+    let mut satisfied = vec![false; query.clauses.len()];
+    let mut score = 0;
+    let mut snippets = Vec::new();
+
+    for (field, text) in fields {
+        let (matched, spans) = match_field(query, text);
+        for (all, m) in satisfied.iter_mut().zip(matched) {
+            *all |= m;
+        }
+
+        if !spans.is_empty() {
+            score += spans.len();
+            snippets.push(snippet(field, text, &spans));
+        }
+    }
+
+    if !satisfied.iter().all(|s| *s) {
+        return None;
+    }
+
+    Some(SearchHit {
+        entry: entry.clone(),
+        score,
+        snippets,
+    })
+}
+
+/// Rank hits: most matches first, newer first on ties
+fn rank(hits: &mut Vec<SearchHit>, limit: usize) {
+    hits.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(b.entry.timestamp.cmp(&a.entry.timestamp))
+    });
+    hits.truncate(limit);
+}
+
+/// Filter, match and rank entries
+pub fn scan<'a>(
+    entries: impl IntoIterator<Item = &'a ConversationEntry>,
+    query: &Query,
+    filter: &ConversationFilter,
+    limit: usize,
+) -> Vec<SearchHit> {
+    let mut hits: Vec<SearchHit> = entries
+        .into_iter()
+        .filter(|e| filter.matches(e))
+        .filter_map(|e| hit(e, query))
+        .collect();
+
+    rank(&mut hits, limit);
+    hits
+}
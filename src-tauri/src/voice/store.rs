@@ -0,0 +1,509 @@
+// Conversation storage
+// Append-only JSON Lines log with periodic compaction. Every save appends one
+// record instead of rewriting the whole history, and a torn last line after a
+// crash only loses that single record.
+
+use super::crypto::Encryption;
+use super::search::{self, Query, SearchHit, SearchIndex};
+use super::{ConversationEntry, ConversationFilter, ConversationPage, PageCursor, SortOrder};
+use crate::error::AppError;
+use crate::schema::{self, Schema};
+use crate::system::SystemOps;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+
+/// Compact once the log holds this many more records than live entries
+const COMPACTION_SLACK: usize = 64;
+
+/// Storage backend for conversation history
+#[async_trait::async_trait]
+pub trait ConversationStore: Send + Sync {
+    /// All entries in insertion order
+    async fn load_all(&self) -> Result<Vec<ConversationEntry>, AppError>;
+
+    /// Number of stored entries
+    async fn count(&self) -> Result<usize, AppError>;
+
+    /// Insert an entry, replacing any stored entry with the same ID
+    async fn insert(&self, entry: ConversationEntry) -> Result<(), AppError>;
+
+    /// Insert entries whose ID is not stored yet; returns how many were added
+    async fn insert_new(&self, entries: Vec<ConversationEntry>) -> Result<usize, AppError>;
+
+    /// Remove entries by ID; returns how many were removed
+    async fn remove(&self, ids: &[String]) -> Result<usize, AppError>;
+
+    /// Number of entries matching the filter
+    async fn count_matching(&self, filter: &ConversationFilter) -> Result<usize, AppError>;
+
+    /// Up to `limit` entries matching the filter, continuing after `cursor`
+    async fn page(
+        &self,
+        filter: &ConversationFilter,
+        cursor: Option<&PageCursor>,
+        order: SortOrder,
+        limit: usize,
+    ) -> Result<ConversationPage, AppError>;
+
+    /// Full-text search over entries matching the filter
+    async fn search(
+        &self,
+        query: &str,
+        filter: &ConversationFilter,
+        limit: usize,
+    ) -> Result<Vec<SearchHit>, AppError>;
+
+    /// Remove everything
+    async fn clear(&self) -> Result<(), AppError>;
+
+    /// Rewrite stored data with the current encryption settings
+    async fn rewrite(&self) -> Result<(), AppError>;
+
+    /// Drop the in-memory copy; the next access reads storage again
+    async fn unload(&self);
+}
+
+/// Format of one log record. Each line carries its own version, so a log
+/// appended to by several app versions is upgraded line by line.
+pub const LOG_SCHEMA: Schema = Schema {
+    name: "conversations.jsonl",
+    migrations: &[schema::add_version],
+};
+
+/// One line of the log
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum LogRecord {
+    Put { entry: ConversationEntry },
+    Delete { id: String },
+}
+
+struct LogState {
+    path: PathBuf,
+    entries: Vec<ConversationEntry>,
+    /// Entry ID -> position in `entries`
+    index: HashMap<String, usize>,
+    /// Records currently in the file (live entries + superseded puts + deletes)
+    records: usize,
+    /// Full-text index, updated with every applied record
+    search: SearchIndex,
+    /// (timestamp, ID) of every entry, for paging
+    order: BTreeSet<(i64, String)>,
+}
+
+impl LogState {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            entries: Vec::new(),
+            index: HashMap::new(),
+            records: 0,
+            search: SearchIndex::default(),
+            order: BTreeSet::new(),
+        }
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        self.index.contains_key(id)
+    }
+
+    /// Apply one record to the in-memory view
+    fn apply(&mut self, record: LogRecord) {
+        self.records += 1;
+
+        match record {
+            LogRecord::Put { entry } => {
+                self.search.add(&entry);
+                self.order.insert((entry.timestamp, entry.id.clone()));
+                match self.index.get(&entry.id) {
+                    Some(&position) => {
+                        let old = std::mem::replace(&mut self.entries[position], entry);
+                        if old.timestamp != self.entries[position].timestamp {
+                            self.order.remove(&(old.timestamp, old.id));
+                        }
+                    }
+                    None => {
+                        self.index.insert(entry.id.clone(), self.entries.len());
+                        self.entries.push(entry);
+                    }
+                }
+            }
+            LogRecord::Delete { id } => {
+                self.search.remove(&id);
+                if let Some(position) = self.index.remove(&id) {
+                    let old = self.entries.remove(position);
+                    self.order.remove(&(old.timestamp, old.id));
+                    for p in self.index.values_mut() {
+                        if *p > position {
+                            *p -= 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn search(&self, query: &str, filter: &ConversationFilter, limit: usize) -> Vec<SearchHit> {
+        let query = Query::parse(query);
+
+        if query.is_empty() {
+            return search::scan(&self.entries, &query, filter, limit);
+        }
+
+        let candidates = self
+            .search
+            .candidates(&query)
+            .into_iter()
+            .filter_map(|id| self.index.get(&id))
+            .map(|&position| &self.entries[position]);
+
+        search::scan(candidates, &query, filter, limit)
+    }
+
+    fn page(
+        &self,
+        filter: &ConversationFilter,
+        cursor: Option<&PageCursor>,
+        order: SortOrder,
+        limit: usize,
+    ) -> ConversationPage {
+        // A cursor with an ID excludes exactly that key. An ID-less cursor
+        // covers the whole millisecond; no arithmetic on the timestamp, so a
+        // crafted `i64::MAX` cursor can't overflow.
+        let excluded =
+            |c: &PageCursor| Bound::Excluded((c.timestamp, c.id.clone().unwrap_or_default()));
+
+        let keys: Box<dyn Iterator<Item = &(i64, String)>> = match (order, cursor) {
+            (SortOrder::NewestFirst, None) => Box::new(self.order.iter().rev()),
+            (SortOrder::NewestFirst, Some(c)) => {
+                Box::new(self.order.range((Bound::Unbounded, excluded(c))).rev())
+            }
+            (SortOrder::OldestFirst, None) => Box::new(self.order.iter()),
+            (SortOrder::OldestFirst, Some(c)) => {
+                let timestamp = c.timestamp;
+                let after = self.order.range((excluded(c), Bound::Unbounded));
+                if c.id.is_some() {
+                    Box::new(after)
+                } else {
+                    Box::new(after.skip_while(move |(t, _)| *t == timestamp))
+                }
+            }
+        };
+
+        let mut entries: Vec<ConversationEntry> = keys
+            .filter_map(|(_, id)| self.index.get(id))
+            .map(|&position| &self.entries[position])
+            .filter(|e| filter.matches(e))
+            .take(limit + 1)
+            .cloned()
+            .collect();
+
+        let next = if entries.len() > limit {
+            entries.truncate(limit);
+            entries.last().map(|e| PageCursor {
+                timestamp: e.timestamp,
+                id: Some(e.id.clone()),
+            })
+        } else {
+            None
+        };
+
+        ConversationPage { entries, next }
+    }
+
+    fn needs_compaction(&self) -> bool {
+        self.records > self.entries.len() * 2 + COMPACTION_SLACK
+    }
+}
+
+/// `ConversationStore` backed by `conversations.jsonl` in the voice directory.
+/// With encryption enabled every line is sealed separately.
+pub struct LogConversationStore {
+    sys: Arc<dyn SystemOps>,
+    encryption: Arc<Encryption>,
+    /// `None` until first use and after `unload`
+    state: Mutex<Option<LogState>>,
+}
+
+impl LogConversationStore {
+    pub fn new(sys: Arc<dyn SystemOps>) -> Self {
+        let encryption = Arc::new(Encryption::new(sys.clone()));
+        Self::with_encryption(sys, encryption)
+    }
+
+    /// Store sharing its keys with other stores
+    pub fn with_encryption(sys: Arc<dyn SystemOps>, encryption: Arc<Encryption>) -> Self {
+        Self {
+            sys,
+            encryption,
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Lazily load the log on first use
+    async fn state(&self) -> Result<MappedMutexGuard<'_, LogState>, AppError> {
+        let mut guard = self.state.lock().await;
+        if guard.is_none() {
+            *guard = Some(self.open().await?);
+        }
+
+        Ok(MutexGuard::map(guard, |state| {
+            state.as_mut().expect("log state loaded above")
+        }))
+    }
+
+    async fn open(&self) -> Result<LogState, AppError> {
+        let voice_dir = super::get_voice_dir(self.sys.as_ref()).await?;
+        let path = voice_dir.join("conversations.jsonl");
+        let legacy_path = voice_dir.join("conversations.json");
+
+        if !self.sys.exists(&path).await {
+            if self.sys.exists(&legacy_path).await {
+                return self.migrate_legacy(path, legacy_path).await;
+            }
+
+            return Ok(LogState::new(path));
+        }
+
+        let content = self.sys.read_to_string(&path).await?;
+        let mut state = LogState::new(path);
+        let mut damaged = !content.is_empty() && !content.ends_with('\n');
+        let mut oldest = LOG_SCHEMA.version();
+
+        for line in content.lines().filter(|l| !l.trim().is_empty()) {
+            // A locked store fails here instead of treating records as damaged
+            let Some(line) = self.encryption.open(line).await? else {
+                log::warn!("⚠️  Skipping undecryptable conversation record");
+                damaged = true;
+                continue;
+            };
+
+            let Ok(value) = serde_json::from_str::<serde_json::Value>(&line) else {
+                log::warn!("⚠️  Skipping damaged conversation record");
+                damaged = true;
+                continue;
+            };
+            // A record from a newer app version fails instead of being dropped
+            let (value, version) = LOG_SCHEMA.upgrade(value)?;
+            oldest = oldest.min(version);
+
+            match serde_json::from_value::<LogRecord>(value) {
+                Ok(record) => state.apply(record),
+                Err(e) => {
+                    log::warn!("⚠️  Skipping damaged conversation record: {}", e);
+                    damaged = true;
+                }
+            }
+        }
+
+        if oldest < LOG_SCHEMA.version() {
+            schema::backup_original(
+                self.sys.as_ref(),
+                &LOG_SCHEMA,
+                &state.path,
+                &content,
+                oldest,
+            )
+            .await?;
+        }
+
+        // Rewrite a damaged or upgraded log right away so new records don't
+        // land after garbage or old-format lines
+        if damaged || oldest < LOG_SCHEMA.version() {
+            self.compact(&mut state).await?;
+        }
+
+        Ok(state)
+    }
+
+    /// One-time migration from the old `conversations.json` array
+    async fn migrate_legacy(
+        &self,
+        path: PathBuf,
+        legacy_path: PathBuf,
+    ) -> Result<LogState, AppError> {
+        let content = self.sys.read_to_string(&legacy_path).await?;
+        let entries: Vec<ConversationEntry> =
+            serde_json::from_str(&content).map_err(AppError::Json)?;
+
+        let mut state = LogState::new(path);
+        for entry in entries {
+            state.apply(LogRecord::Put { entry });
+        }
+        self.compact(&mut state).await?;
+
+        // Keep the original around until the user clears history,
+        // unless history is encrypted and a plain copy would defeat that
+        if !self.encryption.is_sealing().await? {
+            let backup = legacy_path.with_extension("json.migrated");
+            self.sys.write_atomic(&backup, &content, false).await?;
+        }
+        self.sys.remove_file(&legacy_path).await?;
+
+        log::info!(
+            "📦 Migrated {} conversations to append-only log",
+            state.entries.len()
+        );
+        Ok(state)
+    }
+
+    async fn encode(&self, record: &LogRecord) -> Result<String, AppError> {
+        let json = LOG_SCHEMA.to_string(record)?;
+        let mut line = self.encryption.seal(&json).await?;
+        line.push('\n');
+        Ok(line)
+    }
+
+    fn backup_path(state: &LogState) -> PathBuf {
+        state.path.with_file_name("conversations.json.migrated")
+    }
+
+    /// Rewrite the log with only the live entries
+    async fn compact(&self, state: &mut LogState) -> Result<(), AppError> {
+        let mut content = String::new();
+        for entry in &state.entries {
+            content.push_str(
+                &self
+                    .encode(&LogRecord::Put {
+                        entry: entry.clone(),
+                    })
+                    .await?,
+            );
+        }
+
+        self.sys.write_atomic(&state.path, &content, false).await?;
+        state.records = state.entries.len();
+        Ok(())
+    }
+
+    /// Append records and compact if the log grew too much
+    async fn append(&self, state: &mut LogState, records: Vec<LogRecord>) -> Result<(), AppError> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let mut content = String::new();
+        for record in &records {
+            content.push_str(&self.encode(record).await?);
+        }
+        self.sys.append(&state.path, &content).await?;
+
+        for record in records {
+            state.apply(record);
+        }
+
+        if state.needs_compaction() {
+            self.compact(state).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ConversationStore for LogConversationStore {
+    async fn load_all(&self) -> Result<Vec<ConversationEntry>, AppError> {
+        Ok(self.state().await?.entries.clone())
+    }
+
+    async fn count(&self) -> Result<usize, AppError> {
+        Ok(self.state().await?.entries.len())
+    }
+
+    async fn insert(&self, entry: ConversationEntry) -> Result<(), AppError> {
+        let mut state = self.state().await?;
+        self.append(&mut state, vec![LogRecord::Put { entry }])
+            .await
+    }
+
+    async fn insert_new(&self, entries: Vec<ConversationEntry>) -> Result<usize, AppError> {
+        let mut state = self.state().await?;
+        // Also skip duplicates within the batch itself
+        let mut seen = HashSet::new();
+        let records: Vec<LogRecord> = entries
+            .into_iter()
+            .filter(|entry| !state.contains(&entry.id) && seen.insert(entry.id.clone()))
+            .map(|entry| LogRecord::Put { entry })
+            .collect();
+
+        let added = records.len();
+        self.append(&mut state, records).await?;
+        Ok(added)
+    }
+
+    async fn remove(&self, ids: &[String]) -> Result<usize, AppError> {
+        let mut state = self.state().await?;
+        let records: Vec<LogRecord> = ids
+            .iter()
+            .filter(|id| state.contains(id))
+            .map(|id| LogRecord::Delete { id: id.clone() })
+            .collect();
+
+        let removed = records.len();
+        self.append(&mut state, records).await?;
+        Ok(removed)
+    }
+
+    async fn count_matching(&self, filter: &ConversationFilter) -> Result<usize, AppError> {
+        let state = self.state().await?;
+        Ok(state.entries.iter().filter(|e| filter.matches(e)).count())
+    }
+
+    async fn page(
+        &self,
+        filter: &ConversationFilter,
+        cursor: Option<&PageCursor>,
+        order: SortOrder,
+        limit: usize,
+    ) -> Result<ConversationPage, AppError> {
+        Ok(self.state().await?.page(filter, cursor, order, limit))
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        filter: &ConversationFilter,
+        limit: usize,
+    ) -> Result<Vec<SearchHit>, AppError> {
+        Ok(self.state().await?.search(query, filter, limit))
+    }
+
+    async fn clear(&self) -> Result<(), AppError> {
+        let mut state = self.state().await?;
+        if self.sys.exists(&state.path).await {
+            self.sys.remove_file(&state.path).await?;
+        }
+
+        let backup = Self::backup_path(&state);
+        if self.sys.exists(&backup).await {
+            self.sys.remove_file(&backup).await?;
+        }
+        schema::remove_backups(self.sys.as_ref(), &LOG_SCHEMA, &state.path).await?;
+
+        *state = LogState::new(state.path.clone());
+        Ok(())
+    }
+
+    async fn rewrite(&self) -> Result<(), AppError> {
+        let mut state = self.state().await?;
+        self.compact(&mut state).await?;
+
+        // Plain migration backups must not outlive encryption
+        if self.encryption.is_sealing().await? {
+            let backup = Self::backup_path(&state);
+            if self.sys.exists(&backup).await {
+                self.sys.remove_file(&backup).await?;
+            }
+            schema::remove_backups(self.sys.as_ref(), &LOG_SCHEMA, &state.path).await?;
+        }
+        Ok(())
+    }
+
+    async fn unload(&self) {
+        *self.state.lock().await = None;
+    }
+}
@@ -0,0 +1,194 @@
+// Offline speech-to-text through whisper.cpp
+// Audio is resampled to 16 kHz, written to a temporary WAV file and passed to
+// the whisper.cpp CLI (`whisper-cli`, override with `WHISPER_CLI`), whose
+// timestamped output is parsed into segments. With VAD enabled only the
+// detected utterances are sent to whisper.
+
+use super::audio::{self, Audio};
+use super::profiles::language_code;
+use super::vad;
+use super::VoiceSettings;
+use crate::error::AppError;
+use crate::system::SystemOps;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// whisper.cpp only accepts 16 kHz input
+pub const WHISPER_SAMPLE_RATE: u32 = 16_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Segment {
+    /// Offset from the start of the audio in ms
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Transcript {
+    /// Language passed to whisper ("auto" lets it detect)
+    pub language: String,
+    pub text: String,
+    pub segments: Vec<Segment>,
+}
+
+/// Path to the whisper.cpp CLI
+pub fn whisper_program(sys: &dyn SystemOps) -> String {
+    sys.env_var("WHISPER_CLI")
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| "whisper-cli".to_string())
+}
+
+/// Model file from the settings: an absolute path or a file name in the
+/// whisper models directory
+pub async fn model_path(sys: &dyn SystemOps, model: &str) -> Result<PathBuf, AppError> {
+    let path = PathBuf::from(model);
+    let path = if path.is_absolute() {
+        path
+    } else {
+        super::get_models_dir(sys)?.join("whisper").join(model)
+    };
+
+    if !sys.exists(&path).await {
+        return Err(AppError::Voice(format!(
+            "Model pro rozpoznávání řeči nenalezen: {}",
+            path.display()
+        )));
+    }
+    Ok(path)
+}
+
+/// "00:01:02.345" -> ms
+fn parse_time(value: &str) -> Option<i64> {
+    let mut parts = value.trim().rsplitn(3, ':');
+    let seconds: f64 = parts.next()?.replace(',', ".").parse().ok()?;
+    let minutes: i64 = parts.next()?.parse().ok()?;
+    let hours: i64 = parts.next().unwrap_or("0").parse().ok()?;
+    Some((hours * 3600 + minutes * 60) * 1000 + (seconds * 1000.0).round() as i64)
+}
+
+/// Parse `[00:00:00.000 --> 00:00:02.500]  text` lines
+pub fn parse_output(stdout: &str) -> Vec<Segment> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let rest = line.trim_start().strip_prefix('[')?;
+            let (range, text) = rest.split_once(']')?;
+            let (start, end) = range.split_once("-->")?;
+            let text = text.trim();
+            if text.is_empty() || text == "[BLANK_AUDIO]" {
+                return None;
+            }
+
+            Some(Segment {
+                start_ms: parse_time(start)?,
+                end_ms: parse_time(end)?,
+                text: text.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Transcribe mono audio in the input language with its profile's model
+pub async fn transcribe(
+    sys: &dyn SystemOps,
+    settings: &VoiceSettings,
+    audio: &Audio,
+) -> Result<Transcript, AppError> {
+    let language = language_code(&settings.input_language);
+    let profile = settings.profile(&settings.input_language);
+    let model = model_path(sys, &profile.stt_model).await?;
+
+    let input = audio::resample(audio, WHISPER_SAMPLE_RATE)?;
+    let wav = audio::encode_wav(&input)?;
+    let wav_path = sys
+        .temp_dir()
+        .join(format!("claude-stt-{}.wav", uuid::Uuid::new_v4().simple()));
+    sys.write_bytes(&wav_path, &wav).await?;
+
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .to_string();
+    let model_arg = model.to_string_lossy();
+    let wav_arg = wav_path.to_string_lossy();
+    let args = [
+        "-m",
+        model_arg.as_ref(),
+        "-f",
+        wav_arg.as_ref(),
+        "-l",
+        language.as_str(),
+        "-t",
+        threads.as_str(),
+        "--no-prints",
+    ];
+
+    let result = sys.run_command(&whisper_program(sys), &args).await;
+    if let Err(e) = sys.remove_file(&wav_path).await {
+        log::warn!("⚠️  Cannot remove {}: {}", wav_path.display(), e);
+    }
+
+    let output = result?;
+    if !output.status.success() {
+        return Err(AppError::Voice(format!(
+            "Rozpoznávání řeči selhalo: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let segments = parse_output(&String::from_utf8_lossy(&output.stdout));
+    let text = segments
+        .iter()
+        .map(|s| s.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    log::info!(
+        "🎙️  Transcribed {} ms of audio into {} segments",
+        audio.duration_ms(),
+        segments.len()
+    );
+    Ok(Transcript {
+        language,
+        text,
+        segments,
+    })
+}
+
+/// Transcribe only the speech: with VAD enabled the audio is split into
+/// utterances, silence is dropped and segment times stay relative to the
+/// whole recording
+pub async fn transcribe_speech(
+    sys: &dyn SystemOps,
+    settings: &VoiceSettings,
+    audio: &Audio,
+) -> Result<Transcript, AppError> {
+    if !settings.vad.enabled {
+        return transcribe(sys, settings, audio).await;
+    }
+
+    let mut segments = Vec::new();
+    for (utterance, clip) in vad::split(audio, &settings.vad) {
+        let transcript = transcribe(sys, settings, &clip).await?;
+        segments.extend(transcript.segments.into_iter().map(|s| Segment {
+            start_ms: s.start_ms + utterance.start_ms,
+            end_ms: s.end_ms + utterance.start_ms,
+            text: s.text,
+        }));
+    }
+
+    if segments.is_empty() {
+        log::info!("🔇 No speech in {} ms of audio", audio.duration_ms());
+    }
+    let text = segments
+        .iter()
+        .map(|s| s.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok(Transcript {
+        language: language_code(&settings.input_language),
+        text,
+        segments,
+    })
+}
@@ -0,0 +1,2480 @@
+use crate::error::AppError;
+use crate::mocks::MockSystemOps;
+use crate::system::SystemOps;
+use crate::voice::audio::{self, AudioFormat, AudioInput};
+use crate::voice::audio_export::{self, AudioExportFormat, Chapter};
+use crate::voice::catalog;
+use crate::voice::crypto::{self, Encryption, KeySource};
+use crate::voice::export::{self, ExportFormat, ExportSelection};
+use crate::voice::import::{self, ConflictPolicy, ImportFormat, ImportOptions};
+use crate::voice::models::{self, ModelKind};
+use crate::voice::normalize::{self, CodeBlockMode, SpeechLanguage};
+use crate::voice::profiles::{self, LanguageProfile};
+use crate::voice::ptt;
+use crate::voice::retention::{self, ItemKind, PruneReason, RetentionPolicy};
+use crate::voice::search::SearchField;
+use crate::voice::stt;
+use crate::voice::threads::{derive_title, MessageRole, ThreadStore};
+use crate::voice::tts::{self, TtsEngineKind, VoiceSpec};
+use crate::voice::vad::{self, Utterance, VadSettings};
+use crate::voice::validation::{self, normalize_language_tag, FieldError};
+use crate::voice::{
+    clear_conversations, count_conversations, import_conversations, list_stt_models,
+    list_tts_voices, load_conversations, load_conversations_page, save_conversation,
+    save_voice_settings, search_conversations, set_conversation_starred, transcribe_audio,
+    update_voice_settings, ConversationEntry, ConversationFilter, ConversationStore,
+    LogConversationStore, PageCursor, PageRequest, SortOrder, VoiceSettings,
+};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const LOG_PATH: &str = "/home/mockuser/.config/Claude/voice/conversations.jsonl";
+const LEGACY_PATH: &str = "/home/mockuser/.config/Claude/voice/conversations.json";
+
+/// Default settings with `profile` for `language`
+fn with_profile(language: &str, profile: LanguageProfile) -> VoiceSettings {
+    let mut settings = VoiceSettings::default();
+    settings.profiles.insert(language.to_string(), profile);
+    settings
+}
+
+fn entry(id: &str, timestamp: i64) -> ConversationEntry {
+    ConversationEntry {
+        id: id.to_string(),
+        timestamp,
+        user_input: format!("Q{}", id),
+        assistant_response: format!("A{}", id),
+        voice_used: false,
+        played_back: false,
+        starred: false,
+        tool_calls: Vec::new(),
+    }
+}
+
+#[tokio::test]
+async fn test_save_and_load_conversation() {
+    let mock = MockSystemOps::new();
+    let sys: Arc<dyn SystemOps> = Arc::new(mock);
+    let store: Arc<dyn ConversationStore> = Arc::new(LogConversationStore::new(sys.clone()));
+    let threads = ThreadStore::new(sys.clone(), store.clone());
+
+    let entry = ConversationEntry {
+        id: "1".to_string(),
+        timestamp: 100,
+        user_input: "Hi".to_string(),
+        assistant_response: "Hello".to_string(),
+        voice_used: true,
+        played_back: true,
+        starred: false,
+        tool_calls: Vec::new(),
+    };
+
+    save_conversation(&sys, store.as_ref(), &threads, entry.clone())
+        .await
+        .unwrap();
+
+    let loaded = load_conversations(store.as_ref()).await.unwrap();
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded[0].id, "1");
+}
+
+#[tokio::test]
+async fn test_history_limit() {
+    let mock = MockSystemOps::new();
+    let sys: Arc<dyn SystemOps> = Arc::new(mock);
+    let store: Arc<dyn ConversationStore> = Arc::new(LogConversationStore::new(sys.clone()));
+    let threads = ThreadStore::new(sys.clone(), store.clone());
+
+    // Set limit to 2
+    let settings = VoiceSettings {
+        history_limit: 2,
+        ..Default::default()
+    };
+    save_voice_settings(&sys, &settings).await.unwrap();
+
+    // Add 3 entries
+    for i in 1..=3 {
+        save_conversation(
+            &sys,
+            store.as_ref(),
+            &threads,
+            ConversationEntry {
+                id: i.to_string(),
+                timestamp: i,
+                user_input: format!("Q{}", i),
+                assistant_response: format!("A{}", i),
+                voice_used: false,
+                played_back: false,
+                starred: false,
+                tool_calls: Vec::new(),
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    let loaded = load_conversations(store.as_ref()).await.unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded[0].id, "2"); // Oldest should be dropped (1 dropped, 2 and 3 remain)
+    assert_eq!(loaded[1].id, "3");
+}
+
+#[tokio::test]
+async fn test_save_appends_instead_of_rewriting() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let store: Arc<dyn ConversationStore> = Arc::new(LogConversationStore::new(sys.clone()));
+    let threads = ThreadStore::new(sys.clone(), store.clone());
+
+    save_conversation(&sys, store.as_ref(), &threads, entry("1", 1))
+        .await
+        .unwrap();
+    save_conversation(&sys, store.as_ref(), &threads, entry("2", 2))
+        .await
+        .unwrap();
+
+    let log = sys.read_to_string(&PathBuf::from(LOG_PATH)).await.unwrap();
+    assert_eq!(log.lines().count(), 2);
+    assert!(log
+        .lines()
+        .all(|l| { serde_json::from_str::<serde_json::Value>(l).unwrap()["op"] == "put" }));
+
+    // A fresh store replays the log
+    let reopened = LogConversationStore::new(sys.clone());
+    assert_eq!(load_conversations(&reopened).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_migrates_legacy_json() {
+    let legacy = serde_json::to_string_pretty(&vec![entry("1", 1), entry("2", 2)]).unwrap();
+    let mock = MockSystemOps::new().with_file(LEGACY_PATH, &legacy);
+    let sys: Arc<dyn SystemOps> = Arc::new(mock);
+    let store = LogConversationStore::new(sys.clone());
+
+    let loaded = load_conversations(&store).await.unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert!(!sys.exists(&PathBuf::from(LEGACY_PATH)).await);
+    assert!(
+        sys.exists(&PathBuf::from(format!("{}.migrated", LEGACY_PATH)))
+            .await
+    );
+
+    let log = sys.read_to_string(&PathBuf::from(LOG_PATH)).await.unwrap();
+    assert_eq!(log.lines().count(), 2);
+
+    clear_conversations(&store).await.unwrap();
+    assert!(load_conversations(&store).await.unwrap().is_empty());
+    assert!(
+        !sys.exists(&PathBuf::from(format!("{}.migrated", LEGACY_PATH)))
+            .await
+    );
+}
+
+#[tokio::test]
+async fn test_recovers_from_torn_write() {
+    let good = format!(
+        "{}\n",
+        serde_json::json!({"op": "put", "entry": entry("1", 1)})
+    );
+    let torn = format!("{}{{\"op\":\"put\",\"entry\":{{\"id\":\"2", good);
+    let mock = MockSystemOps::new().with_file(LOG_PATH, &torn);
+    let sys: Arc<dyn SystemOps> = Arc::new(mock);
+    let store = LogConversationStore::new(sys.clone());
+
+    let loaded = load_conversations(&store).await.unwrap();
+    assert_eq!(loaded.len(), 1);
+
+    // The damaged tail is compacted away before the next append
+    store.insert(entry("3", 3)).await.unwrap();
+    let reopened = LogConversationStore::new(sys.clone());
+    let ids: Vec<_> = load_conversations(&reopened)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|c| c.id)
+        .collect();
+    assert_eq!(ids, vec!["1", "3"]);
+}
+
+#[tokio::test]
+async fn test_log_compaction() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let store = LogConversationStore::new(sys.clone());
+
+    // Rewriting the same entry over and over must not grow the log forever
+    for i in 0..200 {
+        store.insert(entry("1", i)).await.unwrap();
+    }
+
+    let log = sys.read_to_string(&PathBuf::from(LOG_PATH)).await.unwrap();
+    assert!(log.lines().count() < 100);
+    assert_eq!(store.count().await.unwrap(), 1);
+    assert_eq!(load_conversations(&store).await.unwrap()[0].timestamp, 199);
+}
+
+#[tokio::test]
+async fn test_insert_new_and_remove() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let store = LogConversationStore::new(sys.clone());
+
+    store.insert(entry("1", 1)).await.unwrap();
+    let added = store
+        .insert_new(vec![entry("1", 1), entry("2", 2), entry("2", 2)])
+        .await
+        .unwrap();
+    assert_eq!(added, 1);
+
+    let removed = store
+        .remove(&["1".to_string(), "missing".to_string()])
+        .await
+        .unwrap();
+    assert_eq!(removed, 1);
+    assert_eq!(store.load_all().await.unwrap()[0].id, "2");
+}
+
+#[tokio::test]
+async fn test_import_ignores_history_limit() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let store: Arc<dyn ConversationStore> = Arc::new(LogConversationStore::new(sys.clone()));
+    let threads = ThreadStore::new(sys.clone(), store.clone());
+    let settings = VoiceSettings {
+        history_limit: 2,
+        ..Default::default()
+    };
+    save_voice_settings(&sys, &settings).await.unwrap();
+
+    let batch: Vec<ConversationEntry> = (1..=5).map(|i| entry(&i.to_string(), i)).collect();
+    assert_eq!(
+        import_conversations(store.as_ref(), &threads, batch.clone())
+            .await
+            .unwrap(),
+        5
+    );
+    assert_eq!(load_conversations(store.as_ref()).await.unwrap().len(), 5);
+
+    // Everything is still there, so a second import only finds duplicates
+    assert_eq!(
+        import_conversations(store.as_ref(), &threads, batch)
+            .await
+            .unwrap(),
+        0
+    );
+}
+
+fn chat(id: &str, timestamp: i64, user_input: &str, response: &str) -> ConversationEntry {
+    ConversationEntry {
+        user_input: user_input.to_string(),
+        assistant_response: response.to_string(),
+        ..entry(id, timestamp)
+    }
+}
+
+#[tokio::test]
+async fn test_search_terms_and_phrases() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let store = LogConversationStore::new(sys.clone());
+
+    store
+        .insert(chat(
+            "1",
+            1,
+            "Jak nastavit MCP server?",
+            "Upravte config.json",
+        ))
+        .await
+        .unwrap();
+    store
+        .insert(chat(
+            "2",
+            2,
+            "Server nejede",
+            "Restartujte server a zkontrolujte log",
+        ))
+        .await
+        .unwrap();
+    store
+        .insert(chat("3", 3, "Příliš žluťoučký kůň", "Úpěl ďábelské ódy"))
+        .await
+        .unwrap();
+
+    let filter = ConversationFilter::default();
+    let ids = |hits: Vec<crate::voice::SearchHit>| -> Vec<String> {
+        hits.into_iter().map(|h| h.entry.id).collect()
+    };
+
+    // Prefix match in either field, most matches first
+    let hits = search_conversations(&store, "serv", &filter, None)
+        .await
+        .unwrap();
+    assert_eq!(ids(hits), vec!["2", "1"]);
+
+    // Phrase must be consecutive
+    let hits = search_conversations(&store, "\"mcp server\"", &filter, None)
+        .await
+        .unwrap();
+    assert_eq!(ids(hits), vec!["1"]);
+    let hits = search_conversations(&store, "\"server mcp\"", &filter, None)
+        .await
+        .unwrap();
+    assert!(hits.is_empty());
+
+    // Diacritics and case are ignored
+    let hits = search_conversations(&store, "zlutoucky KUN", &filter, None)
+        .await
+        .unwrap();
+    assert_eq!(ids(hits), vec!["3"]);
+
+    // Every word must match
+    let hits = search_conversations(&store, "server ody", &filter, None)
+        .await
+        .unwrap();
+    assert!(hits.is_empty());
+}
+
+#[tokio::test]
+async fn test_search_filters_and_snippets() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let store = LogConversationStore::new(sys.clone());
+
+    let long = format!("{} Rust je skvělý {}", "a ".repeat(100), "b ".repeat(100));
+    store.insert(chat("1", 100, "rust", &long)).await.unwrap();
+    store
+        .insert(ConversationEntry {
+            voice_used: true,
+            ..chat("2", 200, "Rust hlasem", "ok")
+        })
+        .await
+        .unwrap();
+
+    let voice_only = ConversationFilter {
+        voice_used: Some(true),
+        ..Default::default()
+    };
+    let hits = search_conversations(&store, "rust", &voice_only, None)
+        .await
+        .unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].entry.id, "2");
+
+    let old_only = ConversationFilter {
+        to: Some(150),
+        ..Default::default()
+    };
+    let hits = search_conversations(&store, "rust", &old_only, None)
+        .await
+        .unwrap();
+    assert_eq!(hits.len(), 1);
+
+    let response = &hits[0].snippets[1];
+    assert_eq!(response.field, SearchField::AssistantResponse);
+    assert_eq!(response.parts.first().unwrap().text, "…");
+    assert_eq!(response.parts.last().unwrap().text, "…");
+    let highlighted: Vec<_> = response
+        .parts
+        .iter()
+        .filter(|p| p.highlight)
+        .map(|p| p.text.as_str())
+        .collect();
+    assert_eq!(highlighted, vec!["Rust"]);
+}
+
+#[tokio::test]
+async fn test_search_index_follows_updates() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let store: Arc<dyn ConversationStore> = Arc::new(LogConversationStore::new(sys.clone()));
+    let threads = ThreadStore::new(sys.clone(), store.clone());
+    let filter = ConversationFilter::default();
+
+    save_conversation(
+        &sys,
+        store.as_ref(),
+        &threads,
+        chat("1", 1, "první verze", ""),
+    )
+    .await
+    .unwrap();
+    save_conversation(
+        &sys,
+        store.as_ref(),
+        &threads,
+        chat("1", 1, "druhá verze", ""),
+    )
+    .await
+    .unwrap();
+
+    assert!(search_conversations(store.as_ref(), "prvni", &filter, None)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        search_conversations(store.as_ref(), "druha", &filter, None)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    store.remove(&["1".to_string()]).await.unwrap();
+    assert!(search_conversations(store.as_ref(), "druha", &filter, None)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_paging_is_stable_across_equal_timestamps() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let store = LogConversationStore::new(sys.clone());
+
+    // Five entries, two pairs sharing a timestamp
+    for (id, ts) in [("a", 1), ("b", 2), ("c", 2), ("d", 3), ("e", 3)] {
+        store.insert(entry(id, ts)).await.unwrap();
+    }
+
+    let filter = ConversationFilter::default();
+    let mut request = PageRequest {
+        limit: Some(2),
+        ..Default::default()
+    };
+    let mut seen = Vec::new();
+    loop {
+        let page = load_conversations_page(&store, &filter, &request)
+            .await
+            .unwrap();
+        seen.extend(page.entries.into_iter().map(|e| e.id));
+        match page.next {
+            Some(cursor) => request.before = Some(cursor),
+            None => break,
+        }
+    }
+    assert_eq!(seen, vec!["e", "d", "c", "b", "a"]);
+
+    // Oldest first, continuing after a bare timestamp
+    let request = PageRequest {
+        limit: Some(10),
+        before: Some(PageCursor {
+            timestamp: 2,
+            id: None,
+        }),
+        order: SortOrder::OldestFirst,
+    };
+    let page = load_conversations_page(&store, &filter, &request)
+        .await
+        .unwrap();
+    let ids: Vec<_> = page.entries.into_iter().map(|e| e.id).collect();
+    assert_eq!(ids, vec!["d", "e"]);
+    assert!(page.next.is_none());
+
+    // Cursors at the ends of the timestamp range don't overflow
+    for (timestamp, id, order, expected) in [
+        (i64::MAX, None, SortOrder::OldestFirst, 0),
+        (i64::MAX, Some("x"), SortOrder::OldestFirst, 0),
+        (i64::MAX, None, SortOrder::NewestFirst, 5),
+        (i64::MIN, None, SortOrder::NewestFirst, 0),
+        (i64::MIN, None, SortOrder::OldestFirst, 5),
+    ] {
+        let request = PageRequest {
+            limit: Some(10),
+            before: Some(PageCursor {
+                timestamp,
+                id: id.map(str::to_string),
+            }),
+            order,
+        };
+        let page = load_conversations_page(&store, &filter, &request)
+            .await
+            .unwrap();
+        assert_eq!(page.entries.len(), expected, "{} {:?}", timestamp, order);
+    }
+}
+
+#[tokio::test]
+async fn test_paging_filters_and_count() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let store = LogConversationStore::new(sys.clone());
+
+    for i in 0..10 {
+        store
+            .insert(ConversationEntry {
+                voice_used: i % 2 == 0,
+                played_back: i % 3 == 0,
+                ..entry(&i.to_string(), i)
+            })
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(
+        count_conversations(&store, &ConversationFilter::default())
+            .await
+            .unwrap(),
+        10
+    );
+
+    let filter = ConversationFilter {
+        from: Some(2),
+        voice_used: Some(true),
+        played_back: Some(false),
+        ..Default::default()
+    };
+    assert_eq!(count_conversations(&store, &filter).await.unwrap(), 3);
+
+    let page = load_conversations_page(&store, &filter, &PageRequest::default())
+        .await
+        .unwrap();
+    let ids: Vec<_> = page.entries.into_iter().map(|e| e.id).collect();
+    assert_eq!(ids, vec!["8", "4", "2"]);
+
+    // Re-saving with a new timestamp moves the entry in the timeline
+    store.insert(entry("0", 100)).await.unwrap();
+    let page = load_conversations_page(
+        &store,
+        &ConversationFilter::default(),
+        &PageRequest {
+            limit: Some(1),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(page.entries[0].id, "0");
+    assert_eq!(
+        count_conversations(&store, &ConversationFilter::default())
+            .await
+            .unwrap(),
+        10
+    );
+}
+
+#[tokio::test]
+async fn test_export_formats() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let store = LogConversationStore::new(sys.clone());
+
+    let response = "Použijte:\n\n```rust\nfn main() {}\n```\n\n<script>alert(1)</script>\n\n\
+                    [docs](https://doc.rust-lang.org) [klik](javascript:alert(1)) \
+                    <JavaScript:alert(2)>";
+    store
+        .insert(chat("1", 1_700_000_000_000, "Jak na **Rust**?", response))
+        .await
+        .unwrap();
+    store
+        .insert(chat("2", 1_700_000_100_000, "Jiný dotaz", "Jiná odpověď"))
+        .await
+        .unwrap();
+
+    let selection = ExportSelection {
+        ids: Some(vec!["1".to_string()]),
+        ..Default::default()
+    };
+
+    let path = Path::new("/tmp/export/out.md");
+    let report =
+        export::export_conversations(&sys, &store, &selection, ExportFormat::Markdown, path)
+            .await
+            .unwrap();
+    assert_eq!(report.exported, 1);
+    let markdown = sys.read_to_string(path).await.unwrap();
+    assert!(markdown.contains("```rust\nfn main() {}\n```"));
+    assert!(!markdown.contains("Jiný dotaz"));
+
+    let path = Path::new("/tmp/export/out.html");
+    export::export_conversations(&sys, &store, &selection, ExportFormat::Html, path)
+        .await
+        .unwrap();
+    let html = sys.read_to_string(path).await.unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<strong>Rust</strong>"));
+    // Highlighted code uses inline styles
+    assert!(html.contains("<pre style="));
+    assert!(html.contains("&lt;script&gt;"));
+    assert!(!html.contains("<script>"));
+    // Only http(s) and mailto links stay clickable
+    assert!(html.contains(r#"<a href="https://doc.rust-lang.org">docs</a>"#));
+    assert!(html.contains("klik"));
+    assert!(!html.to_lowercase().contains("javascript:alert(1)"));
+    assert!(!html.to_lowercase().contains("href=\"javascript"));
+
+    let path = Path::new("/tmp/export/out.txt");
+    export::export_conversations(&sys, &store, &selection, ExportFormat::Text, path)
+        .await
+        .unwrap();
+    let text = sys.read_to_string(path).await.unwrap();
+    assert!(text.contains("Jak na Rust?"));
+    assert!(text.contains("fn main() {}"));
+    assert!(!text.contains("```"));
+
+    // Date range selection, JSON round-trips
+    let range = ExportSelection {
+        from: Some(1_700_000_050_000),
+        ..Default::default()
+    };
+    let path = Path::new("/tmp/export/out.json");
+    export::export_conversations(&sys, &store, &range, ExportFormat::Json, path)
+        .await
+        .unwrap();
+    let json = sys.read_to_string(path).await.unwrap();
+    let entries: Vec<ConversationEntry> = serde_json::from_str(&json).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].id, "2");
+
+    // Empty selection is an error, nothing is written
+    let none = ExportSelection {
+        ids: Some(vec![]),
+        ..Default::default()
+    };
+    let path = Path::new("/tmp/export/empty.md");
+    assert!(
+        export::export_conversations(&sys, &store, &none, ExportFormat::Markdown, path)
+            .await
+            .is_err()
+    );
+    assert!(!sys.exists(path).await);
+}
+
+const CLAUDE_AI_EXPORT: &str = r#"[
+  {
+    "uuid": "conv-1",
+    "name": "Test",
+    "created_at": "2024-03-01T10:00:00.000000Z",
+    "chat_messages": [
+      {"uuid": "m1", "sender": "human", "text": "Ahoj", "content": [], "created_at": "2024-03-01T10:00:00.000000Z"},
+      {"uuid": "m2", "sender": "assistant", "text": "", "created_at": "2024-03-01T10:00:05.000000Z",
+       "content": [{"type": "text", "text": "Dobrý den"}, {"type": "tool_use", "id": "t1", "name": "web_search", "input": {"q": "x"}}]},
+      {"uuid": "m3", "sender": "human", "text": "Jsi tam?", "content": []}
+    ]
+  },
+  {
+    "uuid": "conv-2",
+    "chat_messages": [
+      {"uuid": "m4", "sender": "human", "text": "Druhá", "created_at": "2024-03-02T10:00:00Z"},
+      {"uuid": "m5", "sender": "assistant", "text": "Odpověď", "created_at": "2024-03-02T10:00:01Z"}
+    ]
+  }
+]"#;
+
+#[tokio::test]
+async fn test_import_claude_ai_export() {
+    let path = "/tmp/conversations.json";
+    let mock = MockSystemOps::new().with_file(path, CLAUDE_AI_EXPORT);
+    let sys: Arc<dyn SystemOps> = Arc::new(mock);
+    let store: Arc<dyn ConversationStore> = Arc::new(LogConversationStore::new(sys.clone()));
+    let threads = ThreadStore::new(sys.clone(), store.clone());
+
+    // Dry run reports without writing
+    let dry = ImportOptions {
+        dry_run: true,
+        ..Default::default()
+    };
+    let stats = import::import_file(&sys, store.as_ref(), &threads, Path::new(path), &dry)
+        .await
+        .unwrap();
+    assert_eq!(stats.format, ImportFormat::ClaudeAi);
+    assert_eq!((stats.found, stats.invalid, stats.new), (2, 1, 2));
+    assert_eq!(stats.imported, 0);
+    assert_eq!(store.count().await.unwrap(), 0);
+
+    let stats = import::import_file(
+        &sys,
+        store.as_ref(),
+        &threads,
+        Path::new(path),
+        &ImportOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(stats.imported, 2);
+
+    let loaded = load_conversations(store.as_ref()).await.unwrap();
+    assert_eq!(loaded[0].id, "m2");
+    assert_eq!(loaded[0].user_input, "Ahoj");
+    assert_eq!(loaded[0].assistant_response, "Dobrý den");
+    assert_eq!(loaded[0].tool_calls[0].name, "web_search");
+    assert_eq!(loaded[0].timestamp, 1_709_287_200_000);
+    assert_eq!(loaded[1].id, "m5");
+
+    // Second run only finds conflicts
+    let stats = import::import_file(
+        &sys,
+        store.as_ref(),
+        &threads,
+        Path::new(path),
+        &ImportOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!((stats.new, stats.conflicts, stats.imported), (0, 2, 0));
+}
+
+#[tokio::test]
+async fn test_import_jsonl_with_conflicts() {
+    let path = "/tmp/history.jsonl";
+    let content = [
+        r#"{"id": "1", "timestamp": 1700000000, "prompt": "Nová otázka", "response": "Nová odpověď"}"#,
+        r#"{"question": "Bez ID", "answer": "Stabilní ID", "created_at": "2024-01-01T00:00:00Z"}"#,
+        r#"{"id": "1", "user_input": "Duplicitní", "assistant_response": "v souboru"}"#,
+        r#"{"prompt": "Chybí odpověď"}"#,
+        "not json",
+    ]
+    .join("\n");
+    let mock = MockSystemOps::new().with_file(path, &content);
+    let sys: Arc<dyn SystemOps> = Arc::new(mock);
+    let store: Arc<dyn ConversationStore> = Arc::new(LogConversationStore::new(sys.clone()));
+    let threads = ThreadStore::new(sys.clone(), store.clone());
+    store
+        .insert(chat("1", 1, "Stará otázka", "Stará odpověď"))
+        .await
+        .unwrap();
+
+    let stats = import::import_file(
+        &sys,
+        store.as_ref(),
+        &threads,
+        Path::new(path),
+        &ImportOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(stats.format, ImportFormat::Jsonl);
+    assert_eq!(
+        (
+            stats.found,
+            stats.invalid,
+            stats.duplicates,
+            stats.new,
+            stats.conflicts
+        ),
+        (3, 2, 1, 1, 1)
+    );
+    assert_eq!(stats.imported, 1);
+
+    let loaded = load_conversations(store.as_ref()).await.unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded[0].user_input, "Stará otázka");
+    assert!(loaded[1].id.starts_with("jsonl-"));
+
+    // Replace overwrites the conflicting entry, the content-derived ID is stable
+    let replace = ImportOptions {
+        conflicts: ConflictPolicy::Replace,
+        ..Default::default()
+    };
+    let stats = import::import_file(&sys, store.as_ref(), &threads, Path::new(path), &replace)
+        .await
+        .unwrap();
+    assert_eq!((stats.new, stats.conflicts, stats.imported), (0, 2, 2));
+
+    let loaded = load_conversations(store.as_ref()).await.unwrap();
+    assert_eq!(loaded.len(), 2);
+    let first = loaded.iter().find(|e| e.id == "1").unwrap();
+    assert_eq!(first.user_input, "Nová otázka");
+    assert_eq!(first.timestamp, 1_700_000_000_000);
+    // Entries are overwritten in place, never deleted first
+    let log = sys.read_to_string(&PathBuf::from(LOG_PATH)).await.unwrap();
+    assert!(log
+        .lines()
+        .all(|l| { serde_json::from_str::<serde_json::Value>(l).unwrap()["op"] == "put" }));
+}
+
+#[tokio::test]
+async fn test_threads_follow_flat_history() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let conversations: Arc<dyn ConversationStore> =
+        Arc::new(LogConversationStore::new(sys.clone()));
+    conversations
+        .insert(ConversationEntry {
+            voice_used: true,
+            ..chat("msg_1", 10, "Jak funguje MCP?\nDetail", "Takto.")
+        })
+        .await
+        .unwrap();
+    conversations
+        .insert(chat("bad/id", 20, "x", "y"))
+        .await
+        .unwrap();
+
+    let threads = ThreadStore::new(sys.clone(), conversations.clone());
+    let list = threads.list().await.unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].id, "msg_1");
+    assert_eq!(list[0].title, "Jak funguje MCP?");
+    assert_eq!(list[0].message_count, 2);
+
+    let thread = threads.get("msg_1").await.unwrap();
+    assert_eq!(thread.messages[0].role, MessageRole::User);
+    assert!(thread.messages[0].voice_used);
+    assert_eq!(thread.messages[1].role, MessageRole::Assistant);
+    assert_eq!(thread.messages[1].content, "Takto.");
+
+    // Entries saved later get their thread too
+    save_conversation(
+        &sys,
+        conversations.as_ref(),
+        &threads,
+        chat("msg_2", 30, "Další", "Ano."),
+    )
+    .await
+    .unwrap();
+    let list = threads.list().await.unwrap();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0].id, "msg_2");
+
+    // The migration runs once, so a deleted thread doesn't come back even
+    // though its entry stays in the history
+    threads.delete("msg_1").await.unwrap();
+    let history = load_conversations(conversations.as_ref()).await.unwrap();
+    assert!(history.iter().any(|e| e.id == "msg_1"));
+    let reopened = ThreadStore::new(sys.clone(), conversations.clone());
+    let list = reopened.list().await.unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].id, "msg_2");
+}
+
+#[tokio::test]
+async fn test_thread_lifecycle_and_fork() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let conversations: Arc<dyn ConversationStore> =
+        Arc::new(LogConversationStore::new(sys.clone()));
+    let threads = ThreadStore::new(sys.clone(), conversations);
+
+    let thread = threads
+        .create(None, vec!["práce".to_string()])
+        .await
+        .unwrap();
+    threads
+        .add_message(&thread.id, MessageRole::User, "První otázka".to_string())
+        .await
+        .unwrap();
+    threads
+        .add_message(
+            &thread.id,
+            MessageRole::Assistant,
+            "První odpověď".to_string(),
+        )
+        .await
+        .unwrap();
+    let thread = threads
+        .add_message(&thread.id, MessageRole::User, "Druhá otázka".to_string())
+        .await
+        .unwrap();
+    assert_eq!(thread.title, "První otázka");
+    assert_eq!(thread.messages.len(), 3);
+
+    let renamed = threads.rename(&thread.id, "  Nový název ").await.unwrap();
+    assert_eq!(renamed.title, "Nový název");
+
+    let fork = threads
+        .fork(&thread.id, Some(&thread.messages[1].id))
+        .await
+        .unwrap();
+    assert_ne!(fork.id, thread.id);
+    assert_eq!(fork.title, "Nový název (větev)");
+    assert_eq!(fork.messages.len(), 2);
+    assert!(fork
+        .messages
+        .iter()
+        .all(|m| thread.messages.iter().all(|orig| orig.id != m.id)));
+    assert_eq!(fork.tags, vec!["práce"]);
+    assert!(threads.fork(&thread.id, Some("missing")).await.is_err());
+
+    // Pinned threads are listed first
+    threads.set_pinned(&thread.id, true).await.unwrap();
+    let list = threads.list().await.unwrap();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0].id, thread.id);
+    assert!(list[0].pinned);
+
+    threads.delete(&fork.id).await.unwrap();
+    assert!(threads.get(&fork.id).await.is_err());
+    assert!(threads.delete(&fork.id).await.is_err());
+    assert!(threads.get("../../etc/passwd").await.is_err());
+}
+
+#[test]
+fn test_derive_title() {
+    assert_eq!(
+        derive_title("\n  Krátký dotaz \nDruhý řádek"),
+        "Krátký dotaz"
+    );
+    let long = "ž".repeat(80);
+    let title = derive_title(&long);
+    assert_eq!(title.chars().count(), 61);
+    assert!(title.ends_with('…'));
+}
+
+#[tokio::test]
+async fn test_history_limit_keeps_starred() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let store: Arc<dyn ConversationStore> = Arc::new(LogConversationStore::new(sys.clone()));
+    let threads = ThreadStore::new(sys.clone(), store.clone());
+    save_voice_settings(
+        &sys,
+        &VoiceSettings {
+            history_limit: 1,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    save_conversation(&sys, store.as_ref(), &threads, entry("1", 1))
+        .await
+        .unwrap();
+    set_conversation_starred(store.as_ref(), "1", true)
+        .await
+        .unwrap();
+    save_conversation(&sys, store.as_ref(), &threads, entry("2", 2))
+        .await
+        .unwrap();
+    save_conversation(&sys, store.as_ref(), &threads, entry("3", 3))
+        .await
+        .unwrap();
+
+    let ids: Vec<_> = load_conversations(store.as_ref())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|e| e.id)
+        .collect();
+    assert_eq!(ids, vec!["1", "3"]);
+    assert!(set_conversation_starred(store.as_ref(), "missing", true)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_prune_by_age_and_size() {
+    const DAY: i64 = 24 * 60 * 60 * 1000;
+    let now = 100 * DAY;
+
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let conversations: Arc<dyn ConversationStore> =
+        Arc::new(LogConversationStore::new(sys.clone()));
+    let threads = ThreadStore::new(sys.clone(), conversations.clone());
+
+    conversations
+        .insert(entry("old", now - 40 * DAY))
+        .await
+        .unwrap();
+    conversations
+        .insert(ConversationEntry {
+            starred: true,
+            ..entry("old-starred", now - 50 * DAY)
+        })
+        .await
+        .unwrap();
+    conversations
+        .insert(chat("big", now - 5 * DAY, "x", &"y".repeat(2000)))
+        .await
+        .unwrap();
+    conversations.insert(entry("new", now - DAY)).await.unwrap();
+
+    let pinned = threads
+        .create(Some("Pinned".to_string()), vec![])
+        .await
+        .unwrap();
+    threads.set_pinned(&pinned.id, true).await.unwrap();
+
+    save_voice_settings(
+        &sys,
+        &VoiceSettings {
+            retention: RetentionPolicy {
+                max_age_days: Some(30),
+                max_total_bytes: Some(1500),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let preview = retention::prune(&sys, conversations.as_ref(), &threads, now, true)
+        .await
+        .unwrap();
+    assert!(preview.dry_run);
+    let planned: Vec<_> = preview
+        .deleted
+        .iter()
+        .map(|i| (i.id.as_str(), i.kind, i.reason))
+        .collect();
+    assert_eq!(
+        planned,
+        vec![
+            ("old", ItemKind::Entry, PruneReason::Age),
+            ("big", ItemKind::Entry, PruneReason::Size),
+        ]
+    );
+    assert_eq!(preview.kept_pinned, 1);
+    assert_eq!(conversations.count().await.unwrap(), 4);
+
+    let report = retention::prune(&sys, conversations.as_ref(), &threads, now, false)
+        .await
+        .unwrap();
+    assert_eq!(report.deleted, preview.deleted);
+    assert!(report.freed_bytes > 2000);
+
+    let ids: Vec<_> = load_conversations(conversations.as_ref())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|e| e.id)
+        .collect();
+    assert_eq!(ids, vec!["old-starred", "new"]);
+    // The pinned thread and the copies of the kept entries
+    assert_eq!(threads.list().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_prune_counts_thread_copies_once() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let conversations: Arc<dyn ConversationStore> =
+        Arc::new(LogConversationStore::new(sys.clone()));
+    for (id, timestamp) in [("1", 1), ("2", 2), ("3", 3)] {
+        conversations.insert(entry(id, timestamp)).await.unwrap();
+    }
+
+    // Each entry gets a thread copy with the same ID
+    let threads = ThreadStore::new(sys.clone(), conversations.clone());
+    assert_eq!(threads.list().await.unwrap().len(), 3);
+
+    save_voice_settings(
+        &sys,
+        &VoiceSettings {
+            history_limit: 2,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let report = retention::prune(&sys, conversations.as_ref(), &threads, 10, false)
+        .await
+        .unwrap();
+    let deleted: Vec<_> = report
+        .deleted
+        .iter()
+        .map(|i| (i.id.as_str(), i.kind, i.reason))
+        .collect();
+    assert_eq!(deleted, vec![("1", ItemKind::Entry, PruneReason::Count)]);
+
+    assert_eq!(conversations.count().await.unwrap(), 2);
+    let ids: Vec<_> = threads
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|t| t.id)
+        .collect();
+    assert_eq!(ids, vec!["3", "2"]);
+}
+
+fn stored(mock: &MockSystemOps, path: &str) -> String {
+    mock.files
+        .lock()
+        .unwrap()
+        .get(Path::new(path))
+        .cloned()
+        .unwrap_or_default()
+}
+
+#[tokio::test]
+async fn test_encryption_passphrase_lifecycle() {
+    let mock = Arc::new(MockSystemOps::new());
+    let sys: Arc<dyn SystemOps> = mock.clone();
+    let encryption = Arc::new(Encryption::new(sys.clone()));
+    let store: Arc<dyn ConversationStore> = Arc::new(LogConversationStore::with_encryption(
+        sys.clone(),
+        encryption.clone(),
+    ));
+    let threads = ThreadStore::with_encryption(sys.clone(), store.clone(), encryption.clone());
+
+    store
+        .insert(chat("1", 100, "tajná otázka", "tajná odpověď"))
+        .await
+        .unwrap();
+    let thread = threads
+        .create(Some("Vlákno".to_string()), Vec::new())
+        .await
+        .unwrap();
+    let thread_path = format!(
+        "/home/mockuser/.config/Claude/voice/threads/{}.json",
+        thread.id
+    );
+
+    assert!(crypto::enable_encryption(
+        &encryption,
+        store.as_ref(),
+        &threads,
+        KeySource::Passphrase,
+        Some("short"),
+    )
+    .await
+    .is_err());
+    crypto::enable_encryption(
+        &encryption,
+        store.as_ref(),
+        &threads,
+        KeySource::Passphrase,
+        Some("correct horse"),
+    )
+    .await
+    .unwrap();
+
+    let log = stored(&mock, LOG_PATH);
+    assert!(log.lines().all(|l| l.starts_with("enc1:")));
+    assert!(!log.contains("tajná"));
+    assert!(stored(&mock, &thread_path).starts_with("enc1:"));
+    assert!(!stored(&mock, &thread_path).contains("Vlákno"));
+
+    // Locked: history can't be read or written
+    crypto::lock(&encryption, store.as_ref()).await.unwrap();
+    assert!(encryption.status().await.unwrap().locked);
+    assert!(store.load_all().await.is_err());
+    assert!(store.insert(entry("2", 200)).await.is_err());
+    assert!(threads.get(&thread.id).await.is_err());
+
+    assert!(
+        crypto::unlock(&encryption, store.as_ref(), Some("wrong passphrase"))
+            .await
+            .is_err()
+    );
+    crypto::unlock(&encryption, store.as_ref(), Some("correct horse"))
+        .await
+        .unwrap();
+    assert_eq!(
+        store.load_all().await.unwrap()[0].user_input,
+        "tajná otázka"
+    );
+    assert_eq!(threads.get(&thread.id).await.unwrap().title, "Vlákno");
+
+    // Rotation re-encrypts with a single new key
+    let before = stored(&mock, LOG_PATH);
+    crypto::rotate_key(
+        &encryption,
+        store.as_ref(),
+        &threads,
+        KeySource::Passphrase,
+        Some("battery staple"),
+    )
+    .await
+    .unwrap();
+    assert_ne!(stored(&mock, LOG_PATH), before);
+    let config: serde_json::Value = serde_json::from_str(&stored(
+        &mock,
+        "/home/mockuser/.config/Claude/voice/encryption.json",
+    ))
+    .unwrap();
+    assert_eq!(config["keys"].as_array().unwrap().len(), 1);
+
+    // A fresh process needs the new passphrase
+    let reopened = Arc::new(Encryption::new(sys.clone()));
+    let store2 = LogConversationStore::with_encryption(sys.clone(), reopened.clone());
+    assert!(store2.load_all().await.is_err());
+    assert!(crypto::unlock(&reopened, &store2, Some("correct horse"))
+        .await
+        .is_err());
+    crypto::unlock(&reopened, &store2, Some("battery staple"))
+        .await
+        .unwrap();
+    assert_eq!(store2.load_all().await.unwrap().len(), 1);
+
+    crypto::disable_encryption(&encryption, store.as_ref(), &threads)
+        .await
+        .unwrap();
+    assert!(stored(&mock, LOG_PATH).contains("tajná otázka"));
+    assert!(stored(&mock, &thread_path).contains("Vlákno"));
+    assert!(!encryption.status().await.unwrap().enabled);
+}
+
+#[tokio::test]
+async fn test_encryption_keyring_unlocks_automatically() {
+    let mock = Arc::new(MockSystemOps::new());
+    let sys: Arc<dyn SystemOps> = mock.clone();
+    let encryption = Arc::new(Encryption::new(sys.clone()));
+    let store: Arc<dyn ConversationStore> = Arc::new(LogConversationStore::with_encryption(
+        sys.clone(),
+        encryption.clone(),
+    ));
+    let threads = ThreadStore::with_encryption(sys.clone(), store.clone(), encryption.clone());
+
+    store.insert(entry("1", 100)).await.unwrap();
+    crypto::enable_encryption(
+        &encryption,
+        store.as_ref(),
+        &threads,
+        KeySource::Keyring,
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(stored(&mock, LOG_PATH).starts_with("enc1:"));
+
+    // The key went to secret-tool on stdin
+    let key = mock.command_inputs.lock().unwrap().last().cloned().unwrap();
+    mock.command_outputs.lock().unwrap().insert(
+        "secret-tool lookup".to_string(),
+        (true, format!("{}\n", key).into_bytes(), String::new()),
+    );
+
+    let reopened = Arc::new(Encryption::new(sys.clone()));
+    let store2 = LogConversationStore::with_encryption(sys.clone(), reopened.clone());
+    let status = reopened.status().await.unwrap();
+    assert_eq!(status.source, Some(KeySource::Keyring));
+    assert!(!status.locked);
+    assert_eq!(store2.load_all().await.unwrap()[0].id, "1");
+}
+
+#[tokio::test]
+async fn test_failed_disable_keeps_sealing() {
+    let mock = Arc::new(MockSystemOps::new());
+    let sys: Arc<dyn SystemOps> = mock.clone();
+    let encryption = Arc::new(Encryption::new(sys.clone()));
+    let store: Arc<dyn ConversationStore> = Arc::new(LogConversationStore::with_encryption(
+        sys.clone(),
+        encryption.clone(),
+    ));
+    let threads = ThreadStore::with_encryption(sys.clone(), store.clone(), encryption.clone());
+
+    store.insert(entry("1", 100)).await.unwrap();
+    crypto::enable_encryption(
+        &encryption,
+        store.as_ref(),
+        &threads,
+        KeySource::Passphrase,
+        Some("correct horse"),
+    )
+    .await
+    .unwrap();
+
+    // History is decrypted first, then the thread rewrite fails
+    mock.fail_writes(
+        "/home/mockuser/.config/Claude/voice/threads/1.json",
+        std::io::ErrorKind::StorageFull,
+    );
+    assert!(
+        crypto::disable_encryption(&encryption, store.as_ref(), &threads)
+            .await
+            .is_err()
+    );
+    assert!(encryption.status().await.unwrap().enabled);
+    assert!(encryption.is_sealing().await.unwrap());
+
+    store.insert(entry("2", 200)).await.unwrap();
+    let log = stored(&mock, LOG_PATH);
+    assert!(log.lines().last().unwrap().starts_with("enc1:"));
+    assert_eq!(store.load_all().await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_saves_fail_without_losing_data() {
+    let settings_path = "/home/mockuser/.config/Claude/voice/voice_settings.json";
+    let mock = Arc::new(MockSystemOps::new().with_file(settings_path, "{}"));
+    let sys: Arc<dyn SystemOps> = mock.clone();
+
+    save_voice_settings(&sys, &VoiceSettings::default())
+        .await
+        .unwrap();
+    assert_eq!(stored(&mock, &format!("{}.bak", settings_path)), "{}");
+
+    let store = LogConversationStore::new(sys.clone());
+    store.insert(entry("1", 100)).await.unwrap();
+    let log = stored(&mock, LOG_PATH);
+
+    mock.write_failures.lock().unwrap().extend([
+        (
+            PathBuf::from(settings_path),
+            std::io::ErrorKind::StorageFull,
+        ),
+        (PathBuf::from(LOG_PATH), std::io::ErrorKind::StorageFull),
+    ]);
+
+    let saved = stored(&mock, settings_path);
+    assert!(matches!(
+        save_voice_settings(&sys, &VoiceSettings::default()).await,
+        Err(crate::error::AppError::Io(_))
+    ));
+    assert_eq!(stored(&mock, settings_path), saved);
+
+    assert!(store.insert(entry("2", 200)).await.is_err());
+    assert_eq!(stored(&mock, LOG_PATH), log);
+}
+
+#[test]
+fn test_audio_decode_and_resample() {
+    use base64::Engine;
+
+    // Stereo 48 kHz float PCM, channels averaged into mono
+    let frames = 4800;
+    let mut bytes = Vec::new();
+    for _ in 0..frames {
+        bytes.extend_from_slice(&0.5f32.to_le_bytes());
+        bytes.extend_from_slice(&(-0.1f32).to_le_bytes());
+    }
+    let input = AudioInput {
+        format: AudioFormat::PcmF32le,
+        data: base64::engine::general_purpose::STANDARD.encode(&bytes),
+        sample_rate: Some(48_000),
+        channels: Some(2),
+    };
+    let decoded = audio::decode(&input).unwrap();
+    assert_eq!(decoded.samples.len(), frames);
+    assert!((decoded.samples[0] - 0.2).abs() < 1e-6);
+    assert_eq!(decoded.duration_ms(), 100);
+
+    let resampled = audio::resample(&decoded, 16_000).unwrap();
+    assert_eq!(resampled.samples.len(), 1600);
+    assert!(audio::resample(&decoded, 0).is_err());
+
+    // WAV round trip loses only 16-bit precision
+    let wav = audio::encode_wav(&resampled).unwrap();
+    let back = audio::decode_wav(&wav).unwrap();
+    assert_eq!(back.sample_rate, 16_000);
+    assert!((back.samples[10] - 0.2).abs() < 1e-3);
+
+    // A zero rate in the header is rejected instead of dividing by it
+    let mut zero_rate = wav.clone();
+    zero_rate[24..28].copy_from_slice(&0u32.to_le_bytes());
+    assert!(matches!(
+        audio::decode_wav(&zero_rate),
+        Err(AppError::Voice(_))
+    ));
+    let no_channels = AudioInput {
+        channels: Some(0),
+        ..input.clone()
+    };
+    assert!(matches!(
+        audio::decode(&no_channels),
+        Err(AppError::Voice(_))
+    ));
+
+    let missing_rate = AudioInput {
+        sample_rate: None,
+        ..input
+    };
+    assert!(audio::decode(&missing_rate).is_err());
+}
+
+#[test]
+fn test_whisper_output_parsing() {
+    let stdout = "\n[00:00:00.000 --> 00:00:02.480]   Dobrý den.\n\
+                  [00:00:02.480 --> 00:01:03.000]  Jak se máte?\n\
+                  [00:01:03.000 --> 00:01:04.000]   [BLANK_AUDIO]\n";
+    let segments = stt::parse_output(stdout);
+
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[0].text, "Dobrý den.");
+    assert_eq!((segments[1].start_ms, segments[1].end_ms), (2480, 63000));
+
+    assert_eq!(profiles::language_code("cs-CZ"), "cs");
+    assert_eq!(profiles::language_code("EN_us"), "en");
+    assert_eq!(profiles::language_code(""), "auto");
+}
+
+/// 440 Hz sine, loud enough to count as speech
+fn tone(sample_rate: u32, ms: u32) -> Vec<f32> {
+    (0..sample_rate * ms / 1000)
+        .map(|i| 0.3 * (i as f32 * 440.0 * std::f32::consts::TAU / sample_rate as f32).sin())
+        .collect()
+}
+
+fn silence(sample_rate: u32, ms: u32) -> Vec<f32> {
+    vec![0.0; (sample_rate * ms / 1000) as usize]
+}
+
+/// Speech at 500-1100 ms and 2800-3200 ms, a key click at 2000 ms
+fn two_utterances() -> audio::Audio {
+    let rate = 16_000;
+    let mut samples = silence(rate, 500);
+    samples.extend(tone(rate, 600));
+    samples.extend(silence(rate, 900));
+    samples.extend(vec![0.5; (rate * 60 / 1000) as usize]);
+    samples.extend(silence(rate, 740));
+    samples.extend(tone(rate, 400));
+    samples.extend(silence(rate, 300));
+    audio::Audio {
+        samples,
+        sample_rate: rate,
+    }
+}
+
+#[test]
+fn test_vad_splits_utterances_and_drops_noise() {
+    let audio = two_utterances();
+    let settings = VadSettings::default();
+    assert_eq!(
+        vad::detect(&audio, &settings),
+        vec![
+            Utterance {
+                start_ms: 350,
+                end_ms: 1250
+            },
+            Utterance {
+                start_ms: 2650,
+                end_ms: 3350
+            },
+        ]
+    );
+
+    let clips = vad::split(&audio, &settings);
+    assert_eq!(clips[0].1.duration_ms(), 900);
+    assert_eq!(clips[1].1.samples.len(), 16 * 700);
+
+    // A short pause keeps one utterance
+    let settings = VadSettings {
+        min_silence_ms: 2000,
+        ..Default::default()
+    };
+    assert_eq!(vad::detect(&audio, &settings).len(), 1);
+
+    // Uninterrupted speech isn't mistaken for background noise
+    let speech = audio::Audio {
+        samples: tone(16_000, 1000),
+        sample_rate: 16_000,
+    };
+    assert_eq!(vad::detect(&speech, &VadSettings::default()).len(), 1);
+
+    let quiet = audio::Audio {
+        samples: silence(16_000, 1000),
+        sample_rate: 16_000,
+    };
+    assert!(vad::detect(&quiet, &VadSettings::default()).is_empty());
+}
+
+#[tokio::test]
+async fn test_transcribe_with_vad_offsets_segments() {
+    let model = "/home/mockuser/.local/share/Claude/models/whisper/ggml-base.bin";
+    let mock = Arc::new(
+        MockSystemOps::new()
+            .with_file(model, "model")
+            .with_command_output(
+                "whisper-cli",
+                true,
+                "[00:00:00.000 --> 00:00:00.500]  Ahoj\n",
+                "",
+            ),
+    );
+    let sys: Arc<dyn SystemOps> = mock.clone();
+    let settings = VoiceSettings::default();
+
+    let transcript = stt::transcribe_speech(sys.as_ref(), &settings, &two_utterances())
+        .await
+        .unwrap();
+    assert_eq!(transcript.text, "Ahoj Ahoj");
+    let starts: Vec<i64> = transcript.segments.iter().map(|s| s.start_ms).collect();
+    assert_eq!(starts, vec![350, 2650]);
+    assert_eq!(transcript.segments[1].end_ms, 3150);
+    assert_eq!(mock.commands.lock().unwrap().len(), 2);
+
+    // Silence never reaches whisper
+    let quiet = audio::Audio {
+        samples: silence(16_000, 1000),
+        sample_rate: 16_000,
+    };
+    let transcript = stt::transcribe_speech(sys.as_ref(), &settings, &quiet)
+        .await
+        .unwrap();
+    assert!(transcript.segments.is_empty());
+    assert_eq!(mock.commands.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_push_to_talk_shortcut_parsing() {
+    let defaults = VoiceSettings::default();
+    assert!(!defaults.push_to_talk.enabled);
+    assert!(ptt::parse_shortcut(&defaults.push_to_talk.shortcut).is_ok());
+    assert!(ptt::parse_shortcut(" Alt+F9 ").is_ok());
+    assert!(ptt::parse_shortcut("Ctrl+Nope").is_err());
+
+    // An unusable shortcut isn't saved
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+    let settings = VoiceSettings {
+        push_to_talk: ptt::PushToTalk {
+            enabled: true,
+            shortcut: "Ctrl+Nope".to_string(),
+        },
+        ..Default::default()
+    };
+    assert!(save_voice_settings(&sys, &settings).await.is_err());
+
+    // Settings saved before VAD and push-to-talk existed still load
+    let old: VoiceSettings = serde_json::from_str(
+        r#"{"input_language":"cs-CZ","output_voice":"default","output_speed":1.0,
+            "auto_play":false,"history_limit":100}"#,
+    )
+    .unwrap();
+    assert!(old.vad.enabled);
+    assert_eq!(old.push_to_talk, ptt::PushToTalk::default());
+}
+
+#[tokio::test]
+async fn test_transcribe_runs_whisper_with_settings() {
+    let model = "/home/mockuser/.local/share/Claude/models/whisper/ggml-small.bin";
+    let mock = Arc::new(
+        MockSystemOps::new()
+            .with_file(model, "model")
+            .with_command_output(
+                "whisper-cli",
+                true,
+                "[00:00:00.000 --> 00:00:01.000]  Ahoj\n",
+                "",
+            ),
+    );
+    let sys: Arc<dyn SystemOps> = mock.clone();
+    save_voice_settings(
+        &sys,
+        &with_profile(
+            "cs-CZ",
+            LanguageProfile {
+                stt_model: "ggml-small.bin".to_string(),
+                ..Default::default()
+            },
+        ),
+    )
+    .await
+    .unwrap();
+
+    let wav = audio::encode_wav(&audio::Audio {
+        samples: tone(8000, 1000),
+        sample_rate: 8000,
+    })
+    .unwrap();
+    let input = AudioInput {
+        format: AudioFormat::Wav,
+        data: {
+            use base64::Engine;
+            base64::engine::general_purpose::STANDARD.encode(wav)
+        },
+        sample_rate: None,
+        channels: None,
+    };
+
+    let transcript = transcribe_audio(&sys, &input).await.unwrap();
+    assert_eq!(transcript.language, "cs");
+    assert_eq!(transcript.text, "Ahoj");
+    assert_eq!(transcript.segments[0].end_ms, 1000);
+
+    let (program, args) = mock.commands.lock().unwrap().last().cloned().unwrap();
+    assert_eq!(program, "whisper-cli");
+    assert!(args.windows(2).any(|w| w == ["-m", model]));
+    assert!(args.windows(2).any(|w| w == ["-l", "cs"]));
+    // The temporary WAV is gone
+    assert!(mock.blobs.lock().unwrap().is_empty());
+
+    // Switching to a model that isn't installed is refused
+    assert!(matches!(
+        save_voice_settings(&sys, &VoiceSettings::default()).await,
+        Err(AppError::Voice(_))
+    ));
+
+    // A model removed later is reported before running anything
+    mock.files.lock().unwrap().remove(Path::new(model));
+    assert!(transcribe_audio(&sys, &input).await.is_err());
+}
+
+#[test]
+fn test_tts_voice_spec_and_sentences() {
+    let spec = VoiceSpec::parse("piper:cs_CZ-jirka-medium", "cs-CZ");
+    assert_eq!(spec.engine, TtsEngineKind::Piper);
+    assert_eq!(spec.voice, "cs_CZ-jirka-medium");
+
+    let spec = VoiceSpec::parse("default", "en-US");
+    assert_eq!(
+        (spec.engine, spec.voice.as_str()),
+        (TtsEngineKind::Espeak, "en")
+    );
+    assert_eq!(VoiceSpec::parse("espeak:cs+f2", "en").voice, "cs+f2");
+    // WebKit voice names from older settings fall back to the default voice
+    assert_eq!(
+        VoiceSpec::parse("Google čeština", "cs-CZ"),
+        VoiceSpec::parse("default", "cs-CZ")
+    );
+    assert_eq!(VoiceSpec::parse("espeak:", "cs-CZ").voice, "cs");
+
+    assert_eq!(tts::clamp_speed(f32::NAN), 1.0);
+    assert_eq!(tts::clamp_speed(-3.0), 0.5);
+
+    let chunks = tts::split_sentences("Ahoj. Verze 1.5 je venku!\nDalší řádek bez tečky");
+    assert_eq!(
+        chunks,
+        vec!["Ahoj.", "Verze 1.5 je venku!", "Další řádek bez tečky"]
+    );
+}
+
+#[tokio::test]
+async fn test_tts_engines_use_voice_and_speed() {
+    let model = "/home/mockuser/.local/share/Claude/models/piper/cs_CZ-jirka-medium.onnx";
+    let pcm: Vec<u8> = [0i16, 16384, -16384]
+        .iter()
+        .flat_map(|s| s.to_le_bytes())
+        .collect();
+    let wav = audio::encode_wav(&audio::Audio {
+        samples: vec![0.25; 100],
+        sample_rate: 22_050,
+    })
+    .unwrap();
+    let mock = Arc::new(
+        MockSystemOps::new()
+            .with_file(model, "onnx")
+            .with_file(
+                &format!("{}.json", model),
+                r#"{"audio": {"sample_rate": 16000}}"#,
+            )
+            .with_command_bytes("piper", &pcm)
+            .with_command_bytes("espeak-ng", &wav),
+    );
+    let sys: Arc<dyn SystemOps> = mock.clone();
+
+    let settings = with_profile(
+        "cs-CZ",
+        LanguageProfile {
+            output_voice: "piper:cs_CZ-jirka-medium".to_string(),
+            output_speed: 2.0,
+            ..Default::default()
+        },
+    );
+    let audio = tts::synthesize(sys.as_ref(), &settings, "cs-CZ", "Dobrý den")
+        .await
+        .unwrap();
+    assert_eq!(audio.sample_rate, 16_000);
+    assert_eq!(audio.samples, vec![0.0, 0.5, -0.5]);
+
+    let (program, args) = mock.commands.lock().unwrap().last().cloned().unwrap();
+    assert_eq!(program, "piper");
+    assert!(args.windows(2).any(|w| w == ["--length_scale", "0.500"]));
+    assert_eq!(
+        mock.command_inputs.lock().unwrap().last().unwrap(),
+        "Dobrý den"
+    );
+
+    let settings = with_profile(
+        "cs-CZ",
+        LanguageProfile {
+            output_speed: 0.8,
+            ..Default::default()
+        },
+    );
+    let audio = tts::synthesize(sys.as_ref(), &settings, "cs-CZ", "Ahoj")
+        .await
+        .unwrap();
+    assert_eq!(audio.samples.len(), 100);
+    let (program, args) = mock.commands.lock().unwrap().last().cloned().unwrap();
+    assert_eq!(program, "espeak-ng");
+    assert_eq!(args[..4], ["-v", "cs", "-s", "140"]);
+
+    // Streaming hands out one chunk per sentence and stops when cancelled
+    let mut seen = Vec::new();
+    let (_cancel_tx, cancel_rx) = tokio::sync::oneshot::channel();
+    let completed = tts::stream(&sys, "První. Druhá.", cancel_rx, |i, text, _, last| {
+        seen.push((i, text.to_string(), last))
+    })
+    .await
+    .unwrap();
+    assert!(completed);
+    assert_eq!(
+        seen,
+        vec![
+            (0, "První.".to_string(), false),
+            (1, "Druhá.".to_string(), true)
+        ]
+    );
+
+    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel();
+    cancel_tx.send(()).unwrap();
+    let completed = tts::stream(&sys, "První. Druhá.", cancel_rx, |_, _, _, _| {
+        panic!("cancelled stream must not synthesize")
+    })
+    .await
+    .unwrap();
+    assert!(!completed);
+}
+
+#[tokio::test]
+async fn test_list_voices_and_models() {
+    let models = "/home/mockuser/.local/share/Claude/models";
+    let espeak =
+        "Pty Language       Age/Gender VoiceName          File                 Other Languages\n \
+                  5  cs              --/M      Czech              zlw/cs\n \
+                  5  en-us           --/F      English_(America)  gmw/en-US            (en 2)\n";
+    let mock = MockSystemOps::new()
+        .with_file(
+            &format!("{}/piper/cs_CZ-jirka-medium.onnx", models),
+            "0123456789",
+        )
+        .with_file(
+            &format!("{}/piper/cs_CZ-jirka-medium.onnx.json", models),
+            r#"{"language": {"code": "cs_CZ"}, "dataset": "jirka", "audio": {"quality": "medium"}}"#,
+        )
+        .with_file(&format!("{}/whisper/ggml-base.bin", models), "base")
+        .with_file(&format!("{}/whisper/ggml-small.en-q5_1.bin", models), "small")
+        .with_file(&format!("{}/whisper/notes.txt", models), "x")
+        .with_command_output("espeak-ng", true, espeak, "");
+    let sys: Arc<dyn SystemOps> = Arc::new(mock);
+
+    let voices = list_tts_voices(&sys).await.unwrap();
+    assert_eq!(voices.len(), 3);
+    assert_eq!(voices[0].id, "piper:cs_CZ-jirka-medium");
+    assert_eq!(voices[0].language, "cs-CZ");
+    assert_eq!(voices[0].quality.as_deref(), Some("medium"));
+    assert_eq!(voices[0].size_bytes, Some(10));
+    assert_eq!(voices[2].name, "English (America)");
+    assert_eq!(voices[2].gender.as_deref(), Some("female"));
+    // "default" speaks the input language with espeak-ng
+    let selected: Vec<&str> = voices
+        .iter()
+        .filter(|v| v.selected)
+        .map(|v| v.id.as_str())
+        .collect();
+    assert_eq!(selected, vec!["espeak:cs"]);
+
+    // An unknown voice name marks the default voice it falls back to
+    let mut settings = VoiceSettings::default();
+    if let Some(profile) = settings.profiles.get_mut("cs-CZ") {
+        profile.output_voice = "Google čeština".to_string();
+    }
+    let voices = catalog::list_voices(sys.as_ref(), &settings).await.unwrap();
+    let selected: Vec<&str> = voices
+        .iter()
+        .filter(|v| v.selected)
+        .map(|v| v.id.as_str())
+        .collect();
+    assert_eq!(selected, vec!["espeak:cs"]);
+
+    let stt_models = list_stt_models(&sys).await.unwrap();
+    assert_eq!(stt_models.len(), 2);
+    let small = stt_models.iter().find(|m| m.size_class == "small").unwrap();
+    assert!(!small.multilingual);
+    assert_eq!(small.quantization.as_deref(), Some("q5_1"));
+    assert!(stt_models
+        .iter()
+        .any(|m| m.id == "ggml-base.bin" && m.selected && m.multilingual));
+}
+
+#[tokio::test]
+async fn test_model_manager_register_verify_delete() {
+    use sha2::{Digest, Sha256};
+
+    let models = "/home/mockuser/.local/share/Claude/models";
+    let mock = Arc::new(
+        MockSystemOps::new()
+            .with_file("/downloads/ggml-base.bin", "whisper weights")
+            .with_file("/downloads/voices/cs_CZ-jirka-medium.onnx", "piper weights")
+            .with_file("/downloads/voices/cs_CZ-jirka-medium.onnx.json", "{}")
+            .with_file("/downloads/voices/README.md", "docs"),
+    );
+    let sys: Arc<dyn SystemOps> = mock.clone();
+    let fetcher = models::LocalFetcher;
+
+    // A wrong checksum registers nothing and leaves no staged files
+    let err = models::register(
+        sys.as_ref(),
+        &fetcher,
+        "/downloads/ggml-base.bin",
+        Some("00"),
+    )
+    .await;
+    assert!(err.is_err());
+    assert!(models::list_models(sys.as_ref()).await.unwrap().is_empty());
+    assert!(!sys.exists(Path::new(&format!("{}/.staging", models))).await);
+
+    let expected = format!("{:x}", Sha256::digest(b"whisper weights"));
+    let whisper = models::register(
+        sys.as_ref(),
+        &fetcher,
+        "/downloads/ggml-base.bin",
+        Some(&expected.to_uppercase()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(whisper[0].name, "ggml-base.bin");
+    assert_eq!(whisper[0].files[0].sha256, expected);
+
+    // A directory registers the voice together with its config
+    let piper = models::register(sys.as_ref(), &fetcher, "/downloads/voices", None)
+        .await
+        .unwrap();
+    assert_eq!(piper.len(), 1);
+    assert_eq!(piper[0].kind, ModelKind::Piper);
+    assert_eq!(piper[0].name, "cs_CZ-jirka-medium");
+    assert_eq!(piper[0].files.len(), 2);
+    assert!(
+        sys.exists(Path::new(&format!(
+            "{}/piper/cs_CZ-jirka-medium.onnx.json",
+            models
+        )))
+        .await
+    );
+
+    let usage = models::disk_usage(sys.as_ref()).await.unwrap();
+    assert_eq!(usage.whisper_bytes, 15);
+    assert_eq!(usage.piper_bytes, 15);
+    assert_eq!(usage.unregistered_bytes, 0);
+
+    // Tampering is detected
+    sys.remove_file(Path::new(&format!(
+        "{}/piper/cs_CZ-jirka-medium.onnx.json",
+        models
+    )))
+    .await
+    .unwrap();
+    mock.blobs.lock().unwrap().insert(
+        PathBuf::from(format!("{}/whisper/ggml-base.bin", models)),
+        b"corrupted".to_vec(),
+    );
+    let results = models::verify(sys.as_ref()).await.unwrap();
+    assert!(results.iter().all(|r| !r.ok));
+    assert!(results
+        .iter()
+        .any(|r| r.problems[0].contains("Chybí soubor piper/cs_CZ-jirka-medium.onnx.json")));
+
+    models::delete(sys.as_ref(), ModelKind::Whisper, "ggml-base.bin")
+        .await
+        .unwrap();
+    assert!(!sys.exists(Path::new(&format!("{}/whisper", models))).await);
+    assert_eq!(models::list_models(sys.as_ref()).await.unwrap().len(), 1);
+    assert!(
+        models::delete(sys.as_ref(), ModelKind::Whisper, "ggml-base.bin")
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_model_manager_cleans_up_and_stays_in_its_directory() {
+    let models = "/home/mockuser/.local/share/Claude/models";
+    let mock = Arc::new(
+        MockSystemOps::new()
+            .with_file("/downloads/voices/cs_CZ-jirka-medium.onnx", "piper weights")
+            .with_file("/downloads/voices/cs_CZ-jirka-medium.onnx.json", "{}")
+            .with_write_failure(
+                &format!("{}/piper/cs_CZ-jirka-medium.onnx.json", models),
+                std::io::ErrorKind::StorageFull,
+            ),
+    );
+    let sys: Arc<dyn SystemOps> = mock.clone();
+
+    // The config can't be moved into place, so the voice already moved goes too
+    assert!(models::register(
+        sys.as_ref(),
+        &models::LocalFetcher,
+        "/downloads/voices",
+        None
+    )
+    .await
+    .is_err());
+    assert!(!sys.exists(Path::new(&format!("{}/piper", models))).await);
+    assert!(models::list_models(sys.as_ref()).await.unwrap().is_empty());
+
+    // A manifest pointing outside the models directory deletes nothing
+    let manifest = serde_json::json!({
+        "version": 1,
+        "models": [{
+            "kind": "whisper",
+            "name": "evil.bin",
+            "files": [
+                {"path": "/etc/passwd", "sha256": "", "size": 0},
+                {"path": "../../../../.bashrc", "sha256": "", "size": 0}
+            ],
+            "registered_at": 0
+        }]
+    });
+    let mock = Arc::new(
+        MockSystemOps::new()
+            .with_file(&format!("{}/manifest.json", models), &manifest.to_string())
+            .with_file("/etc/passwd", "root")
+            .with_file("/home/mockuser/.bashrc", "alias"),
+    );
+    let sys: Arc<dyn SystemOps> = mock.clone();
+    assert!(models::delete(sys.as_ref(), ModelKind::Whisper, "evil.bin")
+        .await
+        .is_err());
+    assert!(sys.exists(Path::new("/etc/passwd")).await);
+    assert!(sys.exists(Path::new("/home/mockuser/.bashrc")).await);
+    let results = models::verify(sys.as_ref()).await.unwrap();
+    assert_eq!(results[0].problems.len(), 2);
+}
+
+#[tokio::test]
+async fn test_export_audio_with_chapters() {
+    // Every sentence comes back as 125 ms of audio
+    let wav = audio::encode_wav(&audio::Audio {
+        samples: vec![0.25; 1000],
+        sample_rate: 8000,
+    })
+    .unwrap();
+    let mock = Arc::new(MockSystemOps::new().with_command_bytes("espeak-ng", &wav));
+    let sys: Arc<dyn SystemOps> = mock.clone();
+    let store = LogConversationStore::new(sys.clone());
+    for (id, response) in [
+        ("1", "První věta. Druhá věta."),
+        ("2", "Jiná odpověď"),
+        ("3", "Tohle se neexportuje"),
+    ] {
+        store
+            .insert(chat(
+                id,
+                1_700_000_000_000 + id.parse::<i64>().unwrap(),
+                "Dotaz",
+                response,
+            ))
+            .await
+            .unwrap();
+    }
+
+    let ids = vec!["2".to_string(), "1".to_string()];
+    let mut progress = Vec::new();
+    let path = Path::new("/tmp/export/odpovedi.wav");
+    let report =
+        audio_export::export_audio(&sys, &store, &ids, AudioExportFormat::Wav, path, |p| {
+            progress.push((p.entry_id, p.processed, p.total))
+        })
+        .await
+        .unwrap();
+
+    let spans: Vec<(&str, i64, i64)> = report
+        .chapters
+        .iter()
+        .map(|c| (c.entry_id.as_str(), c.start_ms, c.end_ms))
+        .collect();
+    assert_eq!(spans, vec![("1", 0, 250), ("2", 1050, 1175)]);
+    assert!(report.chapters[0].title.ends_with("Dotaz"));
+    assert_eq!(report.duration_ms, 1175);
+    assert_eq!(
+        progress,
+        vec![("1".to_string(), 1, 2), ("2".to_string(), 2, 2)]
+    );
+
+    let bytes = sys.read_bytes(path).await.unwrap();
+    assert!(bytes.windows(4).any(|w| w == b"cue "));
+    assert!(bytes.windows(4).any(|w| w == b"labl"));
+    assert_eq!(
+        u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize,
+        bytes.len() - 8
+    );
+    assert_eq!(audio::decode_wav(&bytes).unwrap().duration_ms(), 1175);
+
+    // Ogg/Opus goes through opusenc with Vorbis chapter comments
+    let path = Path::new("/tmp/export/odpovedi.opus");
+    audio_export::export_audio(&sys, &store, &ids, AudioExportFormat::OggOpus, path, |_| {})
+        .await
+        .unwrap();
+    let (program, args) = mock.commands.lock().unwrap().last().cloned().unwrap();
+    assert_eq!(program, "opusenc");
+    assert!(args.contains(&"CHAPTER002=00:00:01.050".to_string()));
+    assert_eq!(
+        args.last().map(String::as_str),
+        Some("/tmp/export/odpovedi.opus")
+    );
+    assert!(!mock
+        .blobs
+        .lock()
+        .unwrap()
+        .keys()
+        .any(|p| p.starts_with("/tmp/claude-export")));
+
+    // Unknown IDs are reported instead of silently skipped
+    let missing = vec!["1".to_string(), "42".to_string()];
+    assert!(audio_export::export_audio(
+        &sys,
+        &store,
+        &missing,
+        AudioExportFormat::Wav,
+        path,
+        |_| {}
+    )
+    .await
+    .is_err());
+}
+
+#[test]
+fn test_wav_chapter_chunks() {
+    let mut wav = audio::encode_wav(&audio::Audio {
+        samples: vec![0.0; 100],
+        sample_rate: 1000,
+    })
+    .unwrap();
+    let plain = wav.len();
+    let chapters = vec![Chapter {
+        entry_id: "1".to_string(),
+        title: "Úvod".to_string(),
+        start_ms: 50,
+        end_ms: 100,
+    }];
+    audio_export::add_wav_chapters(&mut wav, &chapters, 1000);
+
+    // cue: 8 + 4 + 24, LIST: 8 + "adtl" + labl (8 + id + "Úvod\0" padded)
+    assert_eq!(wav.len(), plain + 36 + 8 + 4 + 8 + 4 + 6);
+    let cue = wav.windows(4).position(|w| w == b"cue ").unwrap();
+    let position = u32::from_le_bytes(wav[cue + 16..cue + 20].try_into().unwrap());
+    assert_eq!(position, 50);
+}
+
+#[test]
+fn test_markdown_to_speech() {
+    let markdown = "# Nadpis\n\nToto je **tučné** a *kurzíva* s [odkazem](https://x.cz).\n\n\
+                    - první\n- druhá\n\n\
+                    ```rust\nfn main() {\n    println!(\"hi\");\n}\n```\n\n\
+                    | A | B |\n|---|---|\n| 1 | 2 |\n";
+
+    assert_eq!(
+        normalize::markdown_to_speech(markdown, SpeechLanguage::Cs, CodeBlockMode::Summarize),
+        "Nadpis.\nToto je tučné a kurzíva s odkazem.\nprvní.\ndruhá.\n\
+         Ukázka kódu v jazyce rust, počet řádků: 3.\nA, B.\n1, 2."
+    );
+
+    let skipped = normalize::markdown_to_speech(markdown, SpeechLanguage::En, CodeBlockMode::Skip);
+    assert!(!skipped.contains("main") && !skipped.contains("Code sample"));
+    assert!(!skipped.contains('*') && !skipped.contains('|'));
+
+    let read = normalize::markdown_to_speech(markdown, SpeechLanguage::En, CodeBlockMode::Read);
+    assert!(read.contains("println!(\"hi\");"));
+
+    assert_eq!(
+        normalize::markdown_to_speech(
+            "```\nx\ny\n```",
+            SpeechLanguage::En,
+            CodeBlockMode::Summarize
+        ),
+        "Code sample, 2 lines."
+    );
+}
+
+#[test]
+fn test_expand_numbers_units_and_abbreviations() {
+    let cs = |text| normalize::expand(text, SpeechLanguage::Cs);
+    assert_eq!(
+        cs("Stojí 25 Kč a váží 3,5 kg."),
+        "Stojí dvacet pět korun a váží tři celé pět kilogramu."
+    );
+    assert_eq!(
+        cs("1 kg, 2 kg, 2 min, 1s"),
+        "jeden kilogram, dva kilogramy, dvě minuty, jedna sekunda"
+    );
+    assert_eq!(
+        cs("50 % a -5 °C"),
+        "padesát procent a mínus pět stupňů Celsia"
+    );
+    assert_eq!(
+        cs("1234567"),
+        "milion dvě stě třicet čtyři tisíc pět set šedesát sedm"
+    );
+    assert_eq!(
+        cs("Rok 2024, kód 007"),
+        "Rok dva tisíce dvacet čtyři, kód nula nula sedm"
+    );
+    assert_eq!(
+        cs("Např. verze 1.5.2 atd."),
+        "Např. verze jedna tečka pět tečka dva a tak dále."
+    );
+    assert_eq!(cs("např. mp3 a 5 m"), "například mp3 a pět m");
+
+    let en = |text| normalize::expand(text, SpeechLanguage::En);
+    assert_eq!(
+        en("It costs 1,500 dollars, i.e. 2.5 GB etc."),
+        "It costs one thousand five hundred dollars, that is two point five gigabytes et cetera."
+    );
+    assert_eq!(
+        en("1 km vs. 21 MB"),
+        "one kilometer versus twenty-one megabytes"
+    );
+    assert_eq!(en("Ask Dr. Smith"), "Ask Doctor Smith");
+    assert_eq!(
+        normalize::number_to_words(1_000_012, SpeechLanguage::En),
+        "one million twelve"
+    );
+}
+
+#[test]
+fn test_prepare_speech_chunks() {
+    let settings = VoiceSettings::default();
+    assert_eq!(
+        normalize::prepare("**Ahoj**. Mám 3 body:\n\n```\nx\n```", &settings, "cs-CZ"),
+        vec!["Ahoj.", "Mám tři body:", "Ukázka kódu, počet řádků: jedna."]
+    );
+
+    let settings = VoiceSettings {
+        code_blocks: CodeBlockMode::Skip,
+        ..Default::default()
+    };
+    assert_eq!(
+        normalize::prepare(
+            "Run `cargo test` twice:\n\n```sh\ncargo test\n```",
+            &settings,
+            "en-US"
+        ),
+        vec!["Run cargo test twice:"]
+    );
+}
+
+#[test]
+fn test_voice_settings_migrate_to_profiles() {
+    // A file from before profiles becomes one profile for its language
+    let old: VoiceSettings = serde_json::from_str(
+        r#"{"input_language":"en-US","output_voice":"piper:en_US-amy-low","output_speed":1.5,
+            "auto_play":true,"history_limit":50,"stt_model":"ggml-small.en.bin"}"#,
+    )
+    .unwrap();
+    assert_eq!(old.profiles.len(), 1);
+    assert_eq!(
+        old.profiles["en-US"],
+        LanguageProfile {
+            stt_model: "ggml-small.en.bin".to_string(),
+            output_voice: "piper:en_US-amy-low".to_string(),
+            output_speed: 1.5,
+        }
+    );
+    assert!(old.auto_play);
+    assert_eq!(old.history_limit, 50);
+
+    // Saved settings mirror the input-language profile into the flat fields
+    let mut settings = VoiceSettings::default();
+    settings.profiles.get_mut("cs-CZ").unwrap().output_speed = 1.25;
+    let json = serde_json::to_value(&settings).unwrap();
+    assert_eq!(json["output_speed"], 1.25);
+    assert_eq!(json["profiles"]["en-US"]["output_voice"], "default");
+
+    // An older client only edits the flat fields, which win for its language
+    let mut edited = json.clone();
+    edited["output_speed"] = serde_json::json!(0.75);
+    let edited: VoiceSettings = serde_json::from_value(edited).unwrap();
+    assert_eq!(edited.profiles["cs-CZ"].output_speed, 0.75);
+    assert_eq!(edited.profiles["en-US"].output_speed, 1.0);
+
+    let roundtrip: VoiceSettings = serde_json::from_value(json).unwrap();
+    assert_eq!(roundtrip.profiles, settings.profiles);
+}
+
+#[test]
+fn test_profile_lookup_and_language_detection() {
+    let settings = with_profile(
+        "en-US",
+        LanguageProfile {
+            output_voice: "espeak:en-us".to_string(),
+            ..Default::default()
+        },
+    );
+    assert_eq!(settings.profile("en-GB").output_voice, "espeak:en-us");
+    // Unknown languages fall back to the input language's profile
+    assert_eq!(settings.profile("de-DE"), settings.profiles["cs-CZ"]);
+
+    let candidates = ["cs-CZ", "en-US"];
+    assert_eq!(
+        profiles::detect_language("Tohle je odpověď, která je česky.", &candidates),
+        Some("cs-CZ")
+    );
+    assert_eq!(
+        profiles::detect_language(
+            "This is the answer.\n\n```rust\nlet je = a;\n```",
+            &candidates
+        ),
+        Some("en-US")
+    );
+    assert_eq!(profiles::detect_language("42", &candidates), None);
+
+    assert_eq!(settings.response_language("The file is ready."), "en-US");
+    assert_eq!(settings.response_language("123"), "cs-CZ");
+    let fixed = VoiceSettings {
+        detect_response_language: false,
+        ..settings
+    };
+    assert_eq!(fixed.response_language("The file is ready."), "cs-CZ");
+}
+
+#[tokio::test]
+async fn test_speech_uses_detected_language_profile() {
+    let wav = audio::encode_wav(&audio::Audio {
+        samples: vec![0.0; 10],
+        sample_rate: 8000,
+    })
+    .unwrap();
+    let mock = Arc::new(MockSystemOps::new().with_command_bytes("espeak-ng", &wav));
+    let sys: Arc<dyn SystemOps> = mock.clone();
+    save_voice_settings(
+        &sys,
+        &with_profile(
+            "en-US",
+            LanguageProfile {
+                output_speed: 2.0,
+                ..Default::default()
+            },
+        ),
+    )
+    .await
+    .unwrap();
+
+    let (_cancel_tx, cancel_rx) = tokio::sync::oneshot::channel();
+    tts::stream(&sys, "It is 5 km away.", cancel_rx, |_, _, _, _| {})
+        .await
+        .unwrap();
+    let (_, args) = mock.commands.lock().unwrap().last().cloned().unwrap();
+    assert_eq!(args[..4], ["-v", "en", "-s", "350"]);
+    assert_eq!(
+        mock.command_inputs.lock().unwrap().last().unwrap(),
+        "It is five kilometers away."
+    );
+
+    let (_cancel_tx, cancel_rx) = tokio::sync::oneshot::channel();
+    tts::stream(&sys, "Je to 5 km daleko.", cancel_rx, |_, _, _, _| {})
+        .await
+        .unwrap();
+    let (_, args) = mock.commands.lock().unwrap().last().cloned().unwrap();
+    assert_eq!(args[..4], ["-v", "cs", "-s", "175"]);
+}
+
+#[test]
+fn test_language_tag_normalization() {
+    for (tag, canonical) in [
+        ("cs-CZ", "cs-CZ"),
+        ("cs_cz", "cs-CZ"),
+        (" EN-us ", "en-US"),
+        ("zh-hant-tw", "zh-Hant-TW"),
+        ("es-419", "es-419"),
+        ("zh-yue-HK", "zh-yue-HK"),
+        ("sl-rozaj-biske", "sl-rozaj-biske"),
+        ("de-CH-1996", "de-CH-1996"),
+        ("en-a-bbb-x-a-ccc", "en-a-bbb-x-a-ccc"),
+        ("x-whatever", "x-whatever"),
+    ] {
+        assert_eq!(
+            normalize_language_tag(tag).as_deref(),
+            Some(canonical),
+            "{}",
+            tag
+        );
+    }
+
+    for tag in [
+        "",
+        "c",
+        "cs-",
+        "čeština",
+        "cs CZ",
+        "cs-CZ-CZ",
+        "de-1996-1996",
+        "en-a-x-b",
+        "en-a-bb-a-cc",
+        "x",
+        "toolonglanguage",
+        "123",
+    ] {
+        assert_eq!(normalize_language_tag(tag), None, "{}", tag);
+    }
+}
+
+#[tokio::test]
+async fn test_flat_settings_save_keeps_the_rest() {
+    let sys: Arc<dyn SystemOps> = Arc::new(MockSystemOps::new());
+
+    let mut settings = VoiceSettings {
+        retention: RetentionPolicy {
+            max_age_days: Some(30),
+            ..Default::default()
+        },
+        vad: VadSettings {
+            min_silence_ms: 1200,
+            ..Default::default()
+        },
+        push_to_talk: ptt::PushToTalk {
+            enabled: true,
+            shortcut: "Alt+Space".to_string(),
+        },
+        ..Default::default()
+    };
+    settings.profiles.insert(
+        "de-DE".to_string(),
+        LanguageProfile {
+            output_speed: 1.5,
+            ..Default::default()
+        },
+    );
+    save_voice_settings(&sys, &settings).await.unwrap();
+
+    // What the settings dialog in app.js sends
+    let saved = update_voice_settings(
+        &sys,
+        &serde_json::json!({
+            "input_language": "cs-CZ",
+            "output_voice": "default",
+            "output_speed": 1.25,
+            "auto_play": true,
+            "history_limit": 20
+        }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(saved.history_limit, 20);
+
+    let loaded = crate::voice::load_voice_settings(&sys).await.unwrap();
+    let tags: Vec<&str> = loaded.profiles.keys().map(String::as_str).collect();
+    assert_eq!(tags, vec!["cs-CZ", "de-DE", "en-US"]);
+    assert_eq!(loaded.profiles["cs-CZ"].output_speed, 1.25);
+    assert_eq!(loaded.profiles["de-DE"].output_speed, 1.5);
+    assert_eq!(loaded.retention.max_age_days, Some(30));
+    assert_eq!(loaded.vad.min_silence_ms, 1200);
+    assert!(loaded.push_to_talk.enabled);
+    assert_eq!(loaded.push_to_talk.shortcut, "Alt+Space");
+    assert!(loaded.auto_play);
+
+    // Profiles sent without the flat fields aren't overwritten by the mirror
+    let mut profiles = serde_json::to_value(&loaded.profiles).unwrap();
+    profiles["cs-CZ"]["output_speed"] = serde_json::json!(0.75);
+    let saved = update_voice_settings(&sys, &serde_json::json!({ "profiles": profiles }))
+        .await
+        .unwrap();
+    assert_eq!(saved.profiles["cs-CZ"].output_speed, 0.75);
+    assert_eq!(saved.vad.min_silence_ms, 1200);
+}
+
+#[tokio::test]
+async fn test_save_validates_settings() {
+    let mock = Arc::new(MockSystemOps::new());
+    let sys: Arc<dyn SystemOps> = mock.clone();
+    let settings_path = "/home/mockuser/.config/Claude/voice/voice_settings.json";
+
+    // Fixable values are clamped and normalized
+    let mut settings = VoiceSettings {
+        input_language: "en_us".to_string(),
+        ..Default::default()
+    };
+    settings.profiles.clear();
+    for (tag, speed) in [("en-us", -3.0), ("cs-CZ", f32::NAN), ("de", 9.0)] {
+        settings.profiles.insert(
+            tag.to_string(),
+            LanguageProfile {
+                output_speed: speed,
+                ..Default::default()
+            },
+        );
+    }
+    let saved = save_voice_settings(&sys, &settings).await.unwrap();
+    assert_eq!(saved.input_language, "en-US");
+    let speeds: Vec<(&str, f32)> = saved
+        .profiles
+        .iter()
+        .map(|(tag, p)| (tag.as_str(), p.output_speed))
+        .collect();
+    assert_eq!(speeds, vec![("cs-CZ", 1.0), ("de", 2.5), ("en-US", 0.5)]);
+    let loaded = crate::voice::load_voice_settings(&sys).await.unwrap();
+    assert_eq!(loaded.profile("en-US").output_speed, 0.5);
+
+    // The rest is reported per field and nothing is written
+    let before = mock
+        .files
+        .lock()
+        .unwrap()
+        .get(Path::new(settings_path))
+        .cloned();
+    let mut invalid = VoiceSettings {
+        input_language: "čeština".to_string(),
+        history_limit: 0,
+        ..Default::default()
+    };
+    invalid
+        .profiles
+        .insert("en_US".to_string(), LanguageProfile::default());
+    invalid
+        .profiles
+        .insert("bad tag".to_string(), LanguageProfile::default());
+
+    let current = crate::voice::load_voice_settings(&sys).await.unwrap();
+    let catalog = catalog::Catalog::load(sys.as_ref(), &invalid)
+        .await
+        .unwrap();
+    let errors = validation::validate(&invalid, &current, &catalog).unwrap_err();
+    let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+    assert_eq!(
+        fields,
+        vec![
+            "input_language",
+            "history_limit",
+            "profiles.bad tag",
+            "profiles.en_US"
+        ]
+    );
+
+    let Err(AppError::Voice(json)) = save_voice_settings(&sys, &invalid).await else {
+        panic!("expected a voice error");
+    };
+    let reported: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+    assert_eq!(reported.len(), 4);
+    assert_eq!(reported[1]["field"], "history_limit");
+    assert_eq!(
+        errors[1],
+        FieldError {
+            field: "history_limit".to_string(),
+            message: "Musí být alespoň 1".to_string(),
+        }
+    );
+    // The webview still gets a plain string error
+    let message = serde_json::to_value(AppError::Voice(json)).unwrap();
+    assert!(message.as_str().unwrap().contains("\"history_limit\""));
+    assert_eq!(
+        mock.files
+            .lock()
+            .unwrap()
+            .get(Path::new(settings_path))
+            .cloned(),
+        before
+    );
+}
+
+#[tokio::test]
+async fn test_save_checks_catalog_and_vad() {
+    let models = "/home/mockuser/.local/share/Claude/models";
+    let espeak = "Pty Language Age/Gender VoiceName File Other Languages\n \
+                  5  cs              --/M      Czech              zlw/cs\n";
+    let mock = Arc::new(
+        MockSystemOps::new()
+            .with_file(&format!("{}/piper/cs_CZ-jirka-medium.onnx", models), "x")
+            .with_file(&format!("{}/whisper/ggml-small.bin", models), "small")
+            .with_command_output("espeak-ng", true, espeak, ""),
+    );
+    let sys: Arc<dyn SystemOps> = mock.clone();
+
+    // The default model isn't installed, but unchanged values aren't checked
+    // and the catalog isn't even loaded
+    save_voice_settings(&sys, &VoiceSettings::default())
+        .await
+        .unwrap();
+    assert!(mock.commands.lock().unwrap().is_empty());
+
+    let mut settings = VoiceSettings::default();
+    settings.profiles.get_mut("cs-CZ").unwrap().output_voice = "Google čeština".to_string();
+    settings.profiles.get_mut("en-US").unwrap().stt_model = "ggml-large.bin".to_string();
+    settings.profiles.insert(
+        "de-DE".to_string(),
+        LanguageProfile {
+            output_voice: "espeak:cs+f2".to_string(),
+            ..Default::default()
+        },
+    );
+    settings.vad.threshold_db = 5.0;
+    settings.vad.margin_db = f32::NAN;
+    settings.vad.min_silence_ms = 60_000;
+
+    let Err(AppError::Voice(json)) = save_voice_settings(&sys, &settings).await else {
+        panic!("expected a voice error");
+    };
+    let errors: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+    let fields: Vec<&str> = errors
+        .iter()
+        .map(|e| e["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            "profiles.cs-CZ.output_voice",
+            "profiles.en-US.stt_model",
+            "vad.threshold_db",
+            "vad.margin_db",
+            "vad.min_silence_ms"
+        ]
+    );
+
+    // Installed voices and models pass, VAD values only count when enabled
+    settings.profiles.get_mut("cs-CZ").unwrap().output_voice =
+        "piper:cs_CZ-jirka-medium".to_string();
+    settings.profiles.get_mut("en-US").unwrap().stt_model =
+        format!("{}/whisper/ggml-small.bin", models);
+    settings.vad.enabled = false;
+    let saved = save_voice_settings(&sys, &settings).await.unwrap();
+    assert_eq!(saved.profiles["de-DE"].output_voice, "espeak:cs+f2");
+}
@@ -0,0 +1,477 @@
+// Conversation threads
+// A thread owns an ordered list of messages and is stored as one JSON file in
+// voice/threads. Flat `ConversationEntry` history is migrated once into
+// single-exchange threads that keep the entry ID; entries saved or imported
+// later get their thread when they are written (`record_entries`). With
+// encryption enabled the whole file is sealed.
+
+use super::crypto::Encryption;
+use super::{ConversationEntry, ConversationStore, ToolCall};
+use crate::error::AppError;
+use crate::schema::{self, Schema};
+use crate::system::{now_millis, SystemOps};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, OnceCell};
+
+/// Marker written after flat history has been migrated
+const MIGRATION_MARKER: &str = ".migrated";
+/// Length of titles derived from the first message
+const TITLE_LENGTH: usize = 60;
+
+/// Format of a thread file
+pub const THREAD_SCHEMA: Schema = Schema {
+    name: "thread",
+    migrations: &[schema::add_version],
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageRole {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub id: String,
+    pub role: MessageRole,
+    pub content: String,
+    /// ms since epoch
+    pub timestamp: i64,
+    #[serde(default)]
+    pub voice_used: bool,
+    #[serde(default)]
+    pub played_back: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Conversation {
+    pub id: String,
+    pub title: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub messages: Vec<Message>,
+}
+
+/// Thread without its messages, for listing
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConversationSummary {
+    pub id: String,
+    pub title: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub tags: Vec<String>,
+    pub pinned: bool,
+    pub message_count: usize,
+}
+
+impl From<&Conversation> for ConversationSummary {
+    fn from(c: &Conversation) -> Self {
+        Self {
+            id: c.id.clone(),
+            title: c.title.clone(),
+            created_at: c.created_at,
+            updated_at: c.updated_at,
+            tags: c.tags.clone(),
+            pinned: c.pinned,
+            message_count: c.messages.len(),
+        }
+    }
+}
+
+/// Title from the first line of a message
+pub fn derive_title(text: &str) -> String {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or("");
+
+    if line.chars().count() <= TITLE_LENGTH {
+        return line.to_string();
+    }
+
+    let cut: String = line.chars().take(TITLE_LENGTH).collect();
+    format!("{}…", cut.trim_end())
+}
+
+fn message(role: MessageRole, content: String, timestamp: i64) -> Message {
+    Message {
+        id: uuid::Uuid::new_v4().to_string(),
+        role,
+        content,
+        timestamp,
+        voice_used: false,
+        played_back: false,
+        tool_calls: Vec::new(),
+    }
+}
+
+/// Single-exchange thread built from a flat entry
+fn from_entry(entry: ConversationEntry) -> Conversation {
+    Conversation {
+        title: derive_title(&entry.user_input),
+        created_at: entry.timestamp,
+        updated_at: entry.timestamp,
+        tags: Vec::new(),
+        pinned: entry.starred,
+        messages: vec![
+            Message {
+                id: format!("{}-user", entry.id),
+                role: MessageRole::User,
+                content: entry.user_input,
+                timestamp: entry.timestamp,
+                voice_used: entry.voice_used,
+                played_back: false,
+                tool_calls: Vec::new(),
+            },
+            Message {
+                id: format!("{}-assistant", entry.id),
+                role: MessageRole::Assistant,
+                content: entry.assistant_response,
+                timestamp: entry.timestamp,
+                voice_used: false,
+                played_back: entry.played_back,
+                tool_calls: entry.tool_calls,
+            },
+        ],
+        id: entry.id,
+    }
+}
+
+/// IDs become file names, so only allow a safe character set
+fn validate_id(id: &str) -> Result<(), AppError> {
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::Voice(format!("Neplatné ID konverzace: {}", id)))
+    }
+}
+
+fn not_found(id: &str) -> AppError {
+    AppError::Voice(format!("Konverzace {} neexistuje", id))
+}
+
+/// Thread storage, one `<id>.json` per thread
+pub struct ThreadStore {
+    sys: Arc<dyn SystemOps>,
+    conversations: Arc<dyn ConversationStore>,
+    encryption: Arc<Encryption>,
+    migrated: OnceCell<()>,
+    /// Serializes read-modify-write cycles
+    lock: Mutex<()>,
+}
+
+impl ThreadStore {
+    pub fn new(sys: Arc<dyn SystemOps>, conversations: Arc<dyn ConversationStore>) -> Self {
+        let encryption = Arc::new(Encryption::new(sys.clone()));
+        Self::with_encryption(sys, conversations, encryption)
+    }
+
+    /// Store sharing its keys with the conversation store
+    pub fn with_encryption(
+        sys: Arc<dyn SystemOps>,
+        conversations: Arc<dyn ConversationStore>,
+        encryption: Arc<Encryption>,
+    ) -> Self {
+        Self {
+            sys,
+            conversations,
+            encryption,
+            migrated: OnceCell::new(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    async fn dir(&self) -> Result<PathBuf, AppError> {
+        let dir = super::get_voice_dir(self.sys.as_ref())
+            .await?
+            .join("threads");
+
+        self.migrated
+            .get_or_try_init(|| self.migrate(dir.clone()))
+            .await?;
+
+        Ok(dir)
+    }
+
+    /// Turn flat history into single-exchange threads, once
+    async fn migrate(&self, dir: PathBuf) -> Result<(), AppError> {
+        let marker = dir.join(MIGRATION_MARKER);
+        if self.sys.exists(&marker).await {
+            return Ok(());
+        }
+
+        if !self.sys.exists(&dir).await {
+            self.sys.create_dir_all(&dir).await?;
+        }
+
+        let mut migrated = 0;
+        for entry in super::load_conversations(self.conversations.as_ref()).await? {
+            if validate_id(&entry.id).is_err() {
+                log::warn!("⚠️  Skipping entry with unusable ID: {}", entry.id);
+                continue;
+            }
+
+            let path = dir.join(format!("{}.json", entry.id));
+            if !self.sys.exists(&path).await {
+                self.write_to(&path, &from_entry(entry)).await?;
+                migrated += 1;
+            }
+        }
+
+        self.sys
+            .write_atomic(&marker, &migrated.to_string(), false)
+            .await?;
+        log::info!("📦 Migrated {} conversation entries to threads", migrated);
+        Ok(())
+    }
+
+    async fn path(&self, id: &str) -> Result<PathBuf, AppError> {
+        validate_id(id)?;
+        Ok(self.dir().await?.join(format!("{}.json", id)))
+    }
+
+    async fn write_to(&self, path: &Path, conversation: &Conversation) -> Result<(), AppError> {
+        let json = THREAD_SCHEMA.to_string_pretty(conversation)?;
+        let content = self.encryption.seal(&json).await?;
+        self.sys.write_atomic(path, &content, false).await
+    }
+
+    /// Read a thread file; `None` when it can't be decrypted or parsed.
+    /// Files in an older format are backed up and rewritten.
+    async fn read_from(&self, path: &Path) -> Result<Option<Conversation>, AppError> {
+        let content = self.sys.read_to_string(path).await?;
+        let Some(json) = self.encryption.open(&content).await? else {
+            log::warn!("⚠️  Cannot decrypt thread {}", path.display());
+            return Ok(None);
+        };
+
+        let (conversation, version) = match THREAD_SCHEMA.parse::<Conversation>(&json) {
+            Ok(parsed) => parsed,
+            // A file from a newer app version must not be overwritten
+            Err(e @ AppError::Config(_)) => return Err(e),
+            Err(e) => {
+                log::warn!("⚠️  Skipping damaged thread {}: {}", path.display(), e);
+                return Ok(None);
+            }
+        };
+
+        if version < THREAD_SCHEMA.version() {
+            schema::backup_original(self.sys.as_ref(), &THREAD_SCHEMA, path, &content, version)
+                .await?;
+            self.write_to(path, &conversation).await?;
+        }
+        Ok(Some(conversation))
+    }
+
+    async fn save(&self, conversation: &Conversation) -> Result<(), AppError> {
+        let path = self.path(&conversation.id).await?;
+        self.write_to(&path, conversation).await
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Conversation, AppError> {
+        let path = self.path(id).await?;
+        if !self.sys.exists(&path).await {
+            return Err(not_found(id));
+        }
+
+        self.read_from(&path)
+            .await?
+            .ok_or_else(|| AppError::Voice(format!("Konverzaci {} nelze přečíst", id)))
+    }
+
+    /// All threads, pinned first, then most recently updated
+    pub async fn list_all(&self) -> Result<Vec<Conversation>, AppError> {
+        let dir = self.dir().await?;
+        let files = match self.sys.read_dir(&dir).await {
+            Ok(files) => files,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let mut conversations = Vec::new();
+        for path in files
+            .into_iter()
+            .filter(|p| p.extension().is_some_and(|e| e == "json"))
+        {
+            if let Some(conversation) = self.read_from(&path).await? {
+                conversations.push(conversation);
+            }
+        }
+
+        conversations.sort_by_key(|c| (Reverse(c.pinned), Reverse(c.updated_at)));
+        Ok(conversations)
+    }
+
+    pub async fn list(&self) -> Result<Vec<ConversationSummary>, AppError> {
+        Ok(self
+            .list_all()
+            .await?
+            .iter()
+            .map(ConversationSummary::from)
+            .collect())
+    }
+
+    pub async fn create(
+        &self,
+        title: Option<String>,
+        tags: Vec<String>,
+    ) -> Result<Conversation, AppError> {
+        let _guard = self.lock.lock().await;
+        let now = now_millis();
+
+        let conversation = Conversation {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: title.unwrap_or_default(),
+            created_at: now,
+            updated_at: now,
+            tags,
+            pinned: false,
+            messages: Vec::new(),
+        };
+
+        self.save(&conversation).await?;
+        Ok(conversation)
+    }
+
+    /// Load, modify and save a thread; bumps `updated_at`
+    async fn update(
+        &self,
+        id: &str,
+        change: impl FnOnce(&mut Conversation),
+    ) -> Result<Conversation, AppError> {
+        let _guard = self.lock.lock().await;
+
+        let mut conversation = self.get(id).await?;
+        change(&mut conversation);
+        conversation.updated_at = now_millis().max(conversation.updated_at);
+
+        self.save(&conversation).await?;
+        Ok(conversation)
+    }
+
+    pub async fn rename(&self, id: &str, title: &str) -> Result<Conversation, AppError> {
+        let title = title.trim().to_string();
+        self.update(id, |c| c.title = title).await
+    }
+
+    pub async fn set_pinned(&self, id: &str, pinned: bool) -> Result<Conversation, AppError> {
+        self.update(id, |c| c.pinned = pinned).await
+    }
+
+    pub async fn set_tags(&self, id: &str, tags: Vec<String>) -> Result<Conversation, AppError> {
+        self.update(id, |c| c.tags = tags).await
+    }
+
+    /// Append a message; an untitled thread takes its title from the first one
+    pub async fn add_message(
+        &self,
+        id: &str,
+        role: MessageRole,
+        content: String,
+    ) -> Result<Conversation, AppError> {
+        self.update(id, |c| {
+            if c.title.is_empty() {
+                c.title = derive_title(&content);
+            }
+            c.messages.push(message(role, content, now_millis()));
+        })
+        .await
+    }
+
+    /// Write the single-exchange threads of flat entries, replacing the
+    /// threads of entries saved again under the same ID
+    pub async fn record_entries(&self, entries: &[ConversationEntry]) -> Result<(), AppError> {
+        let _guard = self.lock.lock().await;
+
+        for entry in entries {
+            if validate_id(&entry.id).is_err() {
+                log::warn!("⚠️  Skipping entry with unusable ID: {}", entry.id);
+                continue;
+            }
+            self.save(&from_entry(entry.clone())).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<(), AppError> {
+        let _guard = self.lock.lock().await;
+
+        let path = self.path(id).await?;
+        if !self.sys.exists(&path).await {
+            return Err(not_found(id));
+        }
+
+        self.sys.remove_file(&path).await?;
+        schema::remove_backups(self.sys.as_ref(), &THREAD_SCHEMA, &path).await
+    }
+
+    /// Save every readable thread again with the current encryption settings
+    pub async fn rewrite(&self) -> Result<(), AppError> {
+        let _guard = self.lock.lock().await;
+
+        let sealing = self.encryption.is_sealing().await?;
+        for conversation in self.list_all().await? {
+            let path = self.path(&conversation.id).await?;
+            self.write_to(&path, &conversation).await?;
+            // Backups of the old format must not outlive encryption either
+            if sealing {
+                schema::remove_backups(self.sys.as_ref(), &THREAD_SCHEMA, &path).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy a thread into a new one, optionally only up to (and including)
+    /// the given message. The copies get new IDs, so a message ID stays unique
+    /// across threads.
+    pub async fn fork(
+        &self,
+        id: &str,
+        up_to_message: Option<&str>,
+    ) -> Result<Conversation, AppError> {
+        let _guard = self.lock.lock().await;
+        let source = self.get(id).await?;
+
+        let mut messages = source.messages;
+        if let Some(message_id) = up_to_message {
+            let position = messages
+                .iter()
+                .position(|m| m.id == message_id)
+                .ok_or_else(|| AppError::Voice(format!("Zpráva {} neexistuje", message_id)))?;
+            messages.truncate(position + 1);
+        }
+        for message in &mut messages {
+            message.id = uuid::Uuid::new_v4().to_string();
+        }
+
+        let now = now_millis();
+        let fork = Conversation {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: format!("{} (větev)", source.title),
+            created_at: now,
+            updated_at: now,
+            tags: source.tags,
+            pinned: false,
+            messages,
+        };
+
+        self.save(&fork).await?;
+        Ok(fork)
+    }
+}
@@ -0,0 +1,418 @@
+// Offline text-to-speech
+// Engines run the Piper or espeak-ng CLI (override with `PIPER_BIN` /
+// `ESPEAK_NG`), which print audio to stdout. A profile's `output_voice` picks
+// the engine: "piper:<model>", "espeak:<voice>", or "default" for espeak-ng in
+// the profile's language. Long text is synthesized sentence by sentence and
+// streamed to the webview as `tts-chunk` events.
+
+use super::audio::{self, Audio};
+use super::profiles::language_code;
+use super::VoiceSettings;
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::system::SystemOps;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tokio::sync::oneshot;
+
+/// espeak-ng words per minute at speed 1.0
+const ESPEAK_BASE_WPM: f32 = 175.0;
+/// Piper models without a config are usually 22.05 kHz
+const PIPER_DEFAULT_SAMPLE_RATE: u32 = 22_050;
+/// Speed range accepted by both engines
+const MIN_SPEED: f32 = 0.5;
+const MAX_SPEED: f32 = 2.5;
+/// Chunks longer than this are split at commas or spaces
+const MAX_CHUNK_CHARS: usize = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TtsEngineKind {
+    Piper,
+    Espeak,
+}
+
+/// Engine and voice resolved from a profile's `output_voice`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VoiceSpec {
+    pub engine: TtsEngineKind,
+    pub voice: String,
+}
+
+impl VoiceSpec {
+    /// "piper:cs_CZ-jirka-medium", "espeak:cs" or "default". Anything else,
+    /// e.g. a WebKit voice name like "Google čeština" saved by an older
+    /// version, falls back to the language's default voice.
+    pub fn parse(output_voice: &str, language: &str) -> Self {
+        let value = output_voice.trim();
+        if let Some(model) = value.strip_prefix("piper:") {
+            return Self {
+                engine: TtsEngineKind::Piper,
+                voice: model.to_string(),
+            };
+        }
+
+        let voice = match value
+            .strip_prefix("espeak-ng:")
+            .or_else(|| value.strip_prefix("espeak:"))
+        {
+            Some(voice) if !voice.is_empty() && voice != "default" => voice.to_string(),
+            Some(_) => language_code(language),
+            None => {
+                if !value.is_empty() && value != "default" {
+                    log::warn!(
+                        "⚠️  Unknown voice \"{}\", using the default voice for {}",
+                        value,
+                        language
+                    );
+                }
+                language_code(language)
+            }
+        };
+
+        Self {
+            engine: TtsEngineKind::Espeak,
+            voice,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait TtsEngine: Send + Sync {
+    /// Synthesize one piece of text; `speed` 1.0 is the voice's natural rate
+    async fn synthesize(
+        &self,
+        sys: &dyn SystemOps,
+        text: &str,
+        voice: &str,
+        speed: f32,
+    ) -> Result<Audio, AppError>;
+}
+
+fn program(sys: &dyn SystemOps, env: &str, default: &str) -> String {
+    sys.env_var(env)
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| default.to_string())
+}
+
+fn failed(output: &std::process::Output) -> AppError {
+    AppError::Voice(format!(
+        "Syntéza řeči selhala: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    ))
+}
+
+/// Piper neural voices (`.onnx` models with a `.onnx.json` config)
+pub struct Piper;
+
+impl Piper {
+    /// Model from a name in the piper models directory or an absolute path
+    pub async fn model_path(sys: &dyn SystemOps, voice: &str) -> Result<PathBuf, AppError> {
+        let file = if voice.ends_with(".onnx") {
+            voice.to_string()
+        } else {
+            format!("{}.onnx", voice)
+        };
+        let path = PathBuf::from(&file);
+        let path = if path.is_absolute() {
+            path
+        } else {
+            super::get_models_dir(sys)?.join("piper").join(file)
+        };
+
+        if !sys.exists(&path).await {
+            return Err(AppError::Voice(format!(
+                "Hlas Piper nenalezen: {}",
+                path.display()
+            )));
+        }
+        Ok(path)
+    }
+
+    /// Sample rate from the model config
+    async fn sample_rate(sys: &dyn SystemOps, model: &std::path::Path) -> u32 {
+        let mut config = model.as_os_str().to_owned();
+        config.push(".json");
+
+        let Ok(content) = sys.read_to_string(&PathBuf::from(config)).await else {
+            return PIPER_DEFAULT_SAMPLE_RATE;
+        };
+        serde_json::from_str::<serde_json::Value>(&content)
+            .ok()
+            .and_then(|c| c["audio"]["sample_rate"].as_u64())
+            .and_then(|r| u32::try_from(r).ok())
+            .unwrap_or(PIPER_DEFAULT_SAMPLE_RATE)
+    }
+}
+
+#[async_trait::async_trait]
+impl TtsEngine for Piper {
+    async fn synthesize(
+        &self,
+        sys: &dyn SystemOps,
+        text: &str,
+        voice: &str,
+        speed: f32,
+    ) -> Result<Audio, AppError> {
+        let model = Self::model_path(sys, voice).await?;
+        let model_arg = model.to_string_lossy();
+        // Piper's length scale is the inverse of speed
+        let length_scale = format!("{:.3}", 1.0 / speed);
+        let args = [
+            "--model",
+            model_arg.as_ref(),
+            "--length_scale",
+            length_scale.as_str(),
+            "--output_raw",
+        ];
+
+        let output = sys
+            .run_command_with_input(&program(sys, "PIPER_BIN", "piper"), &args, text)
+            .await?;
+        if !output.status.success() {
+            return Err(failed(&output));
+        }
+
+        // Raw output is mono 16-bit little-endian PCM
+        let samples = output
+            .stdout
+            .chunks_exact(2)
+            .map(|b| f32::from(i16::from_le_bytes([b[0], b[1]])) / 32768.0)
+            .collect();
+        Ok(Audio {
+            samples,
+            sample_rate: Self::sample_rate(sys, &model).await,
+        })
+    }
+}
+
+/// espeak-ng formant voices, available for most languages
+pub struct Espeak;
+
+#[async_trait::async_trait]
+impl TtsEngine for Espeak {
+    async fn synthesize(
+        &self,
+        sys: &dyn SystemOps,
+        text: &str,
+        voice: &str,
+        speed: f32,
+    ) -> Result<Audio, AppError> {
+        let wpm = ((ESPEAK_BASE_WPM * speed).round() as u32).to_string();
+        let args = ["-v", voice, "-s", wpm.as_str(), "--stdout", "--stdin"];
+
+        let output = sys
+            .run_command_with_input(&program(sys, "ESPEAK_NG", "espeak-ng"), &args, text)
+            .await?;
+        if !output.status.success() {
+            return Err(failed(&output));
+        }
+
+        audio::decode_wav(&output.stdout)
+    }
+}
+
+pub fn engine(kind: TtsEngineKind) -> Box<dyn TtsEngine> {
+    match kind {
+        TtsEngineKind::Piper => Box::new(Piper),
+        TtsEngineKind::Espeak => Box::new(Espeak),
+    }
+}
+
+/// Speed within what the engines handle; NaN falls back to normal speed
+pub fn clamp_speed(speed: f32) -> f32 {
+    if speed.is_finite() {
+        speed.clamp(MIN_SPEED, MAX_SPEED)
+    } else {
+        1.0
+    }
+}
+
+/// Split text into sentence-sized chunks so playback can start early
+pub fn split_sentences(text: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        current.push(c);
+        let boundary = match c {
+            '.' | '!' | '?' | '…' => chars.peek().is_none_or(|n| n.is_whitespace()),
+            '\n' => true,
+            _ => false,
+        };
+        let too_long = current.chars().count() >= MAX_CHUNK_CHARS
+            && (c == ',' || c == ';' || c.is_whitespace());
+
+        if boundary || too_long {
+            let chunk = current.trim();
+            if !chunk.is_empty() {
+                chunks.push(chunk.to_string());
+            }
+            current.clear();
+        }
+    }
+
+    let rest = current.trim();
+    if !rest.is_empty() {
+        chunks.push(rest.to_string());
+    }
+    chunks
+}
+
+/// Synthesize text with the voice and speed of the language's profile
+pub async fn synthesize(
+    sys: &dyn SystemOps,
+    settings: &VoiceSettings,
+    language: &str,
+    text: &str,
+) -> Result<Audio, AppError> {
+    let profile = settings.profile(language);
+    let spec = VoiceSpec::parse(&profile.output_voice, language);
+    let speed = clamp_speed(profile.output_speed);
+    engine(spec.engine)
+        .synthesize(sys, text, &spec.voice, speed)
+        .await
+}
+
+/// Synthesized audio for the webview
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeechAudio {
+    /// Base64-encoded 16-bit mono WAV
+    pub audio: String,
+    pub sample_rate: u32,
+    pub duration_ms: i64,
+}
+
+impl SpeechAudio {
+    pub fn encode(audio: &Audio) -> Result<Self, AppError> {
+        Ok(Self {
+            audio: BASE64.encode(audio::encode_wav(audio)?),
+            sample_rate: audio.sample_rate,
+            duration_ms: audio.duration_ms(),
+        })
+    }
+}
+
+/// Payload of `tts-chunk`
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeechChunk {
+    pub stream_id: String,
+    pub index: usize,
+    pub text: String,
+    #[serde(flatten)]
+    pub audio: SpeechAudio,
+    /// True for the final chunk
+    pub last: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeechFinished {
+    pub stream_id: String,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeechFailed {
+    pub stream_id: String,
+    pub message: String,
+}
+
+/// Normalize the Markdown and synthesize it chunk by chunk, handing each to
+/// `on_chunk`. Returns false when cancelled.
+pub async fn stream(
+    sys: &Arc<dyn SystemOps>,
+    text: &str,
+    mut cancel: oneshot::Receiver<()>,
+    mut on_chunk: impl FnMut(usize, &str, &Audio, bool),
+) -> Result<bool, AppError> {
+    let settings = super::load_voice_settings(sys).await?;
+    let language = settings.response_language(text);
+    let chunks = super::normalize::prepare(text, &settings, &language);
+
+    for (index, chunk) in chunks.iter().enumerate() {
+        if cancel.try_recv().is_ok() {
+            return Ok(false);
+        }
+        let audio = synthesize(sys.as_ref(), &settings, &language, chunk).await?;
+        on_chunk(index, chunk, &audio, index + 1 == chunks.len());
+    }
+    Ok(true)
+}
+
+/// Start speaking in the background and return the stream ID. Audio arrives
+/// as `tts-chunk` events followed by `tts-finished` or `tts-error`.
+pub async fn speak<R: Runtime>(app: AppHandle<R>, text: String) -> Result<String, AppError> {
+    let stream_id = uuid::Uuid::new_v4().to_string();
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+
+    let state = app.state::<AppState>();
+    state
+        .speech
+        .write()
+        .await
+        .insert(stream_id.clone(), cancel_tx);
+
+    let id = stream_id.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let result = stream(&state.sys, &text, cancel_rx, |index, chunk, audio, last| {
+            match SpeechAudio::encode(audio) {
+                Ok(audio) => {
+                    let payload = SpeechChunk {
+                        stream_id: id.clone(),
+                        index,
+                        text: chunk.to_string(),
+                        audio,
+                        last,
+                    };
+                    if let Err(e) = app.emit("tts-chunk", payload) {
+                        log::warn!("⚠️  Cannot emit tts-chunk: {}", e);
+                    }
+                }
+                Err(e) => log::warn!("⚠️  Cannot encode speech chunk: {}", e),
+            }
+        })
+        .await;
+
+        state.speech.write().await.remove(&id);
+
+        let emitted = match result {
+            Ok(completed) => app.emit(
+                "tts-finished",
+                SpeechFinished {
+                    stream_id: id.clone(),
+                    cancelled: !completed,
+                },
+            ),
+            Err(e) => {
+                log::error!("❌ Speech {} failed: {}", id, e);
+                app.emit(
+                    "tts-error",
+                    SpeechFailed {
+                        stream_id: id.clone(),
+                        message: e.to_string(),
+                    },
+                )
+            }
+        };
+        if let Err(e) = emitted {
+            log::warn!("⚠️  Cannot emit end of speech: {}", e);
+        }
+    });
+
+    Ok(stream_id)
+}
+
+/// Stop a running stream. Returns false when it already finished.
+pub async fn stop(state: &AppState, stream_id: &str) -> bool {
+    let Some(cancel_tx) = state.speech.write().await.remove(stream_id) else {
+        return false;
+    };
+    let _ = cancel_tx.send(());
+    true
+}
@@ -0,0 +1,125 @@
+// Voice activity detection
+// Energy based: audio is cut into short frames, frames louder than the noise
+// floor by a margin count as speech. Short bursts (key clicks) are dropped and
+// short pauses don't end an utterance.
+
+use super::audio::Audio;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VadSettings {
+    /// Split audio into utterances and drop silence before transcription
+    pub enabled: bool,
+    /// Frames quieter than this (dBFS) are always silence
+    pub threshold_db: f32,
+    /// How far above the estimated noise floor speech must be (dB)
+    pub margin_db: f32,
+    /// Shorter bursts are treated as noise
+    pub min_speech_ms: u32,
+    /// A pause this long ends an utterance
+    pub min_silence_ms: u32,
+    /// Audio kept before and after each utterance
+    pub padding_ms: u32,
+}
+
+impl Default for VadSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold_db: -45.0,
+            margin_db: 10.0,
+            min_speech_ms: 250,
+            min_silence_ms: 600,
+            padding_ms: 150,
+        }
+    }
+}
+
+/// Analysis frame length
+const FRAME_MS: u32 = 20;
+/// The adaptive threshold never goes above this, so a recording without any
+/// pause (push-to-talk held only while speaking) isn't taken for noise
+const MAX_THRESHOLD_DB: f32 = -25.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Utterance {
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+fn frame_db(frame: &[f32]) -> f32 {
+    let energy = frame.iter().map(|s| s * s).sum::<f32>() / frame.len().max(1) as f32;
+    10.0 * energy.max(1e-10).log10()
+}
+
+/// Speech regions in the audio, in ms
+pub fn detect(audio: &Audio, settings: &VadSettings) -> Vec<Utterance> {
+    let frame_len = (audio.sample_rate * FRAME_MS / 1000).max(1) as usize;
+    let levels: Vec<f32> = audio.samples.chunks(frame_len).map(frame_db).collect();
+    if levels.is_empty() {
+        return Vec::new();
+    }
+
+    // The quietest tenth of the recording approximates background noise
+    let mut sorted = levels.clone();
+    sorted.sort_by(f32::total_cmp);
+    let noise_floor = sorted[sorted.len() / 10];
+    let threshold = settings
+        .threshold_db
+        .max((noise_floor + settings.margin_db).min(MAX_THRESHOLD_DB));
+
+    let frames = |ms: u32| (ms / FRAME_MS).max(1) as usize;
+    let min_speech = frames(settings.min_speech_ms);
+    let min_silence = frames(settings.min_silence_ms);
+
+    // (first, last) speech frame of each region
+    let mut regions: Vec<(usize, usize)> = Vec::new();
+    for (index, level) in levels.iter().enumerate() {
+        if *level < threshold {
+            continue;
+        }
+        match regions.last_mut() {
+            Some((_, last)) if index - *last <= min_silence => *last = index,
+            _ => regions.push((index, index)),
+        }
+    }
+
+    let duration = audio.duration_ms();
+    let padding = i64::from(settings.padding_ms);
+    let mut utterances: Vec<Utterance> = Vec::new();
+    for (first, last) in regions {
+        if last + 1 - first < min_speech {
+            continue;
+        }
+
+        let start = (first as i64 * i64::from(FRAME_MS) - padding).max(0);
+        let end = ((last as i64 + 1) * i64::from(FRAME_MS) + padding).min(duration);
+        match utterances.last_mut() {
+            // Padding can make neighbours touch
+            Some(previous) if start <= previous.end_ms => previous.end_ms = end,
+            _ => utterances.push(Utterance {
+                start_ms: start,
+                end_ms: end,
+            }),
+        }
+    }
+    utterances
+}
+
+/// Cut the audio into utterances; silence is dropped
+pub fn split(audio: &Audio, settings: &VadSettings) -> Vec<(Utterance, Audio)> {
+    let rate = i64::from(audio.sample_rate);
+    detect(audio, settings)
+        .into_iter()
+        .map(|u| {
+            let start = (u.start_ms * rate / 1000) as usize;
+            let end = ((u.end_ms * rate / 1000) as usize).min(audio.samples.len());
+            let clip = Audio {
+                samples: audio.samples[start..end].to_vec(),
+                sample_rate: audio.sample_rate,
+            };
+            (u, clip)
+        })
+        .collect()
+}
@@ -0,0 +1,310 @@
+// Voice settings validation
+// `save_voice_settings` runs settings through `validate` first. Values with an
+// obvious fix are repaired: speeds are clamped like `tts::clamp_speed` does at
+// synthesis time and language tags get their canonical BCP-47 form
+// ("cs_cz" -> "cs-CZ"). Everything else is reported per field, and the list
+// travels to the webview as JSON inside `AppError::Voice`.
+//
+// Voices and models are checked against the installed catalog only when a
+// profile changes them: the default model isn't there before the first
+// download, and a model removed later must not block saving other settings.
+// For the same reason the catalog is only loaded when something changed.
+
+use super::catalog::Catalog;
+use super::profiles::LanguageProfile;
+use super::{ptt, tts, vad::VadSettings, VoiceSettings};
+use crate::error::AppError;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Accepted VAD thresholds in dB
+const VAD_THRESHOLD_DB: (f32, f32) = (-100.0, 0.0);
+const VAD_MARGIN_DB: (f32, f32) = (0.0, 40.0);
+/// Longest accepted VAD duration
+const VAD_MAX_MS: u32 = 10_000;
+
+/// One invalid value; `field` is the path in the settings object, e.g.
+/// "history_limit" or "profiles.en-US"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// `AppError::Voice` carrying the field errors as a JSON array
+pub fn into_error(errors: Vec<FieldError>) -> AppError {
+    match serde_json::to_string(&errors) {
+        Ok(json) => AppError::Voice(json),
+        Err(e) => AppError::Json(e),
+    }
+}
+
+/// Whether any profile changes its voice or model from the saved settings,
+/// i.e. whether `validate` needs the installed catalog at all
+pub fn needs_catalog(settings: &VoiceSettings, current: &VoiceSettings) -> bool {
+    settings.profiles.iter().any(|(tag, profile)| {
+        let canonical = normalize_language_tag(tag).unwrap_or_else(|| tag.clone());
+        let saved = current
+            .profiles
+            .get(&canonical)
+            .cloned()
+            .unwrap_or_default();
+        profile.output_voice != saved.output_voice || profile.stt_model != saved.stt_model
+    })
+}
+
+/// Settings as they will be saved, or every field that can't be fixed.
+/// `current` are the saved settings, `catalog` the installed voices and models.
+pub fn validate(
+    settings: &VoiceSettings,
+    current: &VoiceSettings,
+    catalog: &Catalog,
+) -> Result<VoiceSettings, Vec<FieldError>> {
+    let mut errors = Vec::new();
+    let mut valid = settings.clone();
+
+    match normalize_language_tag(&settings.input_language) {
+        Some(tag) => valid.input_language = tag,
+        None => errors.push(invalid_tag("input_language", &settings.input_language)),
+    }
+
+    // The limit is the number of entries kept, 0 would wipe the history
+    if settings.history_limit == 0 {
+        errors.push(FieldError::new("history_limit", "Musí být alespoň 1"));
+    }
+
+    let mut profiles = BTreeMap::new();
+    for (tag, profile) in &settings.profiles {
+        let field = format!("profiles.{}", tag);
+        let Some(canonical) = normalize_language_tag(tag) else {
+            errors.push(invalid_tag(&field, tag));
+            continue;
+        };
+
+        let saved = current
+            .profiles
+            .get(&canonical)
+            .cloned()
+            .unwrap_or_default();
+        check_profile(&field, profile, &saved, catalog, &mut errors);
+
+        let mut profile = profile.clone();
+        profile.output_speed = tts::clamp_speed(profile.output_speed);
+        if profiles.insert(canonical.clone(), profile).is_some() {
+            errors.push(FieldError::new(
+                field,
+                format!("Profil pro {} je uvedený vícekrát", canonical),
+            ));
+        }
+    }
+    valid.profiles = profiles;
+
+    if settings.push_to_talk.enabled {
+        if let Err(e) = ptt::parse_shortcut(&settings.push_to_talk.shortcut) {
+            let message = match e {
+                AppError::Voice(message) => message,
+                other => other.to_string(),
+            };
+            errors.push(FieldError::new("push_to_talk.shortcut", message));
+        }
+    }
+
+    if settings.vad.enabled {
+        check_vad(&settings.vad, &mut errors);
+    }
+
+    if errors.is_empty() {
+        Ok(valid)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Voice and model of a profile, when they differ from the saved ones
+fn check_profile(
+    field: &str,
+    profile: &LanguageProfile,
+    saved: &LanguageProfile,
+    catalog: &Catalog,
+    errors: &mut Vec<FieldError>,
+) {
+    if profile.output_voice != saved.output_voice && !catalog.has_voice(&profile.output_voice) {
+        errors.push(FieldError::new(
+            format!("{}.output_voice", field),
+            format!("Hlas \"{}\" není nainstalovaný", profile.output_voice),
+        ));
+    }
+    if profile.stt_model != saved.stt_model && !catalog.has_stt_model(&profile.stt_model) {
+        errors.push(FieldError::new(
+            format!("{}.stt_model", field),
+            format!("Model \"{}\" není nainstalovaný", profile.stt_model),
+        ));
+    }
+}
+
+fn check_vad(vad: &VadSettings, errors: &mut Vec<FieldError>) {
+    for (field, value, (min, max)) in [
+        ("vad.threshold_db", vad.threshold_db, VAD_THRESHOLD_DB),
+        ("vad.margin_db", vad.margin_db, VAD_MARGIN_DB),
+    ] {
+        // NaN fails the range check as well
+        if !(min..=max).contains(&value) {
+            errors.push(FieldError::new(
+                field,
+                format!("Musí být mezi {} a {} dB", min, max),
+            ));
+        }
+    }
+
+    for (field, value) in [
+        ("vad.min_speech_ms", vad.min_speech_ms),
+        ("vad.min_silence_ms", vad.min_silence_ms),
+        ("vad.padding_ms", vad.padding_ms),
+    ] {
+        if value > VAD_MAX_MS {
+            errors.push(FieldError::new(
+                field,
+                format!("Může být nejvýše {} ms", VAD_MAX_MS),
+            ));
+        }
+    }
+}
+
+fn invalid_tag(field: &str, tag: &str) -> FieldError {
+    FieldError::new(
+        field,
+        format!("\"{}\" není platný jazykový tag BCP-47 (např. cs-CZ)", tag),
+    )
+}
+
+fn is_alpha(s: &str) -> bool {
+    s.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+fn is_digit(s: &str) -> bool {
+    s.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_alphanumeric(s: &str) -> bool {
+    s.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Canonical form of a well-formed BCP-47 (RFC 5646) tag, `None` when the
+/// tag isn't well-formed. Underscores are accepted as separators since
+/// POSIX locales ("cs_CZ") are a common slip.
+pub fn normalize_language_tag(tag: &str) -> Option<String> {
+    let subtags: Vec<String> = tag
+        .trim()
+        .split(['-', '_'])
+        .map(|s| s.to_ascii_lowercase())
+        .collect();
+    if subtags
+        .iter()
+        .any(|s| s.is_empty() || s.len() > 8 || !is_alphanumeric(s))
+    {
+        return None;
+    }
+
+    let mut out: Vec<String> = Vec::new();
+    let mut rest = subtags.as_slice();
+
+    // Tags may consist of a private use part only ("x-klingon")
+    if rest[0] != "x" {
+        // language, optionally followed by up to three extlang subtags
+        let language = &rest[0];
+        if !is_alpha(language) || !(2..=8).contains(&language.len()) {
+            return None;
+        }
+        out.push(language.clone());
+        rest = &rest[1..];
+        if language.len() <= 3 {
+            let mut extlangs = 0;
+            while let Some(sub) = rest.first() {
+                if extlangs == 3 || sub.len() != 3 || !is_alpha(sub) {
+                    break;
+                }
+                out.push(sub.clone());
+                rest = &rest[1..];
+                extlangs += 1;
+            }
+        }
+
+        // script: Latn
+        if let Some(sub) = rest.first() {
+            if sub.len() == 4 && is_alpha(sub) {
+                let mut script = sub.clone();
+                script[..1].make_ascii_uppercase();
+                out.push(script);
+                rest = &rest[1..];
+            }
+        }
+
+        // region: CZ or 419
+        if let Some(sub) = rest.first() {
+            if sub.len() == 2 && is_alpha(sub) || sub.len() == 3 && is_digit(sub) {
+                out.push(sub.to_ascii_uppercase());
+                rest = &rest[1..];
+            }
+        }
+
+        // variants: 5-8 characters, or 4 starting with a digit
+        while let Some(sub) = rest.first() {
+            let variant = (5..=8).contains(&sub.len())
+                || sub.len() == 4 && sub.starts_with(|c: char| c.is_ascii_digit());
+            if !variant {
+                break;
+            }
+            if out.contains(sub) {
+                return None;
+            }
+            out.push(sub.clone());
+            rest = &rest[1..];
+        }
+
+        // extensions: a singleton other than x with 2-8 character subtags
+        let mut singletons = Vec::new();
+        while let Some(sub) = rest.first() {
+            if sub.len() != 1 || sub == "x" {
+                break;
+            }
+            if singletons.contains(sub) {
+                return None;
+            }
+            singletons.push(sub.clone());
+            out.push(sub.clone());
+            rest = &rest[1..];
+
+            let before = rest.len();
+            while let Some(part) = rest.first() {
+                if part.len() < 2 {
+                    break;
+                }
+                out.push(part.clone());
+                rest = &rest[1..];
+            }
+            if rest.len() == before {
+                return None;
+            }
+        }
+    }
+
+    // private use: x followed by 1-8 character subtags
+    if let Some(sub) = rest.first() {
+        if sub != "x" || rest.len() < 2 {
+            return None;
+        }
+        out.extend(rest.iter().cloned());
+        rest = &[];
+    }
+
+    rest.is_empty().then(|| out.join("-"))
+}
@@ -334,19 +334,13 @@
     async function loadVoiceSettings() {
         if (!window.voiceManager) return;
 
-        // Load voices into dropdown
+        // Load installed voices into dropdown; the backend only accepts these
         const voiceSelect = document.getElementById('outputVoice');
         if (voiceSelect) {
-            const voices = window.voiceManager.getVoices();
-
-            // Wait for voices to load (sometimes delayed)
-            if (voices.length === 0) {
-                window.speechSynthesis.addEventListener('voiceschanged', () => {
-                    const updatedVoices = window.voiceManager.getVoices();
-                    populateVoiceOptions(voiceSelect, updatedVoices);
-                }, { once: true });
-            } else {
-                populateVoiceOptions(voiceSelect, voices);
+            try {
+                populateVoiceOptions(voiceSelect, await invoke('list_tts_voices'));
+            } catch (error) {
+                console.error('Failed to load voices:', error);
             }
         }
 
@@ -377,8 +371,8 @@
         // Add voice options
         voices.forEach(voice => {
             const option = document.createElement('option');
-            option.value = voice.name;
-            option.textContent = `${voice.name} (${voice.lang})`;
+            option.value = voice.id;
+            option.textContent = `${voice.name} (${voice.language})`;
             selectElement.appendChild(option);
         });
 
@@ -399,7 +393,33 @@
             history_limit: parseInt(document.getElementById('historyLimit').value)
         };
 
-        await window.voiceManager.updateSettings(settings);
+        const invalid = await window.voiceManager.updateSettings(settings);
+        markInvalidFields(invalid);
+    }
+
+    // Form inputs for paths reported by settings validation
+    const SETTINGS_FIELD_IDS = {
+        input_language: 'inputLanguage',
+        output_voice: 'outputVoice',
+        output_speed: 'outputSpeed',
+        history_limit: 'historyLimit'
+    };
+
+    function markInvalidFields(fields) {
+        document.querySelectorAll('.form-control.invalid').forEach(el => {
+            el.classList.remove('invalid');
+            el.title = '';
+        });
+
+        fields.forEach(({ field, message }) => {
+            // "profiles.cs-CZ.output_voice" belongs to the voice dropdown
+            const id = SETTINGS_FIELD_IDS[field] || SETTINGS_FIELD_IDS[field.split('.').pop()];
+            const element = id && document.getElementById(id);
+            if (element) {
+                element.classList.add('invalid');
+                element.title = message;
+            }
+        });
     }
 
     async function clearVoiceHistory() {
@@ -264,21 +264,31 @@
         }
 
         // Update voice settings
+        // Returns the invalid fields of a rejected save, empty otherwise
         async updateSettings(newSettings) {
             try {
-                await invoke('save_voice_settings', { settings: newSettings });
-                this.settings = newSettings;
+                // Backend returns the settings as saved (clamped speeds, normalized tags)
+                const saved = await invoke('save_voice_settings', { settings: newSettings });
+                this.settings = saved;
 
                 // Update recognition language
                 if (this.recognition) {
-                    this.recognition.lang = newSettings.input_language;
+                    this.recognition.lang = saved.input_language;
                 }
 
                 log('⚙️ Voice settings updated');
                 showNotification('Nastavení uloženo', 'success');
+                return [];
             } catch (error) {
                 console.error('Failed to save voice settings:', error);
-                showNotification('Nepodařilo se uložit nastavení', 'error');
+                const fields = parseFieldErrors(error);
+                showNotification(
+                    fields
+                        ? 'Neplatné nastavení: ' + fields.map(f => `${f.field}: ${f.message}`).join(', ')
+                        : 'Nepodařilo se uložit nastavení',
+                    'error'
+                );
+                return fields || [];
             }
         }
 
@@ -318,6 +328,19 @@
         }
     }
 
+    // Field errors from settings validation: a JSON array after the error prefix
+    function parseFieldErrors(error) {
+        const message = String(error);
+        const start = message.indexOf('[');
+        if (start === -1) return null;
+        try {
+            const fields = JSON.parse(message.slice(start));
+            return Array.isArray(fields) ? fields : null;
+        } catch (e) {
+            return null;
+        }
+    }
+
     function showNotification(message, type = 'info') {
         // Simple notification implementation
         const notification = document.createElement('div');